@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ASTNode {
     pub node_kind: ASTNodeKind,
@@ -31,20 +33,24 @@ pub enum Visibility {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Item {
     Function {
+        attributes: Vec<Attribute>,
         name: String,
         params: Vec<FunctionParam>,
         return_type: Option<Types>,
         body: Option<Box<Expression>>,
     },
     Struct {
+        attributes: Vec<Attribute>,
         name: String,
         fields: Vec<StructField>,
     },
     Enumeration {
+        attributes: Vec<Attribute>,
         name: String,
         items: Vec<EnumItem>,
     },
     Union {
+        attributes: Vec<Attribute>,
         name: String,
         fields: Vec<StructField>,
     },
@@ -53,6 +59,7 @@ pub enum Item {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EnumItem {
     StructItem {
+        attributes: Vec<Attribute>,
         visibility: Visibility,
         name: String,
         fields: Vec<StructField>,
@@ -60,6 +67,7 @@ pub enum EnumItem {
 
     // TODO
     TupleItem {
+        attributes: Vec<Attribute>,
         visibility: Visibility,
         name: String,
     },
@@ -67,6 +75,7 @@ pub enum EnumItem {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StructField {
+    pub attributes: Vec<Attribute>,
     pub visibility: Visibility,
     pub name: String,
     pub field_type: Types,
@@ -143,8 +152,12 @@ pub enum LoopExpr {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Statement {
     Semicolon,
-    Expression(Box<Expression>),
+    Expression {
+        attributes: Vec<Attribute>,
+        expression: Box<Expression>,
+    },
     Let {
+        attributes: Vec<Attribute>,
         name: Pattern,
         variable_type: Option<Types>,
         initializer: Option<Box<Expression>>,
@@ -176,10 +189,41 @@ pub enum Types {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FunctionParam {
+    pub attributes: Vec<Attribute>,
     pub pattern: Pattern,
     pub param_type: Types,
 }
 
+// #[test] #[inline(always)] #[cfg(target_os = "linux")] #![deprecated = "..."]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Attribute {
+    pub style: AttributeStyle,
+    pub path: Path,
+    pub args: AttributeArgs,
+    pub span: Range<usize>, // # から ] まで
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AttributeStyle {
+    Outer, // #[...] 直後の要素に付く
+    Inner, // #![...] 囲んでいる要素に付く
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AttributeArgs {
+    Empty,                   // #[test]
+    List(Vec<AttributeArg>), // #[cfg(test)]
+    Value(Literal),          // #[deprecated = "..."]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AttributeArg {
+    Literal(Literal),
+    Path(Path),
+    KeyValue { key: Path, value: Literal }, // target_os = "linux"
+    List { path: Path, args: Vec<AttributeArg> }, // all(unix, test)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ArrayElements {
     List(Vec<Expression>),
@@ -7,6 +7,7 @@ pub struct CommandOptionError {
 #[derive(Debug)]
 pub(crate) enum OptionErrorKind {
     HelpRequested,
+    UnknownCommand,
     UnknownOption,
     InvalidOptionArgs,
 }
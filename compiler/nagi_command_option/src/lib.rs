@@ -1,18 +1,28 @@
 use errors::{CommandOptionError, OptionErrorKind};
 use options::{
-    emit::EmitOption, help::HelpOption, log_level::LogLevelOption, target::TargetOption,
+    emit::EmitOption, encoding::EncodingOption, fix::FixOption, from::FromOption, help::HelpOption,
+    keywords::KeywordsOption, lint::LintOption, log_level::LogLevelOption,
+    merge_literals::MergeLiteralsOption, out_dir::OutDirOption, target::TargetOption,
 };
-use std::{collections::HashMap, env, fmt::Debug, iter::from_fn, path::PathBuf};
+use std::{collections::HashMap, env, fmt::Debug, iter::from_fn, iter::Peekable, path::PathBuf};
 
 pub mod errors;
 mod options;
 
 #[derive(Debug)]
 pub struct NagiCommandOption {
+    pub command: NagiCommand,
     pub target_dir: PathBuf,
+    pub output_dir: PathBuf,
     pub output_file_name: PathBuf,
     pub log_level: LogLevel,
     pub output_file_type: OutputFileType,
+    pub source_encoding: SourceEncoding,
+    pub lint_levels: Vec<(String, LintLevel)>,
+    pub fix: bool,
+    pub keyword_locale: Option<KeywordLocale>, // fmtでは変換先, それ以外でNoneの場合はファイルの指定に従う
+    pub keyword_source: Option<KeywordLocale>, // fmtでファイルに指定がない場合の変換元の言語
+    pub merge_literals: bool,                  // grammar fmtで隣接するリテラルを連結する
 }
 
 impl NagiCommandOption {
@@ -29,10 +39,18 @@ impl NagiCommandOption {
 impl Default for NagiCommandOption {
     fn default() -> Self {
         Self {
+            command: NagiCommand::Build,
             target_dir: PathBuf::from("./src"),
+            output_dir: PathBuf::from("./out"),
             output_file_name: PathBuf::from("a"),
             log_level: LogLevel::Normal,
             output_file_type: OutputFileType::Binary,
+            source_encoding: SourceEncoding::Utf8,
+            lint_levels: vec![],
+            fix: false,
+            keyword_locale: None,
+            keyword_source: None,
+            merge_literals: false,
         }
     }
 }
@@ -43,6 +61,13 @@ fn parse_command_option(args: &[String]) -> Result<NagiCommandOption, CommandOpt
         make_option(LogLevelOption {}),
         make_option(EmitOption {}),
         make_option(TargetOption {}),
+        make_option(OutDirOption {}),
+        make_option(EncodingOption {}),
+        make_option(LintOption {}),
+        make_option(FixOption {}),
+        make_option(KeywordsOption {}),
+        make_option(FromOption {}),
+        make_option(MergeLiteralsOption {}),
     ]);
     let options_list: Vec<&dyn CommandOption> = options.values().map(|c| &**c).collect();
     let short_options: HashMap<&str, &Box<dyn CommandOption>> = HashMap::from_iter(
@@ -53,6 +78,22 @@ fn parse_command_option(args: &[String]) -> Result<NagiCommandOption, CommandOpt
 
     let mut args = args.iter().peekable();
     let mut nagi_command_option = NagiCommandOption::default();
+
+    // nagi fmt ... のようにサブコマンドは先頭にのみ置ける
+    if let Some(command) = args.next_if(|arg| !arg.starts_with("-")) {
+        nagi_command_option.command = match command.as_str() {
+            "build" => NagiCommand::Build,
+            "fmt" => NagiCommand::Fmt,
+            "grammar" => parse_grammar_command(&mut args, &options_list)?,
+            _ => {
+                return Err(CommandOptionError {
+                    kind: OptionErrorKind::UnknownCommand,
+                    message: HelpOption::help(&options_list),
+                });
+            }
+        };
+    }
+
     while let Some(arg) = args.next() {
         if !arg.starts_with("-") {
             return Err(CommandOptionError {
@@ -61,11 +102,19 @@ fn parse_command_option(args: &[String]) -> Result<NagiCommandOption, CommandOpt
             });
         }
 
-        let option_args: Vec<&str> = from_fn(|| {
-            args.next_if(|arg| !arg.starts_with("-"))
-                .map(|f| f.as_str())
-        })
-        .collect();
+        // --keywords=ja は --keywords ja と同じ
+        let (arg, value) = match arg.split_once('=') {
+            Some((arg, value)) if arg.starts_with("--") => (arg, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+
+        let option_args: Vec<&str> = value
+            .into_iter()
+            .chain(from_fn(|| {
+                args.next_if(|arg| !arg.starts_with("-"))
+                    .map(|f| f.as_str())
+            }))
+            .collect();
 
         let option = if let Some(option) = arg.strip_prefix("--") {
             options.get(option).map(|o| &**o)
@@ -93,6 +142,60 @@ fn parse_command_option(args: &[String]) -> Result<NagiCommandOption, CommandOpt
     Ok(nagi_command_option)
 }
 
+// nagi grammar check FILE
+// nagi grammar test GRAMMAR INPUT
+// nagi grammar fmt FILE [--merge-literals]
+// nagi grammar export FORMAT FILE
+fn parse_grammar_command<'a, I>(
+    args: &mut Peekable<I>,
+    options: &[&dyn CommandOption],
+) -> Result<NagiCommand, CommandOptionError>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let mut positional = || args.next_if(|arg| !arg.starts_with("-"));
+    let command = match (positional().map(|s| s.as_str()), positional()) {
+        (Some("check"), Some(file)) => GrammarCommand::Check {
+            file: PathBuf::from(file),
+        },
+        (Some("fmt"), Some(file)) => GrammarCommand::Fmt {
+            file: PathBuf::from(file),
+        },
+        (Some("export"), Some(format)) => {
+            let Some(file) = positional() else {
+                return Err(CommandOptionError {
+                    kind: OptionErrorKind::UnknownCommand,
+                    message: HelpOption::help(options),
+                });
+            };
+            GrammarCommand::Export {
+                format: format.clone(),
+                file: PathBuf::from(file),
+            }
+        }
+        (Some("test"), Some(grammar)) => {
+            let Some(input) = positional() else {
+                return Err(CommandOptionError {
+                    kind: OptionErrorKind::UnknownCommand,
+                    message: HelpOption::help(options),
+                });
+            };
+            GrammarCommand::Test {
+                grammar: PathBuf::from(grammar),
+                input: PathBuf::from(input),
+            }
+        }
+        _ => {
+            return Err(CommandOptionError {
+                kind: OptionErrorKind::UnknownCommand,
+                message: HelpOption::help(options),
+            });
+        }
+    };
+
+    Ok(NagiCommand::Grammar(command))
+}
+
 fn parse_option_args(
     nagi_command_option: &mut NagiCommandOption,
     option: &dyn CommandOption,
@@ -123,6 +226,21 @@ fn make_option(option: impl CommandOption + 'static) -> (String, Box<dyn Command
     (option.option().to_string(), Box::new(option))
 }
 
+#[derive(Debug)]
+pub enum NagiCommand {
+    Build,
+    Fmt,                     // キーワードの言語の変換など, ソースコードを書き換える
+    Grammar(GrammarCommand), // EBNFの文法ファイルを扱う
+}
+
+#[derive(Debug)]
+pub enum GrammarCommand {
+    Check { file: PathBuf },                   // 文法の検査とLL(1)の衝突の報告
+    Test { grammar: PathBuf, input: PathBuf }, // 文法で入力を構文解析し, 構文木を表示する
+    Fmt { file: PathBuf },                     // 正規化した文法を表示する
+    Export { format: String, file: PathBuf },  // 他の形式(antlr, lark, w3c)に変換して表示する
+}
+
 #[derive(Debug)]
 pub enum LogLevel {
     Minimal,
@@ -136,6 +254,27 @@ pub enum OutputFileType {
     Binary,
     Object,
     AST,
+    Utf8Source, // UTF-8に変換したソースを出力先のディレクトリに書き出す
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug)]
+pub enum SourceEncoding {
+    Utf8,
+    ShiftJis,
+    EucJp,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum KeywordLocale {
+    English,
+    Japanese,
 }
 
 pub(crate) trait CommandOption {
@@ -1,4 +1,11 @@
 pub(crate) mod emit;
+pub(crate) mod encoding;
+pub(crate) mod fix;
+pub(crate) mod from;
 pub(crate) mod help;
+pub(crate) mod keywords;
+pub(crate) mod lint;
 pub(crate) mod log_level;
+pub(crate) mod merge_literals;
+pub(crate) mod out_dir;
 pub(crate) mod target;
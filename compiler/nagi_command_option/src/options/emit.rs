@@ -28,6 +28,7 @@ impl CommandOption for EmitOption {
             "bin" => OutputFileType::Binary,
             "obj" => OutputFileType::Object,
             "ast" => OutputFileType::AST,
+            "utf8" => OutputFileType::Utf8Source,
             _ => return Err(OptionErrorKind::InvalidOptionArgs),
         };
 
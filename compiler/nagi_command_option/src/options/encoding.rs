@@ -0,0 +1,36 @@
+use crate::{CommandOption, NagiCommandOption, OptionErrorKind, SourceEncoding};
+
+pub(crate) struct EncodingOption;
+
+impl CommandOption for EncodingOption {
+    fn help(&self) -> &str {
+        "ソースファイルの文字コードを指定します"
+    }
+
+    fn option(&self) -> &str {
+        "encoding"
+    }
+
+    fn help_option_args(&self) -> Vec<&str> {
+        vec!["ENCODING"]
+    }
+
+    fn parse_option_args(
+        &self,
+        args: &[&str],
+        nagi_command_option: &mut NagiCommandOption,
+    ) -> Result<(), OptionErrorKind> {
+        let Some(&arg) = args.first() else {
+            unreachable!();
+        };
+
+        nagi_command_option.source_encoding = match arg {
+            "utf-8" | "utf8" => SourceEncoding::Utf8,
+            "shift_jis" | "sjis" => SourceEncoding::ShiftJis,
+            "euc-jp" | "eucjp" => SourceEncoding::EucJp,
+            _ => return Err(OptionErrorKind::InvalidOptionArgs),
+        };
+
+        Ok(())
+    }
+}
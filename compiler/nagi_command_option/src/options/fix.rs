@@ -0,0 +1,23 @@
+use crate::{CommandOption, NagiCommandOption, OptionErrorKind};
+
+pub(crate) struct FixOption;
+
+impl CommandOption for FixOption {
+    fn help(&self) -> &str {
+        "自動修正できるリントを修正します"
+    }
+
+    fn option(&self) -> &str {
+        "fix"
+    }
+
+    fn parse_option_args(
+        &self,
+        _: &[&str],
+        nagi_command_option: &mut NagiCommandOption,
+    ) -> Result<(), OptionErrorKind> {
+        nagi_command_option.fix = true;
+
+        Ok(())
+    }
+}
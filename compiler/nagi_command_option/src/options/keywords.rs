@@ -0,0 +1,35 @@
+use crate::{CommandOption, KeywordLocale, NagiCommandOption, OptionErrorKind};
+
+pub(crate) struct KeywordsOption;
+
+impl CommandOption for KeywordsOption {
+    fn help(&self) -> &str {
+        "fmtでは変換先, それ以外ではファイルに指定がない場合のキーワードの言語(en, ja)を指定します"
+    }
+
+    fn option(&self) -> &str {
+        "keywords"
+    }
+
+    fn help_option_args(&self) -> Vec<&str> {
+        vec!["LANGUAGE"]
+    }
+
+    fn parse_option_args(
+        &self,
+        args: &[&str],
+        nagi_command_option: &mut NagiCommandOption,
+    ) -> Result<(), OptionErrorKind> {
+        let Some(&arg) = args.first() else {
+            unreachable!();
+        };
+
+        nagi_command_option.keyword_locale = match arg {
+            "en" => Some(KeywordLocale::English),
+            "ja" => Some(KeywordLocale::Japanese),
+            _ => return Err(OptionErrorKind::InvalidOptionArgs),
+        };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{CommandOption, LintLevel, NagiCommandOption, OptionErrorKind};
+
+pub(crate) struct LintOption;
+
+impl CommandOption for LintOption {
+    fn help(&self) -> &str {
+        "リントの報告段階(allow, warn, deny)を指定します"
+    }
+
+    fn option(&self) -> &str {
+        "lint"
+    }
+
+    fn help_option_args(&self) -> Vec<&str> {
+        vec!["LINT", "LEVEL"]
+    }
+
+    fn parse_option_args(
+        &self,
+        args: &[&str],
+        nagi_command_option: &mut NagiCommandOption,
+    ) -> Result<(), OptionErrorKind> {
+        let [lint, level] = args else {
+            unreachable!();
+        };
+
+        let level = match *level {
+            "allow" => LintLevel::Allow,
+            "warn" => LintLevel::Warn,
+            "deny" => LintLevel::Deny,
+            _ => return Err(OptionErrorKind::InvalidOptionArgs),
+        };
+
+        nagi_command_option
+            .lint_levels
+            .push((lint.to_string(), level));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use crate::{CommandOption, NagiCommandOption, OptionErrorKind};
+
+pub(crate) struct MergeLiteralsOption;
+
+impl CommandOption for MergeLiteralsOption {
+    fn help(&self) -> &str {
+        "grammar fmtで隣接するリテラルを連結します"
+    }
+
+    fn option(&self) -> &str {
+        "merge-literals"
+    }
+
+    fn parse_option_args(
+        &self,
+        _: &[&str],
+        nagi_command_option: &mut NagiCommandOption,
+    ) -> Result<(), OptionErrorKind> {
+        nagi_command_option.merge_literals = true;
+
+        Ok(())
+    }
+}
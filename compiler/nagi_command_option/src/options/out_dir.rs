@@ -0,0 +1,32 @@
+use crate::{CommandOption, NagiCommandOption, OptionErrorKind};
+use std::path::PathBuf;
+
+pub(crate) struct OutDirOption;
+
+impl CommandOption for OutDirOption {
+    fn help(&self) -> &str {
+        "出力先のディレクトリを指定します"
+    }
+
+    fn option(&self) -> &str {
+        "out-dir"
+    }
+
+    fn help_option_args(&self) -> Vec<&str> {
+        vec!["DIR"]
+    }
+
+    fn parse_option_args(
+        &self,
+        args: &[&str],
+        nagi_command_option: &mut NagiCommandOption,
+    ) -> Result<(), OptionErrorKind> {
+        let Some(&arg) = args.first() else {
+            unreachable!();
+        };
+
+        nagi_command_option.output_dir = PathBuf::from(arg);
+
+        Ok(())
+    }
+}
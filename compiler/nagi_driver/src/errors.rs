@@ -1,12 +1,31 @@
-use std::{error::Error, fmt::Display, io};
+use std::{error::Error, fmt::Display, io, path::PathBuf};
 
+use crate::source_loader::SourceLoadError;
 use nagi_command_option::errors::CommandOptionError;
+use nagi_lexer::{errors::TokenizeError, whitespace::WhitespaceDiagnostic};
 
 #[derive(Debug)]
 pub(crate) enum CompileError {
     IO(io::Error),
     WalkDir(walkdir::Error),
     CommandOption(CommandOptionError),
+    SourceLoad(SourceLoadError),
+    Tokenize(TokenizeError),
+    UnknownLint(String),
+    OutputOverwritesSource(PathBuf), // 出力先が入力のファイルと同じ
+    MissingFormatTarget,             // fmtで変換先の言語が指定されていない
+    Lint {
+        path: PathBuf,
+        diagnostics: Vec<WhitespaceDiagnostic>,
+    },
+    TokenStreamParse {
+        path: PathBuf,
+        message: String, // エラー位置の行を含むメッセージ
+    },
+    Grammar {
+        path: PathBuf,
+        message: String,
+    },
 }
 
 impl Error for CompileError {}
@@ -17,6 +36,31 @@ impl Display for CompileError {
             CompileError::IO(e) => write!(f, "{e}"),
             CompileError::WalkDir(e) => write!(f, "{e}"),
             CompileError::CommandOption(e) => write!(f, "{}", e.message),
+            CompileError::SourceLoad(e) => write!(f, "{e}"),
+            CompileError::Tokenize(e) => write!(f, "{e}"),
+            CompileError::UnknownLint(name) => write!(f, "Unknown lint: {name}"),
+            CompileError::OutputOverwritesSource(path) => write!(
+                f,
+                "Output would overwrite the source file: {}",
+                path.display()
+            ),
+            CompileError::MissingFormatTarget => {
+                write!(
+                    f,
+                    "No target keyword language for fmt, specify --keywords en|ja"
+                )
+            }
+            CompileError::Lint { path, diagnostics } => {
+                let messages: Vec<_> = diagnostics
+                    .iter()
+                    .map(|d| format!("{}: {d}", path.display()))
+                    .collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            CompileError::TokenStreamParse { path, message }
+            | CompileError::Grammar { path, message } => {
+                write!(f, "{}: {message}", path.display())
+            }
         }
     }
 }
@@ -38,3 +82,15 @@ impl From<walkdir::Error> for CompileError {
         CompileError::WalkDir(value)
     }
 }
+
+impl From<SourceLoadError> for CompileError {
+    fn from(value: SourceLoadError) -> Self {
+        CompileError::SourceLoad(value)
+    }
+}
+
+impl From<TokenizeError> for CompileError {
+    fn from(value: TokenizeError) -> Self {
+        CompileError::Tokenize(value)
+    }
+}
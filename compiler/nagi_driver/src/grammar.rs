@@ -0,0 +1,153 @@
+use crate::{convert_keyword_locale, errors::CompileError, source_loader};
+use nagi_command_option::{GrammarCommand, LogLevel, NagiCommandOption};
+use nagi_parser::{
+    ebnf::{
+        export::ExportFormat,
+        first_follow::TerminalSet,
+        interpreter::{self, token_class_names, GrammarParseError},
+        normalize::NormalizeOptions,
+        validate::Severity,
+        Grammar,
+    },
+    ebnf_parser,
+};
+use std::{fs, path::Path};
+
+// nagi grammar ... の処理
+
+pub(crate) fn run_grammar_command(
+    command: &GrammarCommand,
+    args: &NagiCommandOption,
+) -> Result<(), CompileError> {
+    match command {
+        GrammarCommand::Check { file } => check_grammar(file, &args.log_level),
+        GrammarCommand::Test { grammar, input } => test_grammar(grammar, input, args),
+        GrammarCommand::Fmt { file } => format_grammar(file, args.merge_literals),
+        GrammarCommand::Export { format, file } => export_grammar(format, file),
+    }
+}
+
+// 検査の結果とLL(1)の衝突を表示し, エラーがあれば失敗にする
+fn check_grammar(file: &Path, log_level: &LogLevel) -> Result<(), CompileError> {
+    let source = fs::read_to_string(file)?;
+    let mut grammar =
+        ebnf_parser::parse_grammar(&source).map_err(|message| grammar_error(file, message))?;
+    grammar.declare_terminals(token_class_names());
+
+    let mut error_count = 0;
+    for diagnostic in grammar.validate() {
+        if diagnostic.severity() == Severity::Error {
+            error_count += 1;
+        }
+        eprintln!("{}: {}", file.display(), diagnostic.error_message(&source));
+    }
+
+    for conflict in grammar.ll1_conflicts() {
+        error_count += 1;
+        eprintln!("{}: {}", file.display(), conflict.error_message(&source));
+    }
+
+    if matches!(log_level, LogLevel::Detailed | LogLevel::All) {
+        print_first_follow(&grammar);
+    }
+
+    if error_count > 0 {
+        return Err(grammar_error(
+            file,
+            format!("{error_count} error(s) found in grammar"),
+        ));
+    }
+
+    Ok(())
+}
+
+// 文法の開始規則で入力を構文解析し, 構文木を表示する
+fn test_grammar(
+    grammar_file: &Path,
+    input_file: &Path,
+    args: &NagiCommandOption,
+) -> Result<(), CompileError> {
+    let grammar_source = fs::read_to_string(grammar_file)?;
+    let mut grammar = ebnf_parser::parse_grammar(&grammar_source)
+        .map_err(|message| grammar_error(grammar_file, message))?;
+    grammar.declare_terminals(token_class_names());
+
+    let source_code = source_loader::load_source(input_file, &args.source_encoding)?;
+    let keyword_locale = args.keyword_locale.map(convert_keyword_locale);
+    let tree = interpreter::parse_source(&grammar, &source_code, keyword_locale).map_err(|e| {
+        // 文法の誤りは文法ファイルの位置で報告する
+        let file = match e {
+            GrammarParseError::InvalidGrammar(_) => grammar_file,
+            _ => input_file,
+        };
+        grammar_error(file, e.error_message(&grammar_source, &source_code))
+    })?;
+
+    print!("{tree}");
+    Ok(())
+}
+
+// コメントは残らないので, ファイルは書き換えずに標準出力へ出す
+// リテラルは1つのトークンとして扱うので, --merge-literals が指定された場合のみ連結する
+fn format_grammar(file: &Path, merge_literals: bool) -> Result<(), CompileError> {
+    let source = fs::read_to_string(file)?;
+    let grammar =
+        ebnf_parser::parse_grammar(&source).map_err(|message| grammar_error(file, message))?;
+
+    print!("{}", grammar.normalize(NormalizeOptions { merge_literals }));
+    Ok(())
+}
+
+// ANTLR4 の grammar 名にはファイル名を使う
+fn export_grammar(format: &str, file: &Path) -> Result<(), CompileError> {
+    let Some(format) = ExportFormat::from_name(format) else {
+        return Err(grammar_error(
+            file,
+            format!("unknown export format `{format}` (expected antlr, lark or w3c)"),
+        ));
+    };
+
+    let source = fs::read_to_string(file)?;
+    let grammar =
+        ebnf_parser::parse_grammar(&source).map_err(|message| grammar_error(file, message))?;
+
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if !name.starts_with(char::is_alphabetic) {
+        name.insert(0, 'G');
+    }
+
+    print!("{}", grammar.export(format, &name));
+    Ok(())
+}
+
+fn print_first_follow(grammar: &Grammar) {
+    let first_follow = grammar.first_follow();
+    let format_set = |set: Option<&TerminalSet>| {
+        set.into_iter()
+            .flatten()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    for rule in grammar.rules() {
+        let name = rule.ebnf.name;
+        println!("{name}");
+        println!("  FIRST:  {}", format_set(first_follow.first.get(name)));
+        println!("  FOLLOW: {}", format_set(first_follow.follow.get(name)));
+    }
+}
+
+fn grammar_error(file: &Path, message: String) -> CompileError {
+    CompileError::Grammar {
+        path: file.to_path_buf(),
+        message,
+    }
+}
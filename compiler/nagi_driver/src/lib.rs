@@ -1,39 +1,237 @@
-use std::{fs, path::PathBuf, time::Instant};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 use errors::CompileError;
 use nagi_command_option::*;
+use nagi_lexer::{
+    token::Token,
+    whitespace::{self, WhitespaceLint, WhitespaceLintConfig},
+};
+use nagi_parser::{
+    errors::TokenStreamParseError,
+    lexer::code::{attribute, keyword_format, keywords, security, syntax_tree},
+};
 use walkdir::WalkDir;
 
 mod errors;
+mod grammar;
+mod source_loader;
 
 const SOURCE_FILE_EXTENSION: &str = "nagi";
 
 pub fn driver() {
     let start_time = Instant::now();
 
+    // CIなどで失敗を検出できるように終了コードを返す
+    // 所要時間は grammar export などの標準出力に混ざらないよう標準エラーに出す
     match run_compiler() {
-        Ok(_) => println!("{:?}", start_time.elapsed()),
-        Err(e) => eprintln!("{e}"),
+        Ok(_) => eprintln!("{:?}", start_time.elapsed()),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
     }
 }
 
 fn run_compiler() -> Result<(), CompileError> {
     let args = NagiCommandOption::new()?;
-    let files = get_source_files(args.target_dir, SOURCE_FILE_EXTENSION, true)?;
+    if let NagiCommand::Grammar(command) = &args.command {
+        return grammar::run_grammar_command(command, &args);
+    }
+
+    let whitespace_lint_config = make_whitespace_lint_config(&args.lint_levels)?;
+    let files = get_source_files(&args.target_dir, SOURCE_FILE_EXTENSION, true)?;
+    // fmtの--keywordsは変換先の言語で, 変換元はファイルの指定か--fromに従う
+    let (keyword_locale, keyword_target) = match args.command {
+        NagiCommand::Fmt => match args.keyword_locale {
+            Some(target) => (
+                args.keyword_source.map(convert_keyword_locale),
+                Some(convert_keyword_locale(target)),
+            ),
+            None => return Err(CompileError::MissingFormatTarget),
+        },
+        _ => (args.keyword_locale.map(convert_keyword_locale), None),
+    };
 
     for file in files {
-        let source_code = fs::read_to_string(file)?;
+        let mut source_code = source_loader::load_source(&file, &args.source_encoding)?;
+
+        // 文字コードの変換のみ行う
+        if matches!(args.output_file_type, OutputFileType::Utf8Source) {
+            write_utf8_source(&file, &args.target_dir, &args.output_dir, &source_code)?;
+            continue;
+        }
+
+        let mut token_list = nagi_lexer::tokenize(&source_code)?;
+
+        // 文字列とコメントの範囲はコードの字句解析で決める
+        let regions = security::security_regions(&token_list, keyword_locale)
+            .map_err(|e| token_stream_parse_error(&file, &source_code, e))?;
+        nagi_lexer::security::check_suspicious_characters(&source_code, &regions)?;
+
+        // fmtではキーワードの言語を変換して書き換える
+        if let Some(target) = keyword_target {
+            let formatted = keyword_format::convert_keyword_locale(
+                &source_code,
+                &token_list,
+                keyword_locale,
+                target,
+            )
+            .map_err(|e| token_stream_parse_error(&file, &source_code, e))?;
+            source_loader::save_source(&file, &formatted, &args.source_encoding)?;
+            continue;
+        }
+
+        let mut diagnostics = check_whitespace(
+            &file,
+            &source_code,
+            &token_list,
+            keyword_locale,
+            &whitespace_lint_config,
+        )?;
+        // 修正した後も残るものは報告する
+        // 読み込んだ時の文字コードで書き戻す
+        if args.fix && diagnostics.iter().any(|d| d.fix.is_some()) {
+            let fixed = whitespace::apply_fixes(&source_code, &diagnostics);
+            source_loader::save_source(&file, &fixed, &args.source_encoding)?;
+
+            source_code = fixed;
+            token_list = nagi_lexer::tokenize(&source_code)?;
+            diagnostics = check_whitespace(
+                &file,
+                &source_code,
+                &token_list,
+                keyword_locale,
+                &whitespace_lint_config,
+            )?;
+        }
+        check_lint(&file, diagnostics)?;
+
+        // 構文解析ができるまではトークンの接着と属性の検査のみ行う
+        let attribute_warnings = attribute::check_attributes(&token_list, keyword_locale)
+            .map_err(|e| token_stream_parse_error(&file, &source_code, e))?;
+        for warning in attribute_warnings {
+            eprintln!("{}: {warning}", file.display());
+        }
 
         // それぞれ専用のエラー型を作成してからコメントアウトを外す
-        //let token_list = nagi_lexer::tokenize(&source_code)?;
         //let ast = nagi_parser::parse(&token_list)?;
     }
 
     Ok(())
 }
 
+fn make_whitespace_lint_config(
+    lint_levels: &[(String, LintLevel)],
+) -> Result<WhitespaceLintConfig, CompileError> {
+    let mut config = WhitespaceLintConfig::default();
+    for (name, level) in lint_levels {
+        let Some(lint) = WhitespaceLint::from_name(name) else {
+            return Err(CompileError::UnknownLint(name.clone()));
+        };
+
+        let level = match level {
+            LintLevel::Allow => whitespace::LintLevel::Allow,
+            LintLevel::Warn => whitespace::LintLevel::Warn,
+            LintLevel::Deny => whitespace::LintLevel::Deny,
+        };
+        config.set_level(lint, level);
+    }
+
+    Ok(config)
+}
+
+fn convert_keyword_locale(locale: KeywordLocale) -> keywords::KeywordLocale {
+    match locale {
+        KeywordLocale::English => keywords::KeywordLocale::English,
+        KeywordLocale::Japanese => keywords::KeywordLocale::Japanese,
+    }
+}
+
+fn token_stream_parse_error(
+    file: &Path,
+    source_code: &str,
+    error: TokenStreamParseError,
+) -> CompileError {
+    CompileError::TokenStreamParse {
+        path: file.to_path_buf(),
+        message: error.error_message(source_code),
+    }
+}
+
+// リテラルとコメントの中を除いて空白と改行を検査する
+fn check_whitespace(
+    file: &Path,
+    source_code: &str,
+    token_list: &[Token],
+    keyword_locale: Option<keywords::KeywordLocale>,
+    config: &WhitespaceLintConfig,
+) -> Result<Vec<whitespace::WhitespaceDiagnostic>, CompileError> {
+    let tree = syntax_tree::build_syntax_tree(source_code, token_list, keyword_locale)
+        .map_err(|e| token_stream_parse_error(file, source_code, e))?;
+    let skip_ranges = syntax_tree::literal_and_comment_ranges(&tree);
+
+    Ok(whitespace::check_whitespace(
+        token_list,
+        config,
+        &skip_ranges,
+    ))
+}
+
+// 警告は表示のみ, denyのものがあればエラーにする
+fn check_lint(
+    file: &Path,
+    diagnostics: Vec<whitespace::WhitespaceDiagnostic>,
+) -> Result<(), CompileError> {
+    let (denied, warnings): (Vec<_>, Vec<_>) = diagnostics
+        .into_iter()
+        .partition(|d| d.level == whitespace::LintLevel::Deny);
+
+    for warning in warnings {
+        eprintln!("{}: {warning}", file.display());
+    }
+
+    if denied.is_empty() {
+        return Ok(());
+    }
+
+    Err(CompileError::Lint {
+        path: file.to_path_buf(),
+        diagnostics: denied,
+    })
+}
+
+// 変換したソースを出力先のディレクトリに対象のディレクトリと同じ構成で書き出す
+// 入力のファイルは書き換えない
+fn write_utf8_source(
+    file: &Path,
+    target_dir: &Path,
+    output_dir: &Path,
+    source_code: &str,
+) -> Result<(), CompileError> {
+    let relative = match file.strip_prefix(target_dir) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => Path::new(file.file_name().unwrap_or(file.as_os_str())),
+    };
+    let output = output_dir.join(relative);
+
+    if output.exists() && fs::canonicalize(&output)? == fs::canonicalize(file)? {
+        return Err(CompileError::OutputOverwritesSource(file.to_path_buf()));
+    }
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&output, source_code)?;
+
+    Ok(())
+}
+
 fn get_source_files(
-    path: PathBuf,
+    path: &Path,
     target_extension: &str,
     recursive: bool,
 ) -> Result<Vec<PathBuf>, CompileError> {
@@ -0,0 +1,340 @@
+use jis0208::JIS0208;
+use nagi_command_option::SourceEncoding;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+mod jis0208;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const JIS_ROW_SIZE: usize = 94;
+
+// 半角カナ
+const HALF_WIDTH_KATAKANA_FIRST: u8 = 0xA1;
+const HALF_WIDTH_KATAKANA_LAST: u8 = 0xDF;
+const HALF_WIDTH_KATAKANA_OFFSET: u32 = 0xFF61 - HALF_WIDTH_KATAKANA_FIRST as u32;
+
+#[derive(Debug)]
+pub(crate) enum SourceLoadError {
+    IO {
+        path: PathBuf,
+        error: io::Error,
+    },
+    InvalidEncoding {
+        path: PathBuf,
+        encoding: &'static str,
+        position: usize, // バイト単位の位置
+        line: usize,
+        column: usize, // 1始まりのバイト単位
+    },
+    // 書き戻す際に元の文字コードで表せない文字
+    UnencodableCharacter {
+        path: PathBuf,
+        encoding: &'static str,
+        character: char,
+        line: usize,
+        column: usize, // 1始まりのUTF-8のバイト単位
+    },
+}
+
+impl Error for SourceLoadError {}
+
+impl Display for SourceLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceLoadError::IO { path, error } => write!(f, "{}: {error}", path.display()),
+            SourceLoadError::InvalidEncoding {
+                path,
+                encoding,
+                position,
+                line,
+                column,
+            } => write!(
+                f,
+                "{}:{line}:{column}: invalid {encoding} byte sequence at byte offset {position}",
+                path.display()
+            ),
+            SourceLoadError::UnencodableCharacter {
+                path,
+                encoding,
+                character,
+                line,
+                column,
+            } => write!(
+                f,
+                "{}:{line}:{column}: `{character}` cannot be encoded in {encoding}",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// ソースファイルを読み込みUTF-8の文字列に変換する
+/// UTF-8の場合は先頭のBOMを取り除く
+pub(crate) fn load_source(
+    path: &Path,
+    encoding: &SourceEncoding,
+) -> Result<String, SourceLoadError> {
+    let bytes = fs::read(path).map_err(|error| SourceLoadError::IO {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    let result = match encoding {
+        SourceEncoding::Utf8 => decode_utf8(&bytes),
+        SourceEncoding::ShiftJis => decode_shift_jis(&bytes),
+        SourceEncoding::EucJp => decode_euc_jp(&bytes),
+    };
+
+    result.map_err(|position| {
+        let (line, column) = line_and_column(&bytes, position);
+        SourceLoadError::InvalidEncoding {
+            path: path.to_path_buf(),
+            encoding: encoding_name(encoding),
+            position,
+            line,
+            column,
+        }
+    })
+}
+
+/// 変換した文字列を読み込んだ時と同じ文字コードでソースファイルに書き戻す
+pub(crate) fn save_source(
+    path: &Path,
+    source_code: &str,
+    encoding: &SourceEncoding,
+) -> Result<(), SourceLoadError> {
+    let result = match encoding {
+        SourceEncoding::Utf8 => Ok(source_code.as_bytes().to_vec()),
+        SourceEncoding::ShiftJis => encode_shift_jis(source_code),
+        SourceEncoding::EucJp => encode_euc_jp(source_code),
+    };
+
+    let bytes = result.map_err(|position| {
+        let (line, column) = line_and_column(source_code.as_bytes(), position);
+        SourceLoadError::UnencodableCharacter {
+            path: path.to_path_buf(),
+            encoding: encoding_name(encoding),
+            character: source_code[position..].chars().next().unwrap(),
+            line,
+            column,
+        }
+    })?;
+
+    fs::write(path, bytes).map_err(|error| SourceLoadError::IO {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+// エラー時は不正なバイト列の開始位置を返す
+fn decode_utf8(bytes: &[u8]) -> Result<String, usize> {
+    let (bytes, offset) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(bytes) => (bytes, UTF8_BOM.len()),
+        None => (bytes, 0),
+    };
+
+    std::str::from_utf8(bytes)
+        .map(|s| s.to_string())
+        .map_err(|e| offset + e.valid_up_to())
+}
+
+/// 1バイト目 0x81-0x9F 0xE0-0xEF, 2バイト目 0x40-0x7E 0x80-0xFC の2バイト文字と
+/// ASCII, 半角カナ(0xA1-0xDF)に対応する
+fn decode_shift_jis(bytes: &[u8]) -> Result<String, usize> {
+    let mut text = String::with_capacity(bytes.len());
+    let mut position = 0;
+
+    while let Some(&lead) = bytes.get(position) {
+        let (c, length) = match lead {
+            0x00..=0x7F => (lead as char, 1),
+            HALF_WIDTH_KATAKANA_FIRST..=HALF_WIDTH_KATAKANA_LAST => (half_width_katakana(lead), 1),
+            0x81..=0x9F | 0xE0..=0xEF => {
+                let Some(&trail) = bytes.get(position + 1) else {
+                    return Err(position);
+                };
+                if !matches!(trail, 0x40..=0x7E | 0x80..=0xFC) {
+                    return Err(position);
+                }
+
+                let lead_offset = if lead < 0xA0 { 0x81 } else { 0xC1 };
+                let trail_offset = if trail < 0x7F { 0x40 } else { 0x41 };
+                let pointer = (lead - lead_offset) as usize * JIS_ROW_SIZE * 2
+                    + (trail - trail_offset) as usize;
+                (jis0208_char(pointer).ok_or(position)?, 2)
+            }
+            _ => return Err(position),
+        };
+
+        text.push(c);
+        position += length;
+    }
+
+    Ok(text)
+}
+
+/// ASCII, JIS X 0208(0xA1-0xFE の2バイト), 半角カナ(0x8E + 0xA1-0xDF)に対応する
+/// JIS X 0212(0x8F で始まる3バイト)は未対応
+fn decode_euc_jp(bytes: &[u8]) -> Result<String, usize> {
+    let mut text = String::with_capacity(bytes.len());
+    let mut position = 0;
+
+    while let Some(&lead) = bytes.get(position) {
+        let (c, length) = match lead {
+            0x00..=0x7F => (lead as char, 1),
+            0x8E => match bytes.get(position + 1) {
+                Some(&trail @ HALF_WIDTH_KATAKANA_FIRST..=HALF_WIDTH_KATAKANA_LAST) => {
+                    (half_width_katakana(trail), 2)
+                }
+                _ => return Err(position),
+            },
+            0xA1..=0xFE => match bytes.get(position + 1) {
+                Some(&trail @ 0xA1..=0xFE) => {
+                    let pointer = (lead - 0xA1) as usize * JIS_ROW_SIZE + (trail - 0xA1) as usize;
+                    (jis0208_char(pointer).ok_or(position)?, 2)
+                }
+                _ => return Err(position),
+            },
+            _ => return Err(position),
+        };
+
+        text.push(c);
+        position += length;
+    }
+
+    Ok(text)
+}
+
+// エラー時は変換できない文字の位置を返す
+fn encode_shift_jis(text: &str) -> Result<Vec<u8>, usize> {
+    let pointers = jis0208_pointers();
+    let mut bytes = Vec::with_capacity(text.len());
+
+    for (position, c) in text.char_indices() {
+        if c.is_ascii() {
+            bytes.push(c as u8);
+        } else if let Some(byte) = half_width_katakana_byte(c) {
+            bytes.push(byte);
+        } else {
+            let &pointer = pointers.get(&c).ok_or(position)?;
+            let (lead, trail) = (pointer / (JIS_ROW_SIZE * 2), pointer % (JIS_ROW_SIZE * 2));
+            let lead_offset = if lead < 0x1F { 0x81 } else { 0xC1 };
+            let trail_offset = if trail < 0x3F { 0x40 } else { 0x41 };
+            bytes.extend([(lead + lead_offset) as u8, (trail + trail_offset) as u8]);
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn encode_euc_jp(text: &str) -> Result<Vec<u8>, usize> {
+    let pointers = jis0208_pointers();
+    let mut bytes = Vec::with_capacity(text.len());
+
+    for (position, c) in text.char_indices() {
+        if c.is_ascii() {
+            bytes.push(c as u8);
+        } else if let Some(byte) = half_width_katakana_byte(c) {
+            bytes.extend([0x8E, byte]);
+        } else {
+            let &pointer = pointers.get(&c).ok_or(position)?;
+            bytes.extend([
+                (pointer / JIS_ROW_SIZE) as u8 + 0xA1,
+                (pointer % JIS_ROW_SIZE) as u8 + 0xA1,
+            ]);
+        }
+    }
+
+    Ok(bytes)
+}
+
+// 同じ文字が複数の位置にある場合は先の位置を使う
+fn jis0208_pointers() -> HashMap<char, usize> {
+    let mut pointers = HashMap::new();
+    for pointer in (0..JIS0208.len()).rev() {
+        if let Some(c) = jis0208_char(pointer) {
+            pointers.insert(c, pointer);
+        }
+    }
+
+    pointers
+}
+
+fn jis0208_char(pointer: usize) -> Option<char> {
+    let &code_point = JIS0208.get(pointer)?;
+    if code_point == 0 {
+        return None;
+    }
+
+    char::from_u32(code_point as u32)
+}
+
+fn half_width_katakana(byte: u8) -> char {
+    char::from_u32(byte as u32 + HALF_WIDTH_KATAKANA_OFFSET).unwrap()
+}
+
+fn half_width_katakana_byte(c: char) -> Option<u8> {
+    let byte = (c as u32).checked_sub(HALF_WIDTH_KATAKANA_OFFSET)?;
+    (HALF_WIDTH_KATAKANA_FIRST as u32..=HALF_WIDTH_KATAKANA_LAST as u32)
+        .contains(&byte)
+        .then_some(byte as u8)
+}
+
+// 1始まりの行番号と列番号
+fn line_and_column(bytes: &[u8], position: usize) -> (usize, usize) {
+    let before = &bytes[..position];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |p| p + 1);
+
+    (line, position - line_start + 1)
+}
+
+fn encoding_name(encoding: &SourceEncoding) -> &'static str {
+    match encoding {
+        SourceEncoding::Utf8 => "UTF-8",
+        SourceEncoding::ShiftJis => "Shift_JIS",
+        SourceEncoding::EucJp => "EUC-JP",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // JIS X 0208 の全ての文字と半角カナが元のバイト列に戻る
+    #[test]
+    fn encode_round_trip() {
+        let mut text: String = (0..JIS0208.len()).filter_map(jis0208_char).collect();
+        text.extend(
+            (HALF_WIDTH_KATAKANA_FIRST..=HALF_WIDTH_KATAKANA_LAST).map(half_width_katakana),
+        );
+        text.push_str("fn f() {}\r\n\t");
+
+        for (encode, decode) in [
+            (
+                encode_shift_jis as fn(&str) -> _,
+                decode_shift_jis as fn(&[u8]) -> _,
+            ),
+            (encode_euc_jp, decode_euc_jp),
+        ] {
+            let bytes = encode(&text).unwrap();
+            assert_eq!(decode(&bytes).unwrap(), text);
+            assert_eq!(encode(&decode(&bytes).unwrap()).unwrap(), bytes);
+        }
+
+        assert_eq!(
+            encode_shift_jis("関数 f"),
+            Ok(b"\x8A\xD6\x90\x94 f".to_vec())
+        );
+        assert_eq!(encode_euc_jp("関数 f"), Ok(b"\xB4\xD8\xBF\xF4 f".to_vec()));
+        assert_eq!(encode_shift_jis("a😀"), Err(1));
+        assert_eq!(encode_euc_jp("a\u{00E9}"), Err(1));
+    }
+}
@@ -1,9 +1,11 @@
+use crate::security::SuspiciousCharacter;
 use std::{error::Error, fmt::Display};
 
 #[derive(Debug)]
 pub enum TokenizeError {
     InvalidCharacters { c: char, position: usize },
     UnusableWhitespace { c: char, position: usize },
+    SuspiciousCharacters(Vec<SuspiciousCharacter>),
 }
 
 impl Error for TokenizeError {}
@@ -17,6 +19,10 @@ impl Display for TokenizeError {
             TokenizeError::UnusableWhitespace { c, position } => {
                 write!(f, "Unusable whitespace: {c}")
             }
+            TokenizeError::SuspiciousCharacters(list) => {
+                let messages: Vec<_> = list.iter().map(|s| s.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
         }
     }
 }
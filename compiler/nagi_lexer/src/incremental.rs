@@ -0,0 +1,180 @@
+use crate::errors::TokenizeError;
+use crate::token::{Token, TokenKind};
+use crate::Tokenizer;
+use std::ops::Range;
+
+// エディタ向けの差分トークン化
+// 編集箇所の周辺だけを切り分け直し, それ以降は前回のトークンの位置をずらして再利用する
+//
+// トークンは次の文字だけで種類が決まり, 条件を満たさない文字で終わるので
+// 編集箇所より後ろで前回のトークンの境界と一致すれば, それ以降の結果は前回と同じになる
+
+/// 編集前のソースコードのrangeをreplacementに置き換える編集
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// 前回のトークン列に編集を反映したトークン列を返す
+/// new_source_codeは編集を適用した後のソースコード
+pub fn retokenize<'a>(
+    old_token_list: &[Token],
+    new_source_code: &'a str,
+    edit: &TextEdit,
+) -> Result<Vec<Token<'a>>, TokenizeError> {
+    let old_edit_end = edit.range.end;
+    let new_edit_end = edit.range.start + edit.replacement.len();
+    let shift = |old_position: usize| old_position - old_edit_end + new_edit_end;
+
+    // 編集位置を含むトークンの1つ前から切り分け直す
+    // 直前のトークンと結合する可能性があるため
+    let start_index = old_token_list
+        .partition_point(|t| t.token_pos <= edit.range.start)
+        .saturating_sub(2);
+    let start_position = old_token_list.get(start_index).map_or(0, |t| t.token_pos);
+
+    let mut token_list: Vec<Token<'a>> = old_token_list[..start_index]
+        .iter()
+        .map(|t| rebase(t, new_source_code, t.token_pos))
+        .collect();
+
+    let mut tokenizer = Tokenizer::with_offset(new_source_code, start_position);
+    let mut old_index = start_index;
+    loop {
+        let position = tokenizer.position();
+
+        // 編集箇所より後ろで前回の境界と一致したら残りを再利用する
+        if position >= new_edit_end {
+            let old_position = position - new_edit_end + old_edit_end;
+            old_index +=
+                old_token_list[old_index..].partition_point(|t| t.token_pos < old_position);
+
+            if old_token_list
+                .get(old_index)
+                .is_some_and(|t| t.token_pos == old_position)
+            {
+                token_list.extend(
+                    old_token_list[old_index..]
+                        .iter()
+                        .map(|t| rebase(t, new_source_code, shift(t.token_pos))),
+                );
+                break;
+            }
+        }
+
+        let Some(token) = tokenizer.next() else {
+            break;
+        };
+        token_list.push(token?);
+    }
+
+    Ok(token_list)
+}
+
+// 前回のトークンを編集後のソースコードを参照するトークンに作り直す
+fn rebase<'a>(token: &Token, new_source_code: &'a str, token_pos: usize) -> Token<'a> {
+    let token_kind = match &token.token_kind {
+        TokenKind::Identifier(ident) => {
+            TokenKind::Identifier(&new_source_code[token_pos..token_pos + ident.len()])
+        }
+        TokenKind::Number(num) => {
+            TokenKind::Number(&new_source_code[token_pos..token_pos + num.len()])
+        }
+        TokenKind::Symbol(symbol) => TokenKind::Symbol(symbol.clone()),
+        TokenKind::LineBreak(line_break) => TokenKind::LineBreak(line_break.clone()),
+        TokenKind::WhiteSpace(space) => TokenKind::WhiteSpace(space.clone()),
+    };
+
+    Token {
+        token_kind,
+        token_pos,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retokenize, TextEdit};
+    use crate::test_util::Rng;
+    use crate::tokenize;
+
+    fn random_edit(rng: &mut Rng, source_code: &str) -> TextEdit {
+        let boundaries: Vec<usize> = (0..=source_code.len())
+            .filter(|&i| source_code.is_char_boundary(i))
+            .collect();
+        let a = boundaries[rng.index(boundaries.len())];
+        let b = boundaries[rng.index(boundaries.len())];
+
+        TextEdit {
+            range: a.min(b)..a.max(b),
+            replacement: rng.source_code(4),
+        }
+    }
+
+    fn apply(source_code: &str, edit: &TextEdit) -> String {
+        let mut new_source_code = source_code.to_string();
+        new_source_code.replace_range(edit.range.clone(), &edit.replacement);
+        new_source_code
+    }
+
+    // 差分で切り分けた結果が全体を切り分け直した結果と一致する
+    #[test]
+    fn matches_full_tokenize() {
+        let mut rng = Rng::new(0xdead_beef_1234_5678);
+        let mut checked = 0;
+        for _ in 0..20000 {
+            let source_code = rng.source_code(20);
+            let Ok(old_token_list) = tokenize(&source_code) else {
+                continue;
+            };
+
+            let edit = random_edit(&mut rng, &source_code);
+            let new_source_code = apply(&source_code, &edit);
+
+            match (
+                tokenize(&new_source_code),
+                retokenize(&old_token_list, &new_source_code, &edit),
+            ) {
+                (Ok(full), Ok(incremental)) => {
+                    assert_eq!(full, incremental, "{source_code:?} -> {new_source_code:?}");
+                    checked += 1;
+                }
+                (Err(full), Err(incremental)) => {
+                    assert_eq!(format!("{full:?}"), format!("{incremental:?}"));
+                }
+                (full, incremental) => {
+                    panic!("{source_code:?} -> {new_source_code:?}: {full:?} / {incremental:?}")
+                }
+            }
+        }
+        assert!(checked > 5000, "only {checked} edits were checked");
+    }
+
+    // 編集を重ねても全体を切り分け直した結果と一致する
+    #[test]
+    fn consecutive_edits() {
+        let mut rng = Rng::new(0x0123_4567_89ab_cdef);
+        for _ in 0..500 {
+            let mut sources = vec![rng.source_code(20)];
+            let mut edits = vec![];
+            for _ in 0..10 {
+                let source_code = sources.last().unwrap();
+                let edit = random_edit(&mut rng, source_code);
+                sources.push(apply(source_code, &edit));
+                edits.push(edit);
+            }
+
+            let Ok(mut token_list) = tokenize(&sources[0]) else {
+                continue;
+            };
+            for (edit, new_source_code) in edits.iter().zip(&sources[1..]) {
+                let Ok(full) = tokenize(new_source_code) else {
+                    break;
+                };
+                let incremental = retokenize(&token_list, new_source_code, edit).unwrap();
+                assert_eq!(full, incremental, "{new_source_code:?}");
+                token_list = incremental;
+            }
+        }
+    }
+}
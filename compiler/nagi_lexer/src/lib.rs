@@ -4,7 +4,13 @@ use std::str::CharIndices;
 use token::{LineBreak, Space, Symbol, Token, TokenKind};
 
 pub mod errors;
+pub mod incremental;
+pub mod security;
 pub mod token;
+pub mod whitespace;
+
+#[cfg(test)]
+mod test_util;
 
 type Iter<'a> = Peekable<CharIndices<'a>>;
 
@@ -15,26 +21,108 @@ type Iter<'a> = Peekable<CharIndices<'a>>;
 // パーサー側で扱いにくくなるため一旦特定の文字の塊だけにして
 // パーサーに渡す前にそのパーサーに適したトークンに変換する
 pub fn tokenize<'a>(source_code: &'a str) -> Result<Vec<Token<'a>>, TokenizeError> {
-    let mut iter = source_code.char_indices().peekable();
-    let mut token_list = vec![];
+    Tokenizer::new(source_code).collect()
+}
+
+/// トークンを1つずつ遅延して切り分けるイテレータ
+/// エラーを返した後はNoneを返し続ける
+pub struct Tokenizer<'a> {
+    source_code: &'a str,
+    iter: Iter<'a>,
+    offset: usize, // source_codeの先頭の元のソースコード上での位置
+    finished: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(source_code: &'a str) -> Self {
+        Self::with_offset(source_code, 0)
+    }
+
+    /// source_codeのoffsetバイト目から切り分ける
+    /// トークンの位置は元のソースコード上の位置になる
+    pub fn with_offset(source_code: &'a str, offset: usize) -> Self {
+        let source_code = &source_code[offset..];
+        Self {
+            source_code,
+            iter: source_code.char_indices().peekable(),
+            offset,
+            finished: false,
+        }
+    }
+
+    /// 次に切り分ける位置
+    pub fn position(&mut self) -> usize {
+        let position = self
+            .iter
+            .peek()
+            .map(|e| e.0)
+            .unwrap_or(self.source_code.len());
+
+        self.offset + position
+    }
 
-    while let Some(&(position, c)) = iter.peek() {
-        let token = if c.is_ascii_digit() {
-            eat_number(source_code, &mut iter)? // 0-9で始まるものは数値として扱う
+    fn next_token(&mut self) -> Option<Result<Token<'a>, TokenizeError>> {
+        let &(position, c) = self.iter.peek()?;
+        let source_code = self.source_code;
+        let iter = &mut self.iter;
+
+        let result = if c.is_ascii_digit() {
+            eat_number(source_code, iter) // 0-9で始まるものは数値として扱う
         } else if c.is_ascii_whitespace() {
-            eat_whitespace(&mut iter)?
+            eat_whitespace(iter)
         } else if c.is_ascii_punctuation() {
-            eat_symbol(&mut iter)? // ASCIIの記号
-        } else if c.is_alphabetic() {
-            eat_identifier(source_code, &mut iter)? // 日本語などを使用するのでasciiに限定しない
+            eat_symbol(iter) // ASCIIの記号
+        } else if is_identifier_char(c) {
+            eat_identifier(source_code, iter) // 日本語などを使用するのでasciiに限定しない
         } else {
-            return Err(TokenizeError::InvalidCharacters { c, position });
+            Err(TokenizeError::InvalidCharacters { c, position })
+        };
+
+        Some(result)
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let result = match self.next_token()? {
+            Ok(token) => Ok(Token {
+                token_pos: token.token_pos + self.offset,
+                ..token
+            }),
+            Err(e) => {
+                self.finished = true;
+                Err(shift_error_position(e, self.offset))
+            }
         };
 
-        token_list.push(token);
+        Some(result)
+    }
+}
+
+fn shift_error_position(error: TokenizeError, offset: usize) -> TokenizeError {
+    match error {
+        TokenizeError::InvalidCharacters { c, position } => TokenizeError::InvalidCharacters {
+            c,
+            position: position + offset,
+        },
+        TokenizeError::UnusableWhitespace { c, position } => TokenizeError::UnusableWhitespace {
+            c,
+            position: position + offset,
+        },
+        e @ TokenizeError::SuspiciousCharacters(_) => e, // 切り分けでは発生しない
     }
+}
 
-    Ok(token_list)
+/// 識別子の塊に含める文字
+/// 目に見えない文字もここで切り分け, 不審な文字の検査 (security) で報告する
+pub fn is_identifier_char(c: char) -> bool {
+    c.is_alphabetic() || security::is_hidden_character(c)
 }
 
 fn eat_identifier<'a>(source_code: &'a str, iter: &mut Iter) -> Result<Token<'a>, TokenizeError> {
@@ -42,7 +130,7 @@ fn eat_identifier<'a>(source_code: &'a str, iter: &mut Iter) -> Result<Token<'a>
         unreachable!();
     };
 
-    let code = slice_code(source_code, iter, |c| c.is_alphabetic())?;
+    let code = slice_code(source_code, iter, is_identifier_char)?;
 
     Ok(Token {
         token_kind: TokenKind::Identifier(code),
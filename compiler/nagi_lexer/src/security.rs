@@ -0,0 +1,222 @@
+use crate::errors::TokenizeError;
+use std::fmt::Display;
+use std::ops::Range;
+
+// Trojan Source対策
+// 双方向テキストの制御文字や不可視文字, ラテン文字に紛れた似た形の文字は
+// レビューで見えているコードと実際のコードを食い違わせることができるため
+// 構文解析の前にソースコード全体を検査する
+//
+// 文字列やコメントの範囲は自前で追わず, コードの字句解析の結果 (SourceRegion) を使う
+// 制御文字と不可視文字はどこにあってもエラーにし,
+// #[allow(bidi_chars)] #[allow(invisible_chars)] で許可されたリテラルの中身だけを除く
+// 紛らわしい文字はラテン文字を含む識別子の中だけを調べる
+
+pub const ALLOW_BIDI_CHARS: &str = "bidi_chars";
+pub const ALLOW_INVISIBLE_CHARS: &str = "invisible_chars";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspiciousCharacter {
+    pub kind: SuspiciousCharacterKind,
+    pub c: char,
+    pub span: Range<usize>, // 文字のバイト範囲
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuspiciousCharacterKind {
+    BidiControl,
+    Invisible,
+    Confusable { similar: char }, // ラテン文字を含む識別子中の紛らわしい文字
+}
+
+/// 字句解析で分かったソースコード上の範囲
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRegion {
+    pub kind: SourceRegionKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceRegionKind {
+    Identifier,
+    Literal(AllowedCharacters), // 文字列や文字のリテラル
+}
+
+/// リテラルの中で許可する文字
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllowedCharacters {
+    pub bidi_chars: bool,
+    pub invisible_chars: bool,
+}
+
+/// 不審な文字があればエラーを返す
+pub fn check_suspicious_characters(
+    source_code: &str,
+    regions: &[SourceRegion],
+) -> Result<(), TokenizeError> {
+    let list = scan_suspicious_characters(source_code, regions);
+    if list.is_empty() {
+        return Ok(());
+    }
+
+    Err(TokenizeError::SuspiciousCharacters(list))
+}
+
+/// ソースコード全体から不審な文字を探す
+/// regionsは位置の順に並んでいるもの
+pub fn scan_suspicious_characters(
+    source_code: &str,
+    regions: &[SourceRegion],
+) -> Vec<SuspiciousCharacter> {
+    let mut list = vec![];
+    let mut iter = regions.iter().peekable();
+
+    for (position, c) in source_code.char_indices() {
+        let Some(kind) = classify(c) else {
+            continue;
+        };
+
+        while iter.next_if(|r| r.span.end <= position).is_some() {}
+        let allowed = match iter.peek() {
+            Some(SourceRegion {
+                kind: SourceRegionKind::Literal(allowed),
+                span,
+            }) if span.contains(&position) => match kind {
+                SuspiciousCharacterKind::BidiControl => allowed.bidi_chars,
+                SuspiciousCharacterKind::Invisible => allowed.invisible_chars,
+                SuspiciousCharacterKind::Confusable { .. } => false,
+            },
+            _ => false,
+        };
+
+        if !allowed {
+            list.push(SuspiciousCharacter {
+                kind,
+                c,
+                span: position..position + c.len_utf8(),
+            });
+        }
+    }
+
+    for region in regions {
+        if region.kind == SourceRegionKind::Identifier {
+            list.extend(scan_identifier(source_code, region.span.clone()));
+        }
+    }
+
+    list.sort_by_key(|s| s.span.start);
+    list
+}
+
+/// 字句解析で識別子の一部として扱う, 目に見えない文字か
+/// 識別子やリテラルとして切り分けた後に scan_suspicious_characters で報告する
+pub fn is_hidden_character(c: char) -> bool {
+    classify(c).is_some()
+}
+
+/// 識別子にラテン文字と紛らわしい文字が混在していないかを調べる
+fn scan_identifier(source_code: &str, span: Range<usize>) -> Vec<SuspiciousCharacter> {
+    let start = span.start;
+    let ident = &source_code[span];
+
+    if !ident.chars().any(|c| c.is_ascii_alphabetic()) {
+        return vec![];
+    }
+
+    ident
+        .char_indices()
+        .filter_map(|(pos, c)| {
+            let similar = confusable_latin(c)?;
+            Some(SuspiciousCharacter {
+                kind: SuspiciousCharacterKind::Confusable { similar },
+                c,
+                span: start + pos..start + pos + c.len_utf8(),
+            })
+        })
+        .collect()
+}
+
+fn classify(c: char) -> Option<SuspiciousCharacterKind> {
+    match c {
+        '\u{061C}' // ARABIC LETTER MARK
+        | '\u{200E}' // LEFT-TO-RIGHT MARK
+        | '\u{200F}' // RIGHT-TO-LEFT MARK
+        | '\u{202A}'..='\u{202E}' // LRE, RLE, PDF, LRO, RLO
+        | '\u{2066}'..='\u{2069}' // LRI, RLI, FSI, PDI
+        => Some(SuspiciousCharacterKind::BidiControl),
+        '\u{00AD}' // SOFT HYPHEN
+        | '\u{034F}' // COMBINING GRAPHEME JOINER
+        | '\u{115F}' | '\u{1160}' // HANGUL FILLER
+        | '\u{17B4}' | '\u{17B5}'
+        | '\u{180E}' // MONGOLIAN VOWEL SEPARATOR
+        | '\u{200B}'..='\u{200D}' // ZWSP, ZWNJ, ZWJ
+        | '\u{2060}'..='\u{2064}' // WORD JOINER, 不可視の演算子
+        | '\u{3164}' // HANGUL FILLER
+        | '\u{FE00}'..='\u{FE0F}' // VARIATION SELECTOR
+        | '\u{FEFF}' // ZERO WIDTH NO-BREAK SPACE
+        | '\u{FFA0}' // HALFWIDTH HANGUL FILLER
+        => Some(SuspiciousCharacterKind::Invisible),
+        _ => None,
+    }
+}
+
+// キリル文字やギリシャ文字のうちラテン文字と見分けがつかないもの
+fn confusable_latin(c: char) -> Option<char> {
+    let similar = match c {
+        'а' => 'a',
+        'е' => 'e',
+        'о' | 'ο' => 'o',
+        'р' | 'ρ' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' | 'χ' => 'x',
+        'і' => 'i',
+        'ј' => 'j',
+        'ѕ' => 's',
+        'ԁ' => 'd',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+        'ν' => 'v',
+        'А' | 'Α' => 'A',
+        'В' | 'Β' => 'B',
+        'Е' | 'Ε' => 'E',
+        'Н' | 'Η' => 'H',
+        'І' | 'Ι' => 'I',
+        'Ј' => 'J',
+        'К' | 'Κ' => 'K',
+        'М' | 'Μ' => 'M',
+        'О' | 'Ο' => 'O',
+        'Р' | 'Ρ' => 'P',
+        'С' => 'C',
+        'Т' | 'Τ' => 'T',
+        'Х' | 'Χ' => 'X',
+        'Ζ' => 'Z',
+        'Ν' => 'N',
+        _ => return None,
+    };
+
+    Some(similar)
+}
+
+impl Display for SuspiciousCharacter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code_point = self.c as u32;
+        let Range { start, end } = self.span;
+        match self.kind {
+            SuspiciousCharacterKind::BidiControl => write!(
+                f,
+                "Bidirectional control character U+{code_point:04X} at {start}..{end}"
+            ),
+            SuspiciousCharacterKind::Invisible => {
+                write!(
+                    f,
+                    "Invisible character U+{code_point:04X} at {start}..{end}"
+                )
+            }
+            SuspiciousCharacterKind::Confusable { similar } => write!(
+                f,
+                "Character U+{code_point:04X} looks like '{similar}' at {start}..{end}"
+            ),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// 乱択テストで共有する疑似乱数とソースコードの断片
+
+/// 固定のシードから同じ列を再現できる疑似乱数 (xorshift64)
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// 0..len の範囲の添字
+    pub(crate) fn index(&mut self, len: usize) -> usize {
+        (self.next() % len as u64) as usize
+    }
+
+    /// FRAGMENTSから0個以上max_fragments個以下を選んで連結する
+    pub(crate) fn source_code(&mut self, max_fragments: u64) -> String {
+        let len = self.next() % (max_fragments + 1);
+        (0..len)
+            .map(|_| FRAGMENTS[self.index(FRAGMENTS.len())])
+            .collect()
+    }
+}
+
+/// 単純なトークンの境界になりやすい文字の断片
+pub(crate) const FRAGMENTS: &[&str] = &[
+    "a", "bc", "日本", "1", "23", " ", "\t", "\n", "\r", "\r\n", "+", "_", "(", "\"", "\u{200B}",
+    "\u{3000}",
+];
@@ -0,0 +1,411 @@
+use crate::incremental::TextEdit;
+use crate::token::{LineBreak, Space, Token, TokenKind};
+use std::fmt::Display;
+use std::ops::Range;
+
+// 空白と改行の一貫性の検査
+// インデントのタブとスペースの混在, CRLFとLFの混在, 行末の空白, 単独のCRを報告する
+// ファイル内で最初に現れたインデント・改行の種類をそのファイルの基準とする
+// 文字列などのリテラルとコメントの中は値や内容が変わるため検査しない
+
+const TAB_WIDTH: usize = 4; // タブからスペースへ変換する際の幅
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WhitespaceLint {
+    MixedIndentation,
+    MixedLineEndings,
+    TrailingWhitespace,
+    LoneCarriageReturn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone)]
+pub struct WhitespaceLintConfig {
+    pub mixed_indentation: LintLevel,
+    pub mixed_line_endings: LintLevel,
+    pub trailing_whitespace: LintLevel,
+    pub lone_carriage_return: LintLevel,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhitespaceDiagnostic {
+    pub lint: WhitespaceLint,
+    pub level: LintLevel,
+    pub span: Range<usize>,
+    pub fix: Option<TextEdit>, // 自動で直せない場合はNone
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Indentation {
+    Spaces,
+    Tabs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl WhitespaceLint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WhitespaceLint::MixedIndentation => "mixed_indentation",
+            WhitespaceLint::MixedLineEndings => "mixed_line_endings",
+            WhitespaceLint::TrailingWhitespace => "trailing_whitespace",
+            WhitespaceLint::LoneCarriageReturn => "lone_carriage_return",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let lint = match name {
+            "mixed_indentation" => WhitespaceLint::MixedIndentation,
+            "mixed_line_endings" => WhitespaceLint::MixedLineEndings,
+            "trailing_whitespace" => WhitespaceLint::TrailingWhitespace,
+            "lone_carriage_return" => WhitespaceLint::LoneCarriageReturn,
+            _ => return None,
+        };
+
+        Some(lint)
+    }
+}
+
+impl WhitespaceLintConfig {
+    pub fn level(&self, lint: WhitespaceLint) -> LintLevel {
+        match lint {
+            WhitespaceLint::MixedIndentation => self.mixed_indentation,
+            WhitespaceLint::MixedLineEndings => self.mixed_line_endings,
+            WhitespaceLint::TrailingWhitespace => self.trailing_whitespace,
+            WhitespaceLint::LoneCarriageReturn => self.lone_carriage_return,
+        }
+    }
+
+    pub fn set_level(&mut self, lint: WhitespaceLint, level: LintLevel) {
+        match lint {
+            WhitespaceLint::MixedIndentation => self.mixed_indentation = level,
+            WhitespaceLint::MixedLineEndings => self.mixed_line_endings = level,
+            WhitespaceLint::TrailingWhitespace => self.trailing_whitespace = level,
+            WhitespaceLint::LoneCarriageReturn => self.lone_carriage_return = level,
+        }
+    }
+}
+
+impl Default for WhitespaceLintConfig {
+    fn default() -> Self {
+        Self {
+            mixed_indentation: LintLevel::Warn,
+            mixed_line_endings: LintLevel::Warn,
+            trailing_whitespace: LintLevel::Warn,
+            lone_carriage_return: LintLevel::Warn,
+        }
+    }
+}
+
+/// トークン列から空白と改行の不整合を探す
+/// レベルがAllowのものは報告しない
+/// skip_rangesは検査しないリテラルとコメントの範囲 (位置の順)
+pub fn check_whitespace(
+    token_list: &[Token],
+    config: &WhitespaceLintConfig,
+    skip_ranges: &[Range<usize>],
+) -> Vec<WhitespaceDiagnostic> {
+    let mut diagnostics = vec![];
+    let mut file_indentation = None;
+    let mut file_line_ending = None;
+    let mut line_start = true;
+
+    for (index, token) in token_list.iter().enumerate() {
+        if is_skipped(skip_ranges, token.token_pos) {
+            line_start = matches!(token.token_kind, TokenKind::LineBreak(_));
+            continue;
+        }
+
+        match &token.token_kind {
+            TokenKind::WhiteSpace(spaces) => {
+                let span = token.token_pos..token.token_pos + spaces.len();
+                let at_line_end = token_list
+                    .get(index + 1)
+                    .is_none_or(|t| matches!(t.token_kind, TokenKind::LineBreak(_)));
+
+                if at_line_end {
+                    diagnostics.push(make_diagnostic(
+                        WhitespaceLint::TrailingWhitespace,
+                        span,
+                        Some(String::new()),
+                    ));
+                } else if line_start {
+                    let indentation = *file_indentation.get_or_insert(indentation_style(spaces));
+                    if !is_consistent_indentation(spaces, indentation) {
+                        diagnostics.push(make_diagnostic(
+                            WhitespaceLint::MixedIndentation,
+                            span,
+                            convert_indentation(spaces, indentation),
+                        ));
+                    }
+                }
+
+                line_start = false;
+            }
+            TokenKind::LineBreak(line_breaks) => {
+                let mut position = token.token_pos;
+                for line_ending in split_line_endings(line_breaks) {
+                    let span = position..position + line_ending_text(line_ending).len();
+                    position = span.end;
+
+                    if line_ending == LineEnding::Cr {
+                        let expected = file_line_ending.unwrap_or(LineEnding::Lf);
+                        diagnostics.push(make_diagnostic(
+                            WhitespaceLint::LoneCarriageReturn,
+                            span,
+                            Some(line_ending_text(expected).to_string()),
+                        ));
+                        continue;
+                    }
+
+                    let expected = *file_line_ending.get_or_insert(line_ending);
+                    if line_ending != expected {
+                        diagnostics.push(make_diagnostic(
+                            WhitespaceLint::MixedLineEndings,
+                            span,
+                            Some(line_ending_text(expected).to_string()),
+                        ));
+                    }
+                }
+
+                line_start = true;
+            }
+            _ => line_start = false,
+        }
+    }
+
+    diagnostics
+        .into_iter()
+        .filter_map(|diagnostic| {
+            let level = config.level(diagnostic.lint);
+            (level != LintLevel::Allow).then_some(WhitespaceDiagnostic {
+                level,
+                ..diagnostic
+            })
+        })
+        .collect()
+}
+
+/// 診断の修正を適用したソースコードを返す
+pub fn apply_fixes(source_code: &str, diagnostics: &[WhitespaceDiagnostic]) -> String {
+    let mut fixes: Vec<&TextEdit> = diagnostics.iter().filter_map(|d| d.fix.as_ref()).collect();
+    fixes.sort_by_key(|fix| fix.range.start);
+
+    let mut fixed = String::with_capacity(source_code.len());
+    let mut position = 0;
+    for fix in fixes {
+        // 範囲が重なる修正は先のものを優先する
+        if fix.range.start < position {
+            continue;
+        }
+
+        fixed.push_str(&source_code[position..fix.range.start]);
+        fixed.push_str(&fix.replacement);
+        position = fix.range.end;
+    }
+    fixed.push_str(&source_code[position..]);
+
+    fixed
+}
+
+fn make_diagnostic(
+    lint: WhitespaceLint,
+    span: Range<usize>,
+    replacement: Option<String>,
+) -> WhitespaceDiagnostic {
+    WhitespaceDiagnostic {
+        lint,
+        level: LintLevel::Warn,
+        fix: replacement.map(|replacement| TextEdit {
+            range: span.clone(),
+            replacement,
+        }),
+        span,
+    }
+}
+
+// positionがリテラルかコメントの中か
+fn is_skipped(skip_ranges: &[Range<usize>], position: usize) -> bool {
+    let index = skip_ranges.partition_point(|range| range.end <= position);
+    skip_ranges
+        .get(index)
+        .is_some_and(|range| range.contains(&position))
+}
+
+fn indentation_style(spaces: &[Space]) -> Indentation {
+    match spaces.first() {
+        Some(Space::Tab) => Indentation::Tabs,
+        _ => Indentation::Spaces,
+    }
+}
+
+fn is_consistent_indentation(spaces: &[Space], indentation: Indentation) -> bool {
+    let expected = match indentation {
+        Indentation::Spaces => Space::Space,
+        Indentation::Tabs => Space::Tab,
+    };
+
+    spaces.iter().all(|space| *space == expected)
+}
+
+// タブに揃える場合, 幅がタブの幅で割り切れなければ自動では直さない
+fn convert_indentation(spaces: &[Space], indentation: Indentation) -> Option<String> {
+    let width: usize = spaces
+        .iter()
+        .map(|space| match space {
+            Space::Space => 1,
+            Space::Tab => TAB_WIDTH,
+        })
+        .sum();
+
+    match indentation {
+        Indentation::Spaces => Some(" ".repeat(width)),
+        Indentation::Tabs if width.is_multiple_of(TAB_WIDTH) => {
+            Some("\t".repeat(width / TAB_WIDTH))
+        }
+        Indentation::Tabs => None,
+    }
+}
+
+// 改行の塊を1行ごとの改行に分ける
+fn split_line_endings(line_breaks: &[LineBreak]) -> Vec<LineEnding> {
+    let mut line_endings = vec![];
+    let mut iter = line_breaks.iter().peekable();
+    while let Some(line_break) = iter.next() {
+        let line_ending = match line_break {
+            LineBreak::LF => LineEnding::Lf,
+            LineBreak::CR if iter.next_if(|l| **l == LineBreak::LF).is_some() => LineEnding::CrLf,
+            LineBreak::CR => LineEnding::Cr,
+        };
+        line_endings.push(line_ending);
+    }
+
+    line_endings
+}
+
+fn line_ending_text(line_ending: LineEnding) -> &'static str {
+    match line_ending {
+        LineEnding::Lf => "\n",
+        LineEnding::CrLf => "\r\n",
+        LineEnding::Cr => "\r",
+    }
+}
+
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintLevel::Allow => write!(f, "allow"),
+            LintLevel::Warn => write!(f, "warning"),
+            LintLevel::Deny => write!(f, "error"),
+        }
+    }
+}
+
+impl Display for WhitespaceDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Range { start, end } = self.span;
+        let message = match self.lint {
+            WhitespaceLint::MixedIndentation => "Indentation mixes tabs and spaces",
+            WhitespaceLint::MixedLineEndings => "Line ending differs from the rest of the file",
+            WhitespaceLint::TrailingWhitespace => "Trailing whitespace",
+            WhitespaceLint::LoneCarriageReturn => "Lone carriage return",
+        };
+
+        write!(
+            f,
+            "{}: {message} at {start}..{end} [{}]",
+            self.level,
+            self.lint.name()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_fixes, check_whitespace, LintLevel, WhitespaceDiagnostic, WhitespaceLint,
+        WhitespaceLintConfig,
+    };
+    use crate::tokenize;
+    use std::ops::Range;
+
+    fn check(source_code: &str, skip_ranges: &[Range<usize>]) -> Vec<WhitespaceDiagnostic> {
+        let token_list = tokenize(source_code).unwrap();
+        check_whitespace(&token_list, &WhitespaceLintConfig::default(), skip_ranges)
+    }
+
+    fn lints(diagnostics: &[WhitespaceDiagnostic]) -> Vec<WhitespaceLint> {
+        diagnostics.iter().map(|d| d.lint).collect()
+    }
+
+    #[test]
+    fn reports_each_lint() {
+        let source_code = "a\n\tb \r\n    c\rd\n";
+        let diagnostics = check(source_code, &[]);
+        assert_eq!(
+            lints(&diagnostics),
+            [
+                WhitespaceLint::TrailingWhitespace,
+                WhitespaceLint::MixedLineEndings,
+                WhitespaceLint::MixedIndentation,
+                WhitespaceLint::LoneCarriageReturn,
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.level == LintLevel::Warn));
+        assert_eq!(apply_fixes(source_code, &diagnostics), "a\n\tb\n\tc\nd\n");
+    }
+
+    // タブの幅で割り切れないインデントは直さない
+    #[test]
+    fn tab_indentation_fix() {
+        let source_code = "a\n\tb\n    c\n  d\n      e\n";
+        let diagnostics = check(source_code, &[]);
+        let fixes: Vec<_> = diagnostics
+            .iter()
+            .map(|d| d.fix.as_ref().map(|fix| fix.replacement.as_str()))
+            .collect();
+        assert_eq!(fixes, [Some("\t"), None, None]);
+
+        // 修正を繰り返しても変わらない
+        let fixed = apply_fixes(source_code, &diagnostics);
+        assert_eq!(fixed, "a\n\tb\n\tc\n  d\n      e\n");
+        let diagnostics = check(&fixed, &[]);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.fix.is_none()));
+        assert_eq!(apply_fixes(&fixed, &diagnostics), fixed);
+    }
+
+    #[test]
+    fn space_indentation_fix() {
+        let source_code = "a\n  b\n\t c\n";
+        let diagnostics = check(source_code, &[]);
+        assert_eq!(apply_fixes(source_code, &diagnostics), "a\n  b\n     c\n");
+    }
+
+    // 除く範囲の中の空白と改行は報告しない
+    #[test]
+    fn skips_ranges() {
+        let source_code = "a = \"x  \r\n\t  y\";  \n  b\n";
+        let end = source_code.find(';').unwrap();
+        let skip_range = 4..end;
+        let diagnostics = check(source_code, std::slice::from_ref(&skip_range));
+        assert_eq!(lints(&diagnostics), [WhitespaceLint::TrailingWhitespace]);
+        assert_eq!(diagnostics[0].span.start, end + 1);
+
+        // 除いた範囲のインデントはファイルの基準にしない
+        let skip_range = 0..5;
+        let diagnostics = check("\"\n\tx\"\n  y\n", std::slice::from_ref(&skip_range));
+        assert!(diagnostics.is_empty());
+    }
+}
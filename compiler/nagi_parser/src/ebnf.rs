@@ -1,128 +1,82 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Range, RangeInclusive};
 use std::rc::Rc;
 
-pub type EBNFState = u64;
-pub type EBNFStateKey = u16;
+pub mod export;
+pub mod first_follow;
+pub mod interpreter;
+pub mod normalize;
+mod printer;
+pub mod validate;
 
-const DEPTH_BIT_SHIFT: usize = 48; // ツリーの深さ
-const GROUP_BIT_SHIFT: usize = 32; // 同じ深さにおけるグループ番号
-const PARENT_GROUP_BIT_SHIFT: usize = 16;
-const CHILDREN_GROUP_BIT_SHIFT: usize = 0;
-const BIT_MASK: EBNFState = 0xFFFF;
-const DEPTH_BIT_MASK: EBNFState = BIT_MASK << DEPTH_BIT_SHIFT;
-const CHILDREN_GROUP_BIT_MASK: EBNFState = BIT_MASK << CHILDREN_GROUP_BIT_SHIFT;
-const PARENT_GROUP_BIT_MASK: EBNFState = BIT_MASK << PARENT_GROUP_BIT_SHIFT;
-const GROUP_BIT_MASK: EBNFState = BIT_MASK << GROUP_BIT_SHIFT;
+pub type EBNFNodeId = u32;
+pub type EBNFState = EBNFNodeId; // ルールの位置(状態)はノードのID
 
 #[derive(Debug)]
-pub(crate) struct EBNF<'a> {
-    pub name: &'a str,                               // 定義したルール名
-    expr: Rc<EBNFNode<'a>>,                          // ツリー構造(ルールの中身)
-    state_map: HashMap<EBNFState, Rc<EBNFNode<'a>>>, // ルールの位置(状態)に応じたマップ
-    full_state_map: HashMap<EBNFState, EBNFState>,   // 部分的な状態キーから完全な状態へのマップ
+pub struct EBNF<'a> {
+    pub name: &'a str,             // 定義したルール名
+    expr: Rc<EBNFNode<'a>>,        // ツリー構造(ルールの中身)
+    arena: Vec<EBNFArenaNode<'a>>, // 行きがけ順に並べたノード (根が0)
+}
+
+// ノードと, ツリーを移動するためのリンク
+#[derive(Debug)]
+struct EBNFArenaNode<'a> {
+    node: Rc<EBNFNode<'a>>,
+    parent: Option<EBNFNodeId>,
+    next_sibling: Option<EBNFNodeId>,
+    first_child: Option<EBNFNodeId>,
 }
 
 impl<'a> EBNF<'a> {
     pub fn new(name: &'a str, expr: EBNFNode<'a>) -> Self {
         let expr = Rc::new(expr);
-        let state_list = make_state_pair_list(&expr);
-        let mut state_map = HashMap::new();
-        let mut full_state_map = HashMap::new();
-        for (key, value) in state_list {
-            let state_key = key & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
-            state_map.insert(state_key, value);
-            full_state_map.insert(state_key, key);
-        }
+        let arena = make_arena(&expr);
 
-        Self {
-            name,
-            expr,
-            state_map,
-            full_state_map,
-        }
+        Self { name, expr, arena }
     }
 
     pub fn get_node(&self, state: &EBNFState) -> Option<&EBNFNode<'a>> {
-        self.state_map.get(state).map(|node| &**node)
+        self.arena.get(*state as usize).map(|node| &*node.node)
     }
 
     pub fn root(&self) -> EBNFState {
-        make_state_key(0, 0, 0, 0)
+        0
+    }
+
+    /// ルールに含まれるノードの数
+    pub fn node_count(&self) -> usize {
+        self.arena.len()
     }
 
     // 親ノードに移動
     pub fn parent(&self, state: EBNFState) -> Option<EBNFState> {
-        let state = state & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
-        let &full_state = self.full_state_map.get(&state)?;
-        let depth = (full_state & DEPTH_BIT_MASK) >> DEPTH_BIT_SHIFT;
-        if depth == 0 {
-            return None;
-        }
-
-        let parent_depth = depth - 1;
-        let parent_depth_key = parent_depth << DEPTH_BIT_SHIFT;
-        let parent_group_number = (full_state & PARENT_GROUP_BIT_MASK) >> PARENT_GROUP_BIT_SHIFT;
-        let parent_group_key = parent_group_number << GROUP_BIT_SHIFT;
-
-        Some(parent_depth_key | parent_group_key)
+        self.arena.get(state as usize)?.parent
     }
 
     // 隣のノードへ移動
     // 同じグループの末端であれば親ノードの隣のノードへ
     // その親が末端であればさらにその親を見ていく
     pub fn next_group(&self, state: EBNFState) -> Option<EBNFState> {
-        let state = state & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
-        let &full_state = self.full_state_map.get(&state)?;
-        let depth_key = full_state & DEPTH_BIT_MASK;
-        let group = (full_state & GROUP_BIT_MASK) >> GROUP_BIT_SHIFT;
-        let next_group_key = (group + 1) << GROUP_BIT_SHIFT;
-
-        // 単純に1加算した値が次のグループかを判断できないので
-        // 親を参照し親のデータから次のグループに移動するかを判断する
-        let Some(parent_state) = self.parent(state) else {
-            // 親はいないが次のグループが存在する場合
-            let next_state = depth_key | next_group_key;
-            return if self.state_map.contains_key(&next_state) {
-                Some(next_state)
-            } else {
-                None
-            };
-        };
-
-        // 次のグループがない場合親ノードへ
-        let &full_parent_state = self.full_state_map.get(&parent_state)?;
-        let group_start_point =
-            (full_parent_state & CHILDREN_GROUP_BIT_MASK) >> CHILDREN_GROUP_BIT_SHIFT;
-        let parent_node = self.get_node(&parent_state)?;
-        let count = get_child_count(parent_node) as u64;
-        let group_limit = group_start_point + count;
-        let group_position = group + 1;
-        if group_limit < group_position + 1 {
-            // グループが存在しない場合はさらに親を見る
-            return self.next_group(parent_state);
+        let mut current = self.arena.get(state as usize)?;
+        loop {
+            if let Some(next) = current.next_sibling {
+                return Some(next);
+            }
+            current = &self.arena[current.parent? as usize];
         }
-
-        let next_state = depth_key | next_group_key;
-
-        Some(next_state)
     }
 
     // 子のノードに移動する
     // 子ノードがなければ同グループの隣のノードへ
     // グループの末端かつ子ノードがなければ親ノードへ
     pub fn step_in(&self, state: EBNFState) -> Option<(&EBNFNode<'a>, EBNFState)> {
-        let state = state & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
-        let &full_state = self.full_state_map.get(&state)?;
-        let depth = (full_state & DEPTH_BIT_MASK) >> DEPTH_BIT_SHIFT;
-        let child_depth_key = (depth + 1) << DEPTH_BIT_SHIFT;
-        let child_group_key = full_state & CHILDREN_GROUP_BIT_MASK;
-        let child_state = child_depth_key | child_group_key;
-
-        let Some(node) = self.state_map.get(&child_state) else {
+        let Some(child_state) = self.arena.get(state as usize)?.first_child else {
             return self.step_over(state); // 子がいない場合はstep_overと同じ
         };
 
-        Some((node, child_state))
+        Some((self.get_node(&child_state)?, child_state))
     }
 
     // 親ノードの隣のノードへ移動
@@ -142,8 +96,93 @@ impl<'a> EBNF<'a> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub(crate) enum EBNFNode<'a> {
+/// 複数の規則からなる文法
+#[derive(Debug)]
+pub struct Grammar<'a> {
+    source: &'a str,
+    rules: Vec<GrammarRule<'a>>,       // 定義順
+    rule_map: HashMap<&'a str, usize>, // 規則名からrulesの添字へのマップ
+    start: usize,                      // 開始規則の添字
+    terminals: HashSet<String>,        // 規則以外で参照できる終端記号の名前
+}
+
+#[derive(Debug)]
+pub struct GrammarRule<'a> {
+    pub ebnf: EBNF<'a>,
+    pub span: Range<usize>, // 規則名の先頭から ; までの範囲
+}
+
+impl<'a> Grammar<'a> {
+    /// 規則名は重複していない前提
+    /// 最初の規則を開始規則にする
+    pub fn new(source: &'a str, rules: Vec<GrammarRule<'a>>) -> Self {
+        let rule_map: HashMap<_, _> = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| (rule.ebnf.name, i))
+            .collect();
+        debug_assert_eq!(rule_map.len(), rules.len(), "Duplicate rule names");
+
+        Self {
+            source,
+            rules,
+            rule_map,
+            start: 0,
+            terminals: HashSet::new(),
+        }
+    }
+
+    /// IDENT など, トークンの種類を表す終端記号の名前を登録する
+    /// 同名の規則がある場合は規則が優先される
+    pub fn declare_terminals<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.terminals.extend(names.into_iter().map(Into::into));
+    }
+
+    /// 規則ではなく終端記号として扱う名前か
+    pub fn is_terminal(&self, name: &str) -> bool {
+        !self.rule_map.contains_key(name) && self.terminals.contains(name)
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// 規則名やリテラルなど, ソースコードを切り出した文字列の範囲
+    /// ソースコードの一部でない文字列ではNone
+    pub fn span_of(&self, text: &str) -> Option<Range<usize>> {
+        let start = (text.as_ptr() as usize).checked_sub(self.source.as_ptr() as usize)?;
+        let end = start + text.len();
+        (end <= self.source.len()).then_some(start..end)
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&GrammarRule<'a>> {
+        self.rule_map.get(name).map(|&i| &self.rules[i])
+    }
+
+    pub fn rules(&self) -> &[GrammarRule<'a>] {
+        &self.rules
+    }
+
+    pub fn start_rule(&self) -> &GrammarRule<'a> {
+        &self.rules[self.start]
+    }
+
+    /// 開始規則を変更する
+    pub fn set_start_rule(&mut self, name: &str) -> Result<(), String> {
+        let Some(&index) = self.rule_map.get(name) else {
+            return Err(format!("undefined rule: {name}"));
+        };
+        self.start = index;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EBNFNode<'a> {
     Expansion(&'a str),            // Hoge
     Concat(Vec<Rc<EBNFNode<'a>>>), // Hoge Fuga
     Or(Vec<Rc<EBNFNode<'a>>>),     // Hoge | Fuga
@@ -154,9 +193,130 @@ pub(crate) enum EBNFNode<'a> {
         max: Option<u64>,
     },
     Group(Rc<EBNFNode<'a>>), // (Hoge)
-    Literal(&'a str),        // "hogefuga"
+    // "hogefuga" 'hogefuga'
+    // 引用符の中の書いたままの文字列 (エスケープは literal_value で解釈する)
+    // 正規化でリテラルを連結した場合のみ所有する
+    Literal(Cow<'a, str>),
+    // [a-z] [^"\n]
+    Class {
+        text: &'a str, // [ から ] まで
+        class: CharClass,
+    },
+    // Hoge - Fuga (Fugaに一致するものを除いたHoge)
+    Exception {
+        node: Rc<EBNFNode<'a>>,
+        except: Rc<EBNFNode<'a>>,
+    },
+}
+
+/// 文字の範囲の集まり
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CharClass {
+    pub negated: bool, // [^...]
+    pub ranges: Vec<RangeInclusive<char>>,
+}
+
+impl CharClass {
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|range| range.contains(&c)) != self.negated
+    }
+}
+
+/// Literalのエスケープを解釈した文字列
+pub fn literal_value<'a>(raw: &Cow<'a, str>) -> Cow<'a, str> {
+    match raw {
+        Cow::Borrowed(raw) => unescape_literal(raw),
+        Cow::Owned(raw) => Cow::Owned(unescape_literal(raw).into_owned()),
+    }
+}
+
+/// リテラルのエスケープを解釈した文字列
+/// 構文解析で検査済みなので, 不正なエスケープは読み飛ばす
+pub fn unescape_literal(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            value.extend(read_escape(&mut chars));
+        } else {
+            value.push(c);
+        }
+    }
+
+    Cow::Owned(value)
+}
+
+/// 二重引用符で囲んだリテラルとして書ける形にする
+pub fn escape_literal(value: &str) -> String {
+    let mut text = String::with_capacity(value.len() + 2);
+    text.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\r' => text.push_str("\\r"),
+            '\0' => text.push_str("\\0"),
+            _ => text.push(c),
+        }
+    }
+    text.push('"');
+    text
+}
+
+// \ の後ろを読み, エスケープした文字を返す
+// ESCAPE ::= "\\" ( "n" | "t" | "r" | "0" | "\\" | "\"" | "'" | "[" | "]" | "-" | "^" )
+//          | "\\x" HEX_DIGIT HEX_DIGIT
+//          | "\\u{" HEX_DIGIT+ "}"
+pub(crate) fn read_escape(iter: &mut impl Iterator<Item = char>) -> Option<char> {
+    let c = match iter.next()? {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        c @ ('\\' | '"' | '\'' | '[' | ']' | '-' | '^') => c,
+        'x' => {
+            let high = iter.next()?.to_digit(16)?;
+            let low = iter.next()?.to_digit(16)?;
+            let value = high * 16 + low;
+            if value > MAX_ASCII_ESCAPE {
+                return None;
+            }
+            char::from_u32(value)?
+        }
+        'u' => {
+            if iter.next()? != '{' {
+                return None;
+            }
+
+            let mut value = 0;
+            let mut digits = 0;
+            loop {
+                match iter.next()? {
+                    '}' if digits > 0 => break,
+                    c if digits < MAX_UNICODE_ESCAPE_DIGITS => {
+                        value = value * 16 + c.to_digit(16)?;
+                        digits += 1;
+                    }
+                    _ => return None,
+                }
+            }
+            char::from_u32(value)?
+        }
+        _ => return None,
+    };
+
+    Some(c)
 }
 
+const MAX_ASCII_ESCAPE: u32 = 0x7F;
+const MAX_UNICODE_ESCAPE_DIGITS: usize = 6;
+
 #[derive(Debug)]
 pub(crate) enum Quantifier {
     Question,                 // ?
@@ -165,84 +325,274 @@ pub(crate) enum Quantifier {
     Braces(u64, Option<u64>), // {
 }
 
-fn make_state_key(
-    depth: EBNFStateKey,
-    group: EBNFStateKey,
-    parent_group: EBNFStateKey,
-    child_group: EBNFStateKey,
-) -> EBNFState {
-    let depth = (depth as EBNFState) << DEPTH_BIT_SHIFT;
-    let group = (group as EBNFState) << GROUP_BIT_SHIFT;
-    let parent_group = (parent_group as EBNFState) << PARENT_GROUP_BIT_SHIFT;
-    let child_group = (child_group as EBNFState) << CHILDREN_GROUP_BIT_SHIFT;
-    depth | group | parent_group | child_group
-}
-
-fn make_state_pair_list<'a>(expr: &Rc<EBNFNode<'a>>) -> Vec<(EBNFState, Rc<EBNFNode<'a>>)> {
-    let mut group_count_map = HashMap::new();
-    let mut vec = vec![];
-    let mut stack = vec![(expr.clone(), 0, 0)];
-    while let Some((current_node, depth, parent_group_number)) = stack.pop() {
-        let group_number = *group_count_map
-            .entry(depth)
-            .and_modify(|e| *e += 1)
-            .or_insert(0);
-        let child_group_number = group_count_map
-            .get(&(depth + 1))
-            .map(|e| *e + 1)
-            .unwrap_or(0);
-
-        match &*current_node {
-            EBNFNode::Expansion(_) => {
-                let key = make_state_key(depth, group_number, parent_group_number, 0);
-                vec.push((key, current_node));
-            }
-            EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => {
-                let key =
-                    make_state_key(depth, group_number, parent_group_number, child_group_number);
-                vec.push((key, current_node.clone()));
-
-                for node in nodes.iter().rev() {
-                    stack.push((node.clone(), depth + 1, group_number));
+// ノードを行きがけ順に並べ, 親と最初の子と次の兄弟へのリンクを張る
+fn make_arena<'a>(expr: &Rc<EBNFNode<'a>>) -> Vec<EBNFArenaNode<'a>> {
+    let mut arena: Vec<EBNFArenaNode<'a>> = vec![];
+    let mut last_child: Vec<Option<EBNFNodeId>> = vec![]; // ノードごとの直前に追加した子
+    let mut stack = vec![(expr.clone(), None)];
+    while let Some((current_node, parent)) = stack.pop() {
+        let id = EBNFNodeId::try_from(arena.len()).expect("Too many EBNF nodes");
+
+        if let Some(parent) = parent {
+            match last_child[parent as usize].replace(id) {
+                Some(previous) => arena[previous as usize].next_sibling = Some(id),
+                None => arena[parent as usize].first_child = Some(id),
+            }
+        }
+
+        let children: Vec<&Rc<EBNFNode<'a>>> = match &*current_node {
+            EBNFNode::Expansion(_) | EBNFNode::Literal(_) | EBNFNode::Class { .. } => vec![],
+            EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => nodes.iter().collect(),
+            EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => vec![node],
+            EBNFNode::Exception { node, except } => vec![node, except],
+        };
+        for node in children.into_iter().rev() {
+            stack.push((node.clone(), Some(id)));
+        }
+
+        arena.push(EBNFArenaNode {
+            node: current_node,
+            parent,
+            next_sibling: None,
+            first_child: None,
+        });
+        last_child.push(None);
+    }
+
+    arena
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EBNFNode, EBNF};
+    use std::mem::discriminant;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    // 以前の実装 (深さとグループ番号を16bitずつu64に詰めた状態とHashMap) を比較のために残したもの
+    // 以前のstep_inは子のグループ番号をずらさずに状態を作っていたため0番以外のグループの子に移れず,
+    // 子のない葉からも1つ深い段の0番のグループに移ってしまっていた
+    // 同じノードを辿って比べられるよう, この2点だけ直してある
+    mod bit_packed {
+        use super::super::EBNFNode;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        pub type EBNFState = u64;
+        type EBNFStateKey = u16;
+
+        const DEPTH_BIT_SHIFT: usize = 48;
+        const GROUP_BIT_SHIFT: usize = 32;
+        const PARENT_GROUP_BIT_SHIFT: usize = 16;
+        const BIT_MASK: EBNFState = 0xFFFF;
+        const DEPTH_BIT_MASK: EBNFState = BIT_MASK << DEPTH_BIT_SHIFT;
+        const CHILDREN_GROUP_BIT_MASK: EBNFState = BIT_MASK;
+        const PARENT_GROUP_BIT_MASK: EBNFState = BIT_MASK << PARENT_GROUP_BIT_SHIFT;
+        const GROUP_BIT_MASK: EBNFState = BIT_MASK << GROUP_BIT_SHIFT;
+
+        pub struct Rule<'a> {
+            state_map: HashMap<EBNFState, Rc<EBNFNode<'a>>>,
+            full_state_map: HashMap<EBNFState, EBNFState>,
+        }
+
+        impl<'a> Rule<'a> {
+            pub fn new(expr: &Rc<EBNFNode<'a>>) -> Self {
+                let mut state_map = HashMap::new();
+                let mut full_state_map = HashMap::new();
+                for (key, value) in make_state_pair_list(expr) {
+                    let state_key = key & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
+                    state_map.insert(state_key, value);
+                    full_state_map.insert(state_key, key);
+                }
+
+                Self {
+                    state_map,
+                    full_state_map,
                 }
             }
-            EBNFNode::Repeat {
-                node,
-                min: _,
-                max: _,
-            } => {
-                let key =
-                    make_state_key(depth, group_number, parent_group_number, child_group_number);
-                vec.push((key, current_node.clone()));
-                stack.push((node.clone(), depth + 1, group_number));
-            }
-            EBNFNode::Group(node) => {
-                let key =
-                    make_state_key(depth, group_number, parent_group_number, child_group_number);
-                vec.push((key, current_node.clone()));
-                stack.push((node.clone(), depth + 1, group_number));
-            }
-            EBNFNode::Literal(_) => {
-                let key = make_state_key(depth, group_number, parent_group_number, 0);
-                vec.push((key, current_node));
+
+            pub fn root(&self) -> EBNFState {
+                0
+            }
+
+            fn get_node(&self, state: &EBNFState) -> Option<&EBNFNode<'a>> {
+                self.state_map.get(state).map(|node| &**node)
+            }
+
+            fn parent(&self, state: EBNFState) -> Option<EBNFState> {
+                let state = state & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
+                let &full_state = self.full_state_map.get(&state)?;
+                let depth = (full_state & DEPTH_BIT_MASK) >> DEPTH_BIT_SHIFT;
+                if depth == 0 {
+                    return None;
+                }
+
+                let parent_group = (full_state & PARENT_GROUP_BIT_MASK) >> PARENT_GROUP_BIT_SHIFT;
+                Some(((depth - 1) << DEPTH_BIT_SHIFT) | (parent_group << GROUP_BIT_SHIFT))
+            }
+
+            fn next_group(&self, state: EBNFState) -> Option<EBNFState> {
+                let state = state & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
+                let &full_state = self.full_state_map.get(&state)?;
+                let depth_key = full_state & DEPTH_BIT_MASK;
+                let group = (full_state & GROUP_BIT_MASK) >> GROUP_BIT_SHIFT;
+                let next_state = depth_key | ((group + 1) << GROUP_BIT_SHIFT);
+
+                let Some(parent_state) = self.parent(state) else {
+                    return self
+                        .state_map
+                        .contains_key(&next_state)
+                        .then_some(next_state);
+                };
+
+                let &full_parent_state = self.full_state_map.get(&parent_state)?;
+                let group_start_point = full_parent_state & CHILDREN_GROUP_BIT_MASK;
+                let count = get_child_count(self.get_node(&parent_state)?) as u64;
+                if group_start_point + count < group + 2 {
+                    return self.next_group(parent_state);
+                }
+
+                Some(next_state)
+            }
+
+            pub fn step_in(&self, state: EBNFState) -> Option<(&EBNFNode<'a>, EBNFState)> {
+                let state = state & (DEPTH_BIT_MASK | GROUP_BIT_MASK);
+                let &full_state = self.full_state_map.get(&state)?;
+                if get_child_count(self.get_node(&state)?) == 0 {
+                    return self.step_over(state);
+                }
+
+                let depth = (full_state & DEPTH_BIT_MASK) >> DEPTH_BIT_SHIFT;
+                let child_group = full_state & CHILDREN_GROUP_BIT_MASK;
+                let child_state =
+                    ((depth + 1) << DEPTH_BIT_SHIFT) | (child_group << GROUP_BIT_SHIFT);
+                let node = self.state_map.get(&child_state)?;
+
+                Some((node, child_state))
+            }
+
+            fn step_over(&self, state: EBNFState) -> Option<(&EBNFNode<'a>, EBNFState)> {
+                let next_state = self.next_group(state)?;
+                Some((self.get_node(&next_state)?, next_state))
+            }
+        }
+
+        fn make_state_key(
+            depth: EBNFStateKey,
+            group: EBNFStateKey,
+            parent_group: EBNFStateKey,
+            child_group: EBNFStateKey,
+        ) -> EBNFState {
+            ((depth as EBNFState) << DEPTH_BIT_SHIFT)
+                | ((group as EBNFState) << GROUP_BIT_SHIFT)
+                | ((parent_group as EBNFState) << PARENT_GROUP_BIT_SHIFT)
+                | child_group as EBNFState
+        }
+
+        fn make_state_pair_list<'a>(expr: &Rc<EBNFNode<'a>>) -> Vec<(EBNFState, Rc<EBNFNode<'a>>)> {
+            let mut group_count_map = HashMap::new();
+            let mut list = vec![];
+            let mut stack = vec![(expr.clone(), 0, 0)];
+            while let Some((current_node, depth, parent_group)) = stack.pop() {
+                let group = *group_count_map
+                    .entry(depth)
+                    .and_modify(|e| *e += 1)
+                    .or_insert(0);
+                let child_group = group_count_map.get(&(depth + 1)).map_or(0, |e| *e + 1);
+
+                let children: Vec<&Rc<EBNFNode<'a>>> = match &*current_node {
+                    EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => nodes.iter().collect(),
+                    EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => vec![node],
+                    _ => vec![],
+                };
+                let child_group = if children.is_empty() { 0 } else { child_group };
+                for node in children.into_iter().rev() {
+                    stack.push((node.clone(), depth + 1, group));
+                }
+
+                let key = make_state_key(depth, group, parent_group, child_group);
+                list.push((key, current_node));
+            }
+
+            list
+        }
+
+        fn get_child_count(node: &EBNFNode) -> usize {
+            match node {
+                EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => nodes.len(),
+                EBNFNode::Repeat { .. } | EBNFNode::Group(_) => 1,
+                _ => 0,
             }
         }
     }
 
-    vec.into_iter().collect()
-}
+    // 100個の選択それぞれに99個のリテラルと1つの繰り返しか規則名を持つ, 10201ノードの規則
+    fn synthetic_expr() -> EBNFNode<'static> {
+        let alternatives = (0..100)
+            .map(|i| {
+                let mut nodes: Vec<Rc<EBNFNode>> = (0..99)
+                    .map(|_| Rc::new(EBNFNode::Literal("x".into())))
+                    .collect();
+                nodes.push(Rc::new(match i % 2 {
+                    0 => EBNFNode::Repeat {
+                        node: Rc::new(EBNFNode::Group(Rc::new(EBNFNode::Expansion("A")))),
+                        min: 0,
+                        max: None,
+                    },
+                    _ => EBNFNode::Expansion("B"),
+                }));
+                Rc::new(EBNFNode::Or(nodes))
+            })
+            .collect();
+
+        EBNFNode::Concat(alternatives)
+    }
+
+    // cargo test --release -- --ignored --nocapture bench で実行する
+    #[test]
+    #[ignore]
+    fn bench_traversal() {
+        const WALKS: usize = 20;
+        let expr = synthetic_expr();
+
+        let start = Instant::now();
+        let bit_packed = bit_packed::Rule::new(&Rc::new(expr.clone()));
+        let bit_packed_build = start.elapsed();
+        let start = Instant::now();
+        let arena = EBNF::new("S", expr);
+        let arena_build = start.elapsed();
+        assert_eq!(arena.node_count(), 10201);
+
+        let start = Instant::now();
+        let mut bit_packed_nodes = vec![];
+        for _ in 0..WALKS {
+            bit_packed_nodes.clear();
+            let mut state = bit_packed.root();
+            while let Some((node, next)) = bit_packed.step_in(state) {
+                bit_packed_nodes.push(discriminant(node));
+                state = next;
+            }
+        }
+        let bit_packed_walk = start.elapsed();
+
+        let start = Instant::now();
+        let mut arena_nodes = vec![];
+        for _ in 0..WALKS {
+            arena_nodes.clear();
+            let mut state = arena.root();
+            while let Some((node, next)) = arena.step_in(state) {
+                arena_nodes.push(discriminant(node));
+                state = next;
+            }
+        }
+        let arena_walk = start.elapsed();
 
-fn get_child_count<'a>(node: &EBNFNode<'a>) -> usize {
-    match node {
-        EBNFNode::Expansion(_) => 0,
-        EBNFNode::Or(nodes) => nodes.len(),
-        EBNFNode::Concat(nodes) => nodes.len(),
-        EBNFNode::Repeat {
-            node: _,
-            min: _,
-            max: _,
-        } => 1,
-        EBNFNode::Group(_) => 1,
-        EBNFNode::Literal(_) => 0,
+        // 同じ順にノードを辿る
+        assert_eq!(arena_nodes.len(), arena.node_count() - 1);
+        assert_eq!(arena_nodes, bit_packed_nodes);
+        eprintln!(
+            "{} nodes, {WALKS} walks: bit-packed build {bit_packed_build:?} walk {bit_packed_walk:?}, arena build {arena_build:?} walk {arena_walk:?}",
+            arena.node_count()
+        );
     }
 }
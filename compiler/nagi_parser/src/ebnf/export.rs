@@ -0,0 +1,666 @@
+use super::{
+    literal_value, normalize::NormalizeOptions, printer::Precedence, CharClass, EBNFNode, Grammar,
+};
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+// 他のパーサ生成器の文法への変換
+// 正規化した規則を開始規則から順に出力する
+//
+// 大文字だけの名前の規則と文字クラスを含む規則は字句の規則として扱う
+// (ANTLR4 の lexer rule, Lark の terminal)
+// 規則として定義されていない名前 (IDENT など) はトークンとして宣言する
+//
+// 対応する書き方がないもの:
+// - {n,m} は ANTLR4 と W3C では x x (x x?)? のように展開する
+// - Hoge - Fuga は両方が文字の集合であれば差の集合にし, そうでなければ W3C 以外では除外を省いて注記を付ける
+// - 空のリテラルは選択肢であれば残りの選択肢を省略可能にし, それ以外は空のグループ () にする
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Antlr4, // .g4
+    Lark,   // .lark
+    W3c,    // XML仕様のEBNF
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "antlr" | "antlr4" => Some(ExportFormat::Antlr4),
+            "lark" => Some(ExportFormat::Lark),
+            "w3c" => Some(ExportFormat::W3c),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Antlr4 => "g4",
+            ExportFormat::Lark => "lark",
+            ExportFormat::W3c => "ebnf",
+        }
+    }
+}
+
+impl Grammar<'_> {
+    /// grammar_nameは ANTLR4 の grammar 宣言にのみ使う
+    pub fn export(&self, format: ExportFormat, grammar_name: &str) -> String {
+        let grammar = self.normalize(NormalizeOptions::default());
+        Exporter::new(&grammar, format).export(grammar_name)
+    }
+}
+
+struct Exporter<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    format: ExportFormat,
+    lexical: HashSet<&'a str>,       // 字句の規則
+    names: HashMap<&'a str, String>, // 規則名から出力する名前へのマップ
+    notes: Vec<String>,              // 出力中の規則に付ける注記
+}
+
+impl<'g, 'a> Exporter<'g, 'a> {
+    fn new(grammar: &'g Grammar<'a>, format: ExportFormat) -> Self {
+        let lexical: HashSet<_> = grammar
+            .rules()
+            .iter()
+            .filter(|rule| {
+                !rule.ebnf.name.chars().any(char::is_lowercase) || contains_class(&rule.ebnf.expr)
+            })
+            .map(|rule| rule.ebnf.name)
+            .collect();
+
+        // 書式の規則に合わせた名前が重複する場合は _ を付ける
+        let mut used = HashSet::new();
+        if format == ExportFormat::Lark && grammar.start_rule().ebnf.name != "start" {
+            used.insert("start".to_string()); // 開始規則の別名に使う
+        }
+        let mut names = HashMap::new();
+        for rule in grammar.rules() {
+            let name = rule.ebnf.name;
+            let mut exported = export_name(format, name, lexical.contains(name));
+            while !used.insert(exported.clone()) {
+                exported.push('_');
+            }
+            names.insert(name, exported);
+        }
+
+        Self {
+            grammar,
+            format,
+            lexical,
+            names,
+            notes: vec![],
+        }
+    }
+
+    fn export(&mut self, grammar_name: &str) -> String {
+        let grammar = self.grammar;
+        let mut lines = vec![];
+
+        let tokens = self.undefined_names();
+        match self.format {
+            ExportFormat::Antlr4 => {
+                // 構文の規則がなければ字句解析器だけの文法にする
+                let lexer_only = grammar
+                    .rules()
+                    .iter()
+                    .all(|rule| self.lexical.contains(rule.ebnf.name));
+                let kind = if lexer_only {
+                    "lexer grammar"
+                } else {
+                    "grammar"
+                };
+                lines.push(format!("{kind} {grammar_name};"));
+                lines.push(String::new());
+                if !tokens.is_empty() {
+                    lines.push(format!("tokens {{ {} }}", tokens.join(", ")));
+                    lines.push(String::new());
+                }
+            }
+            ExportFormat::Lark => {
+                let start = &self.names[grammar.start_rule().ebnf.name];
+                if start != "start" {
+                    lines.push(format!("start: {start}"));
+                    lines.push(String::new());
+                }
+                if !tokens.is_empty() {
+                    lines.push(format!("%declare {}", tokens.join(" ")));
+                    lines.push(String::new());
+                }
+            }
+            ExportFormat::W3c => (),
+        }
+
+        let fragments = self.fragments();
+        let start = grammar.start_rule();
+        let rest = grammar
+            .rules()
+            .iter()
+            .filter(|rule| !std::ptr::eq(*rule, start));
+        for rule in std::iter::once(start).chain(rest) {
+            let name = self.names[rule.ebnf.name].clone();
+            let (body, _) = self.expr(&rule.ebnf.expr);
+
+            for note in self.notes.drain(..) {
+                lines.push(match self.format {
+                    ExportFormat::Antlr4 | ExportFormat::Lark => format!("// {note}"),
+                    ExportFormat::W3c => format!("/* {note} */"),
+                });
+            }
+
+            lines.push(match self.format {
+                ExportFormat::Antlr4 if fragments.contains(rule.ebnf.name) => {
+                    format!("fragment {name} : {body} ;")
+                }
+                ExportFormat::Antlr4 => format!("{name} : {body} ;"),
+                ExportFormat::Lark => format!("{name}: {body}"),
+                ExportFormat::W3c => format!("{name} ::= {body}"),
+            });
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    // 規則として定義されていない参照 (出現順)
+    fn undefined_names(&self) -> Vec<String> {
+        let mut names = vec![];
+        for rule in self.grammar.rules() {
+            collect_expansions(&rule.ebnf.expr, &mut |name| {
+                if self.grammar.rule(name).is_none() && !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            });
+        }
+        names
+    }
+
+    // ANTLR4 で fragment にする, 構文の規則から参照されない字句の規則
+    fn fragments(&self) -> HashSet<&'a str> {
+        let mut referenced = HashSet::from([self.grammar.start_rule().ebnf.name]);
+        for rule in self.grammar.rules() {
+            if !self.lexical.contains(rule.ebnf.name) {
+                collect_expansions(&rule.ebnf.expr, &mut |name| {
+                    referenced.insert(name);
+                });
+            }
+        }
+
+        self.lexical
+            .iter()
+            .filter(|name| !referenced.contains(*name))
+            .copied()
+            .collect()
+    }
+
+    fn expr(&mut self, node: &EBNFNode<'a>) -> (String, Precedence) {
+        match node {
+            EBNFNode::Expansion(name) => {
+                let name = self
+                    .names
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| name.to_string());
+                (name, Precedence::Primary)
+            }
+            // 空の文字列は書けない書式があるので空のグループにする
+            EBNFNode::Literal(raw) if raw.is_empty() => ("()".to_string(), Precedence::Primary),
+            EBNFNode::Literal(raw) => self.literal(&literal_value(raw)),
+            EBNFNode::Class { class, .. } => (self.class(class), Precedence::Primary),
+            EBNFNode::Group(node) => {
+                let (text, _) = self.expr(node);
+                (format!("({text})"), Precedence::Primary)
+            }
+            EBNFNode::Or(nodes) => {
+                // 空のリテラルの選択肢は除き, 残りを省略可能にする
+                let (has_empty, rest) = split_empty(nodes);
+                if !has_empty {
+                    let items: Vec<_> = nodes
+                        .iter()
+                        .map(|node| self.operand(node, Precedence::Concat))
+                        .collect();
+                    return (items.join(" | "), Precedence::Or);
+                }
+
+                match self.choice_operand(rest) {
+                    Some(operand) => self.repeat(&operand, 0, Some(1)),
+                    None => ("()".to_string(), Precedence::Primary),
+                }
+            }
+            EBNFNode::Concat(nodes) => {
+                let items: Vec<_> = nodes
+                    .iter()
+                    .map(|node| self.operand(node, Precedence::Concat))
+                    .collect();
+                (items.join(" "), Precedence::Concat)
+            }
+            EBNFNode::Exception { node, except } => {
+                if let (Some(set), Some(except_set)) = (char_set(node), char_set(except)) {
+                    let class = to_class(difference(&set, &except_set));
+                    return (self.class(&class), Precedence::Primary);
+                }
+
+                if self.format == ExportFormat::W3c {
+                    let node = self.operand(node, Precedence::Repeat);
+                    let except = self.operand(except, Precedence::Repeat);
+                    return (format!("{node} - {except}"), Precedence::Exception);
+                }
+
+                self.notes
+                    .push(format!("exception dropped: {} - {}", node, except));
+                self.expr(node)
+            }
+            EBNFNode::Repeat { node, min, max } => {
+                // (x | "")? や (x | "")* は空の選択肢を除いても同じ
+                if let (0, EBNFNode::Or(nodes)) = (min, &**node)
+                    && let (true, rest) = split_empty(nodes)
+                {
+                    return match self.choice_operand(rest) {
+                        Some(operand) => self.repeat(&operand, 0, *max),
+                        None => ("()".to_string(), Precedence::Primary),
+                    };
+                }
+
+                let operand = self.operand(node, Precedence::Primary);
+                self.repeat(&operand, *min, *max)
+            }
+        }
+    }
+
+    // 空の選択肢を除いた残りの選択肢を1つの項にする
+    fn choice_operand(&mut self, nodes: Vec<Rc<EBNFNode<'a>>>) -> Option<String> {
+        match nodes.as_slice() {
+            [] => None,
+            [node] => Some(self.operand(node, Precedence::Primary)),
+            _ => Some(self.operand(&EBNFNode::Or(nodes), Precedence::Primary)),
+        }
+    }
+
+    // minより弱い場合は括弧で囲む
+    fn operand(&mut self, node: &EBNFNode<'a>, min: Precedence) -> String {
+        let (text, node_precedence) = self.expr(node);
+        if node_precedence < min {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    fn repeat(&self, operand: &str, min: u64, max: Option<u64>) -> (String, Precedence) {
+        match (min, max) {
+            (0, Some(1)) => return (format!("{operand}?"), Precedence::Repeat),
+            (0, None) => return (format!("{operand}*"), Precedence::Repeat),
+            (1, None) => return (format!("{operand}+"), Precedence::Repeat),
+            _ => (),
+        }
+
+        if self.format == ExportFormat::Lark {
+            return match max {
+                Some(max) if max == min => (format!("{operand}~{min}"), Precedence::Repeat),
+                Some(max) => (format!("{operand}~{min}..{max}"), Precedence::Repeat),
+                None => (format!("{operand}~{min} {operand}*"), Precedence::Concat),
+            };
+        }
+
+        // x{2,} → x x+, x{1,3} → x (x x?)?
+        let mut items = vec![operand.to_string(); min.saturating_sub(1) as usize];
+        match max {
+            None => items.push(format!("{operand}+")),
+            Some(max) => {
+                if min > 0 {
+                    items.push(operand.to_string());
+                }
+                let mut optional = String::new();
+                for _ in min..max {
+                    optional = if optional.is_empty() {
+                        format!("{operand}?")
+                    } else {
+                        format!("({operand} {optional})?")
+                    };
+                }
+                if !optional.is_empty() {
+                    items.push(optional);
+                }
+            }
+        }
+
+        match items.len() {
+            0 => ("()".to_string(), Precedence::Primary),
+            1 => (items.pop().unwrap(), Precedence::Repeat),
+            _ => (items.join(" "), Precedence::Concat),
+        }
+    }
+
+    fn literal(&self, value: &str) -> (String, Precedence) {
+        match self.format {
+            ExportFormat::Antlr4 => (
+                format!("'{}'", escape_chars(value, &['\'', '\\'])),
+                Precedence::Primary,
+            ),
+            ExportFormat::Lark => (
+                format!("\"{}\"", escape_chars(value, &['"', '\\'])),
+                Precedence::Primary,
+            ),
+            ExportFormat::W3c => w3c_literal(value),
+        }
+    }
+
+    fn class(&self, class: &CharClass) -> String {
+        let mut items = String::new();
+        for range in &class.ranges {
+            let (start, end) = (*range.start(), *range.end());
+            // W3Cでは範囲の両端を同じ形で書く (#x2F-9 ではなく #x2F-#x39)
+            let bare = start.is_ascii_alphanumeric() && end.is_ascii_alphanumeric();
+            items.push_str(&self.class_char(start, bare));
+            if start != end {
+                items.push('-');
+                items.push_str(&self.class_char(end, bare));
+            }
+        }
+
+        match (self.format, class.negated) {
+            (ExportFormat::Antlr4, false) => format!("[{items}]"),
+            (ExportFormat::Antlr4, true) => format!("~[{items}]"),
+            (ExportFormat::Lark, false) => format!("/[{items}]/"),
+            (ExportFormat::Lark, true) => format!("/[^{items}]/"),
+            (ExportFormat::W3c, false) => format!("[{items}]"),
+            (ExportFormat::W3c, true) => format!("[^{items}]"),
+        }
+    }
+
+    // 文字クラスの中の1文字
+    // bareでなければW3Cでは #xN で書く
+    fn class_char(&self, c: char, bare: bool) -> String {
+        match self.format {
+            ExportFormat::Antlr4 => escape_chars(&c.to_string(), &[']', '\\', '-']),
+            ExportFormat::Lark => escape_chars(&c.to_string(), &[']', '[', '\\', '-', '^', '/']),
+            ExportFormat::W3c if bare => c.to_string(),
+            ExportFormat::W3c => format!("#x{:X}", c as u32),
+        }
+    }
+}
+
+fn export_name(format: ExportFormat, name: &str, lexical: bool) -> String {
+    match (format, lexical) {
+        (ExportFormat::Antlr4, true) => change_first_char(name, char::to_ascii_uppercase),
+        (ExportFormat::Antlr4, false) => change_first_char(name, char::to_ascii_lowercase),
+        (ExportFormat::Lark, true) => snake_case(name).to_uppercase(),
+        (ExportFormat::Lark, false) => snake_case(name),
+        (ExportFormat::W3c, _) => name.to_string(),
+    }
+}
+
+fn change_first_char(name: &str, f: fn(&char) -> char) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|c| f(&c))
+        .into_iter()
+        .chain(chars)
+        .collect()
+}
+
+// FuncDecl → func_decl, BIN_DIGIT → bin_digit
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()) {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+        previous = Some(c);
+    }
+    snake
+}
+
+// 引用符の中で使える形にする
+fn escape_chars(value: &str, special: &[char]) -> String {
+    let mut text = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\n' => text.push_str("\\n"),
+            '\r' => text.push_str("\\r"),
+            '\t' => text.push_str("\\t"),
+            c if special.contains(&c) => {
+                text.push('\\');
+                text.push(c);
+            }
+            c if c.is_control() => text.push_str(&format!("\\u{:04X}", c as u32)),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+// W3C のリテラルにはエスケープがないので, 引用符と制御文字で区切って連結する
+fn w3c_literal(value: &str) -> (String, Precedence) {
+    let mut items: Vec<String> = vec![];
+    let mut current = String::new();
+    let flush = |current: &mut String, items: &mut Vec<String>| {
+        if current.is_empty() {
+            return;
+        }
+        let quote = if current.contains('"') { '\'' } else { '"' };
+        items.push(format!("{quote}{current}{quote}"));
+        current.clear();
+    };
+
+    for c in value.chars() {
+        if c.is_control() {
+            flush(&mut current, &mut items);
+            items.push(format!("#x{:X}", c as u32));
+        } else {
+            // 両方の引用符を含む場合は区切る
+            let other = if c == '"' { '\'' } else { '"' };
+            if matches!(c, '"' | '\'') && current.contains(other) {
+                flush(&mut current, &mut items);
+            }
+            current.push(c);
+        }
+    }
+    flush(&mut current, &mut items);
+
+    match items.len() {
+        0 => ("\"\"".to_string(), Precedence::Primary),
+        1 => (items.pop().unwrap(), Precedence::Primary),
+        _ => (items.join(" "), Precedence::Concat),
+    }
+}
+
+// 空のリテラルの選択肢があるかと, それ以外の選択肢
+fn split_empty<'a>(nodes: &[Rc<EBNFNode<'a>>]) -> (bool, Vec<Rc<EBNFNode<'a>>>) {
+    let is_empty =
+        |node: &Rc<EBNFNode>| matches!(&**node, EBNFNode::Literal(raw) if raw.is_empty());
+    let rest: Vec<_> = nodes
+        .iter()
+        .filter(|node| !is_empty(node))
+        .cloned()
+        .collect();
+    (rest.len() < nodes.len(), rest)
+}
+
+fn contains_class(node: &EBNFNode) -> bool {
+    match node {
+        EBNFNode::Class { .. } => true,
+        EBNFNode::Expansion(_) | EBNFNode::Literal(_) => false,
+        EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => nodes.iter().any(|n| contains_class(n)),
+        EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => contains_class(node),
+        EBNFNode::Exception { node, except } => contains_class(node) || contains_class(except),
+    }
+}
+
+fn collect_expansions<'a, F: FnMut(&'a str)>(node: &EBNFNode<'a>, f: &mut F) {
+    match node {
+        EBNFNode::Expansion(name) => f(name),
+        EBNFNode::Literal(_) | EBNFNode::Class { .. } => (),
+        EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => {
+            for node in nodes {
+                collect_expansions(node, f);
+            }
+        }
+        EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => collect_expansions(node, f),
+        EBNFNode::Exception { node, except } => {
+            collect_expansions(node, f);
+            collect_expansions(except, f);
+        }
+    }
+}
+
+type CharRanges = Vec<RangeInclusive<char>>;
+
+// 1文字の集合として表せるノードの, 重ならず昇順に並んだ範囲
+fn char_set(node: &EBNFNode) -> Option<CharRanges> {
+    match node {
+        EBNFNode::Class { class, .. } => {
+            let ranges = union(class.ranges.clone());
+            Some(if class.negated {
+                complement(&ranges)
+            } else {
+                ranges
+            })
+        }
+        EBNFNode::Literal(raw) => {
+            let value = literal_value(raw);
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(vec![c..=c]),
+                _ => None,
+            }
+        }
+        EBNFNode::Or(nodes) => {
+            let mut ranges = vec![];
+            for node in nodes {
+                ranges.extend(char_set(node)?);
+            }
+            Some(union(ranges))
+        }
+        EBNFNode::Group(node) => char_set(node),
+        EBNFNode::Exception { node, except } => {
+            Some(difference(&char_set(node)?, &char_set(except)?))
+        }
+        EBNFNode::Expansion(_) | EBNFNode::Concat(_) | EBNFNode::Repeat { .. } => None,
+    }
+}
+
+fn union(mut ranges: CharRanges) -> CharRanges {
+    ranges.sort_by_key(|range| *range.start());
+    let mut merged: CharRanges = vec![];
+    for range in ranges {
+        if let Some(last) = merged.last_mut()
+            && next_char(*last.end()).is_none_or(|next| *range.start() <= next)
+        {
+            if range.end() > last.end() {
+                *last = *last.start()..=*range.end();
+            }
+            continue;
+        }
+        merged.push(range);
+    }
+    merged
+}
+
+fn complement(ranges: &[RangeInclusive<char>]) -> CharRanges {
+    let mut result = vec![];
+    let mut start = Some('\0');
+    for range in ranges {
+        if let Some(current) = start
+            && current < *range.start()
+        {
+            result.push(current..=prev_char(*range.start()).unwrap());
+        }
+        start = next_char(*range.end());
+    }
+    if let Some(start) = start {
+        result.push(start..=char::MAX);
+    }
+    result
+}
+
+fn difference(ranges: &[RangeInclusive<char>], except: &[RangeInclusive<char>]) -> CharRanges {
+    let except = complement(except);
+    let mut result = vec![];
+    for range in ranges {
+        for other in &except {
+            let start = *range.start().max(other.start());
+            let end = *range.end().min(other.end());
+            if start <= end {
+                result.push(start..=end);
+            }
+        }
+    }
+    result
+}
+
+// 範囲の数が少ない方の書き方にする
+fn to_class(ranges: CharRanges) -> CharClass {
+    let inverse = complement(&ranges);
+    if inverse.len() < ranges.len() {
+        CharClass {
+            negated: true,
+            ranges: inverse,
+        }
+    } else {
+        CharClass {
+            negated: false,
+            ranges,
+        }
+    }
+}
+
+// サロゲートの範囲は文字ではないので飛ばす
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        c => char::from_u32(c as u32 + 1),
+    }
+}
+
+fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        c => char::from_u32((c as u32).checked_sub(1)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExportFormat;
+    use crate::ebnf_parser::parse_grammar;
+
+    // 期待する出力は tests/golden/sample.* にある
+    const SAMPLE: &str = r#"Program ::= FuncDecl+;
+FuncDecl ::= "fn" IDENT "(" Params? ")" Block;
+Params ::= IDENT ("," IDENT){0,3};
+Block ::= "{" Stmt{2,} "}" | "{" Stmt{1,3} "}" | "{" Stmt{2} "}";
+Stmt ::= "let" IDENT "=" Expr ";" | Expr - "x" ";" | 'say "it\'s"' | "\t" | ";" | "";
+Expr ::= Sign NUMBER | IDENT;
+Sign ::= "+" | "-" | "";
+Empty ::= "";
+NUMBER ::= DIGIT+ ("." DIGIT+ | "")?;
+DIGIT ::= [0-9] - "5" | "5";
+LETTER ::= [^a-z\]\-/] - [0-9];
+"#;
+
+    #[test]
+    fn golden() {
+        let grammar = parse_grammar(SAMPLE).unwrap();
+        for (format, expected) in [
+            (
+                ExportFormat::Antlr4,
+                include_str!("../../tests/golden/sample.g4"),
+            ),
+            (
+                ExportFormat::Lark,
+                include_str!("../../tests/golden/sample.lark"),
+            ),
+            (
+                ExportFormat::W3c,
+                include_str!("../../tests/golden/sample.ebnf"),
+            ),
+        ] {
+            assert_eq!(grammar.export(format, "Sample"), expected, "{format:?}");
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use super::{escape_literal, literal_value, validate::is_nullable, EBNFNode, Grammar};
+use crate::ebnf_parser::point_at;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+use std::ops::Range;
+
+// FIRST集合とFOLLOW集合, LL(1)の衝突の検出
+// 終端記号はリテラルと文字クラスと規則として定義されていない名前(IDENT など)で, 空のリテラルは空列として扱う
+// Hoge - Fuga は Hoge の集合で近似する
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Terminal<'a> {
+    Literal(Cow<'a, str>), // エスケープを解釈した文字列
+    Class(&'a str),        // [a-z]
+    Named(&'a str),        // IDENT などトークンの種類
+    EndOfInput,            // 開始規則の後ろ
+}
+
+pub type TerminalSet<'a> = BTreeSet<Terminal<'a>>;
+
+impl Display for Terminal<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Terminal::Literal(literal) => write!(f, "{}", escape_literal(literal)),
+            Terminal::Class(text) => write!(f, "{text}"),
+            Terminal::Named(name) => write!(f, "{name}"),
+            Terminal::EndOfInput => write!(f, "EOF"),
+        }
+    }
+}
+
+/// 規則ごとのFIRST集合とFOLLOW集合
+/// 各ノードの集合は first_of と follow_of で求める
+#[derive(Debug)]
+pub struct FirstFollow<'a> {
+    pub first: HashMap<&'a str, TerminalSet<'a>>,
+    pub follow: HashMap<&'a str, TerminalSet<'a>>,
+    pub nullable: HashSet<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionKind {
+    Alternative { first: usize, second: usize }, // Or の選択肢の添字 (0始まり)
+    Repeat,                                      // 繰り返しを続けるか抜けるか
+}
+
+/// 1つ先読みで選択できない分岐
+#[derive(Debug, PartialEq, Eq)]
+pub struct LL1Conflict {
+    pub rule: String,
+    pub kind: DecisionKind,
+    pub terminals: Vec<String>,
+    pub span: Range<usize>, // 分岐のノードの範囲
+}
+
+impl LL1Conflict {
+    pub fn error_message(&self, source: &str) -> String {
+        [format!("error: {self}"), point_at(source, self.span.start)].join("\n")
+    }
+}
+
+impl Display for LL1Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let terminals = self.terminals.join(", ");
+        match self.kind {
+            DecisionKind::Alternative { first, second } => write!(
+                f,
+                "LL(1) conflict in rule {}: alternatives {} and {} both start with {terminals}",
+                self.rule,
+                first + 1,
+                second + 1
+            ),
+            DecisionKind::Repeat => write!(
+                f,
+                "LL(1) conflict in rule {}: repetition can both continue and end on {terminals}",
+                self.rule
+            ),
+        }
+    }
+}
+
+impl<'a> Grammar<'a> {
+    pub fn first_follow(&self) -> FirstFollow<'a> {
+        let nullable = self.nullable_rules();
+
+        // 変化がなくなるまで繰り返す
+        let mut first: HashMap<&'a str, TerminalSet<'a>> = self
+            .rules
+            .iter()
+            .map(|rule| (rule.ebnf.name, TerminalSet::new()))
+            .collect();
+        loop {
+            let mut changed = false;
+            for rule in &self.rules {
+                let set = first_of_node(&rule.ebnf.expr, &first, &nullable);
+                let current = first.entry(rule.ebnf.name).or_default();
+                if set.len() != current.len() {
+                    *current = set;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let mut follow: HashMap<&'a str, TerminalSet<'a>> = self
+            .rules
+            .iter()
+            .map(|rule| (rule.ebnf.name, TerminalSet::new()))
+            .collect();
+        follow
+            .entry(self.start_rule().ebnf.name)
+            .or_default()
+            .insert(Terminal::EndOfInput);
+
+        let mut first_follow = FirstFollow {
+            first,
+            follow,
+            nullable,
+        };
+        loop {
+            let mut changed = false;
+            for rule in &self.rules {
+                let mut updates = vec![];
+                let rule_follow = &first_follow.follow[rule.ebnf.name];
+                first_follow.visit(&rule.ebnf.expr, rule_follow, &mut |node, node_follow, _| {
+                    if let EBNFNode::Expansion(name) = node {
+                        updates.push((*name, node_follow.clone()));
+                    }
+                });
+
+                for (name, node_follow) in updates {
+                    let Some(set) = first_follow.follow.get_mut(name) else {
+                        continue; // 未定義の規則
+                    };
+                    let count = set.len();
+                    set.extend(node_follow);
+                    changed |= set.len() != count;
+                }
+            }
+
+            if !changed {
+                return first_follow;
+            }
+        }
+    }
+
+    /// 全ての Or と繰り返しの分岐を検査し, 1つ先読みで選べないものを返す
+    pub fn ll1_conflicts(&self) -> Vec<LL1Conflict> {
+        let first_follow = self.first_follow();
+
+        let mut conflicts = vec![];
+        for rule in &self.rules {
+            let rule_follow = &first_follow.follow[rule.ebnf.name];
+            first_follow.visit(&rule.ebnf.expr, rule_follow, &mut |node, follow, ff| {
+                let span = self.node_span(node);
+                let mut push = |kind, terminals: TerminalSet| {
+                    if terminals.is_empty() {
+                        return;
+                    }
+                    conflicts.push(LL1Conflict {
+                        rule: rule.ebnf.name.to_string(),
+                        kind,
+                        terminals: terminals.iter().map(|t| t.to_string()).collect(),
+                        span: span.clone(),
+                    });
+                };
+
+                match node {
+                    EBNFNode::Or(nodes) => {
+                        // 空列に一致する選択肢はFOLLOWでも選ばれる
+                        let predict: Vec<TerminalSet> = nodes
+                            .iter()
+                            .map(|node| {
+                                let mut set = ff.first_of(node);
+                                if ff.is_nullable(node) {
+                                    set.extend(follow.iter().cloned());
+                                }
+                                set
+                            })
+                            .collect();
+
+                        for first in 0..predict.len() {
+                            for second in first + 1..predict.len() {
+                                push(
+                                    DecisionKind::Alternative { first, second },
+                                    &predict[first] & &predict[second],
+                                );
+                            }
+                        }
+                    }
+                    EBNFNode::Repeat { node, min, max } if *max != Some(*min) => {
+                        push(DecisionKind::Repeat, &ff.first_of(node) & follow);
+                    }
+                    _ => (),
+                }
+            });
+        }
+
+        conflicts
+    }
+
+    // ノードの中のリテラルと規則名の範囲を合わせたもの (括弧は含まない)
+    fn node_span(&self, node: &EBNFNode<'a>) -> Range<usize> {
+        self.source_span(node).unwrap_or(0..0)
+    }
+
+    // 正規化で作ったリテラルなど, ソースコードにないものは含めない
+    pub(super) fn source_span(&self, node: &EBNFNode<'a>) -> Option<Range<usize>> {
+        let nodes = match node {
+            EBNFNode::Expansion(name) => return self.span_of(name),
+            EBNFNode::Literal(Cow::Borrowed(literal)) => {
+                let span = self.span_of(literal)?;
+                return Some(span.start - 1..span.end + 1); // 引用符を含める
+            }
+            EBNFNode::Literal(Cow::Owned(_)) => return None,
+            EBNFNode::Class { text, .. } => return self.span_of(text),
+            EBNFNode::Exception { node, except } => vec![node, except],
+            EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => nodes.iter().collect(),
+            EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => vec![node],
+        };
+
+        let spans: Vec<_> = nodes.iter().filter_map(|n| self.source_span(n)).collect();
+        let start = spans.iter().map(|s| s.start).min()?;
+        let end = spans.iter().map(|s| s.end).max()?;
+        Some(start..end)
+    }
+}
+
+impl<'a> FirstFollow<'a> {
+    /// ノードの先頭に現れうる終端記号
+    pub fn first_of(&self, node: &EBNFNode<'a>) -> TerminalSet<'a> {
+        first_of_node(node, &self.first, &self.nullable)
+    }
+
+    pub fn is_nullable(&self, node: &EBNFNode<'a>) -> bool {
+        is_nullable(node, &self.nullable)
+    }
+
+    /// ruleの中のnodeの直後に現れうる終端記号
+    pub fn follow_of(
+        &self,
+        expr: &EBNFNode<'a>,
+        rule_follow: &TerminalSet<'a>,
+        node: &EBNFNode<'a>,
+    ) -> TerminalSet<'a> {
+        let mut result = TerminalSet::new();
+        self.visit(expr, rule_follow, &mut |current, follow, _| {
+            if std::ptr::eq(current, node) {
+                result.extend(follow.iter().cloned());
+            }
+        });
+        result
+    }
+
+    // 全てのノードをそのFOLLOW集合と共に訪れる
+    fn visit<'b, F>(&self, node: &'b EBNFNode<'a>, follow: &TerminalSet<'a>, f: &mut F)
+    where
+        F: FnMut(&'b EBNFNode<'a>, &TerminalSet<'a>, &Self),
+    {
+        f(node, follow, self);
+
+        match node {
+            EBNFNode::Expansion(_) | EBNFNode::Literal(_) | EBNFNode::Class { .. } => (),
+            EBNFNode::Concat(nodes) => {
+                // 後ろから順に, 残りの部分のFIRST集合を積み上げる
+                let mut rest_follow = follow.clone();
+                let mut follows = vec![];
+                for node in nodes.iter().rev() {
+                    follows.push(rest_follow.clone());
+                    let mut next = self.first_of(node);
+                    if self.is_nullable(node) {
+                        next.extend(rest_follow);
+                    }
+                    rest_follow = next;
+                }
+
+                for (node, follow) in nodes.iter().zip(follows.iter().rev()) {
+                    self.visit(node, follow, f);
+                }
+            }
+            EBNFNode::Or(nodes) => {
+                for node in nodes {
+                    self.visit(node, follow, f);
+                }
+            }
+            EBNFNode::Repeat {
+                node: inner, max, ..
+            } => {
+                // 2回以上繰り返せる場合は中身の後ろに中身が続く
+                let mut inner_follow = follow.clone();
+                if *max != Some(1) {
+                    inner_follow.extend(self.first_of(inner));
+                }
+                self.visit(inner, &inner_follow, f);
+            }
+            EBNFNode::Group(inner) => self.visit(inner, follow, f),
+            EBNFNode::Exception { node, except } => {
+                self.visit(node, follow, f);
+                self.visit(except, follow, f);
+            }
+        }
+    }
+}
+
+// firstに含まれない規則名は終端記号として扱う
+fn first_of_node<'a>(
+    node: &EBNFNode<'a>,
+    first: &HashMap<&'a str, TerminalSet<'a>>,
+    nullable: &HashSet<&'a str>,
+) -> TerminalSet<'a> {
+    match node {
+        EBNFNode::Expansion(name) => first
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| TerminalSet::from([Terminal::Named(name)])),
+        EBNFNode::Literal(literal) if literal.is_empty() => TerminalSet::new(),
+        EBNFNode::Literal(literal) => {
+            TerminalSet::from([Terminal::Literal(literal_value(literal))])
+        }
+        EBNFNode::Class { text, .. } => TerminalSet::from([Terminal::Class(text)]),
+        EBNFNode::Concat(nodes) => {
+            let mut set = TerminalSet::new();
+            for node in nodes {
+                set.extend(first_of_node(node, first, nullable));
+                if !is_nullable(node, nullable) {
+                    break;
+                }
+            }
+            set
+        }
+        EBNFNode::Or(nodes) => nodes
+            .iter()
+            .flat_map(|node| first_of_node(node, first, nullable))
+            .collect(),
+        EBNFNode::Repeat { node, .. }
+        | EBNFNode::Group(node)
+        | EBNFNode::Exception { node, .. } => first_of_node(node, first, nullable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DecisionKind, LL1Conflict, TerminalSet};
+    use crate::ebnf::EBNFNode;
+    use crate::ebnf_parser::parse_grammar;
+
+    const SOURCE: &str = "S ::= A \"end\";\nA ::= \"a\" B | \"a\" \"c\" | \"d\"?;\nB ::= \"b\"* \"b\"?;\nL ::= (\"x\" \"y\")* \"x\";";
+
+    fn show(set: Option<&TerminalSet>) -> String {
+        set.into_iter()
+            .flatten()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn first_and_follow_sets() {
+        let grammar = parse_grammar(SOURCE).unwrap();
+        let first_follow = grammar.first_follow();
+
+        assert_eq!(show(first_follow.first.get("S")), "\"a\" \"d\" \"end\"");
+        assert_eq!(show(first_follow.first.get("A")), "\"a\" \"d\"");
+        assert_eq!(show(first_follow.first.get("B")), "\"b\"");
+        assert_eq!(show(first_follow.follow.get("S")), "EOF");
+        assert_eq!(show(first_follow.follow.get("A")), "\"end\"");
+        assert_eq!(show(first_follow.follow.get("B")), "\"end\"");
+        // 開始規則から使われない規則のFOLLOWは空
+        assert_eq!(show(first_follow.follow.get("L")), "");
+
+        let mut nullable: Vec<_> = first_follow.nullable.iter().copied().collect();
+        nullable.sort();
+        assert_eq!(nullable, ["A", "B"]);
+    }
+
+    // 繰り返しの中身の後ろには中身の先頭か繰り返しの後ろが続く
+    #[test]
+    fn follow_of_node() {
+        let grammar = parse_grammar(SOURCE).unwrap();
+        let first_follow = grammar.first_follow();
+        let rule = grammar.rule("L").unwrap();
+        let EBNFNode::Concat(nodes) = &*rule.ebnf.expr else {
+            panic!("{:?}", rule.ebnf.expr);
+        };
+        let EBNFNode::Repeat { node: body, .. } = &*nodes[0] else {
+            panic!("{:?}", nodes[0]);
+        };
+        let rule_follow = TerminalSet::new();
+
+        let follow = first_follow.follow_of(&rule.ebnf.expr, &rule_follow, &nodes[0]);
+        assert_eq!(show(Some(&follow)), "\"x\"");
+        let follow = first_follow.follow_of(&rule.ebnf.expr, &rule_follow, body);
+        assert_eq!(show(Some(&follow)), "\"x\"");
+        let follow = first_follow.follow_of(&rule.ebnf.expr, &rule_follow, &nodes[1]);
+        assert_eq!(show(Some(&follow)), "");
+    }
+
+    #[test]
+    fn ll1_conflicts() {
+        let grammar = parse_grammar(SOURCE).unwrap();
+        let conflicts = grammar.ll1_conflicts();
+        let spans: Vec<_> = conflicts
+            .iter()
+            .map(|conflict| &SOURCE[conflict.span.clone()])
+            .collect();
+        assert_eq!(
+            spans,
+            ["\"a\" B | \"a\" \"c\" | \"d\"", "\"b\"", "\"x\" \"y\""]
+        );
+
+        let conflicts: Vec<_> = conflicts
+            .into_iter()
+            .map(|conflict| LL1Conflict {
+                span: 0..0,
+                ..conflict
+            })
+            .collect();
+        let conflict = |rule: &str, kind, terminals: &[&str]| LL1Conflict {
+            rule: rule.to_string(),
+            kind,
+            terminals: terminals.iter().map(|t| t.to_string()).collect(),
+            span: 0..0,
+        };
+        assert_eq!(
+            conflicts,
+            [
+                conflict(
+                    "A",
+                    DecisionKind::Alternative {
+                        first: 0,
+                        second: 1
+                    },
+                    &["\"a\""]
+                ),
+                conflict("B", DecisionKind::Repeat, &["\"b\""]),
+                conflict("L", DecisionKind::Repeat, &["\"x\""]),
+            ]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "LL(1) conflict in rule A: alternatives 1 and 2 both start with \"a\""
+        );
+        assert_eq!(
+            conflicts[1].to_string(),
+            "LL(1) conflict in rule B: repetition can both continue and end on \"b\""
+        );
+    }
+
+    // 空列に一致する選択肢はFOLLOWと衝突する
+    #[test]
+    fn nullable_alternative_conflicts_with_follow() {
+        let grammar = parse_grammar("S ::= A \"x\"; A ::= \"x\" | \"y\"?;").unwrap();
+        let conflicts = grammar.ll1_conflicts();
+        assert_eq!(conflicts.len(), 1, "{conflicts:?}");
+        assert_eq!(
+            conflicts[0].to_string(),
+            "LL(1) conflict in rule A: alternatives 1 and 2 both start with \"x\""
+        );
+
+        let grammar = parse_grammar("E ::= T (\"+\" T)*; T ::= \"n\" | \"(\" E \")\";").unwrap();
+        assert_eq!(grammar.ll1_conflicts(), []);
+    }
+}
@@ -0,0 +1,631 @@
+use super::{escape_literal, unescape_literal, validate::GrammarDiagnostic, EBNFNode, Grammar};
+use crate::{
+    ebnf_parser::point_at,
+    errors::TokenStreamParseError,
+    lexer::{
+        code::{
+            keywords::{KeywordLocale, NagiCodeKeyword},
+            nagi_code_lexer::{
+                tokenize_program_with_keywords, NagiIdentifier, NagiLiteral, NagiProgramToken,
+                NagiProgramTokenKind,
+            },
+        },
+        Lexer, Memo, RuleId,
+    },
+};
+use std::{collections::BTreeSet, fmt::Display, ops::Range};
+
+// 文法をそのまま解釈してトークン列を構文解析する
+// 選択は先に書いたものを優先し, 繰り返しは可能な限り繰り返す (PEGと同じ)
+// 規則ごとの結果はpackrat方式でメモする
+//
+// リテラルはトークンの表記と比較し, 規則として定義されていない IDENT などの名前はトークンの種類と比較する
+// 文字クラスは1文字のトークンに一致する
+//
+// EBNF::step_in などの状態の移動は使わず, ノードを再帰で辿る
+// 状態はツリー上の位置しか持たないため, 選択の分岐ごとの巻き戻し位置や繰り返しの回数を別に積む必要があり,
+// また選択の子からのstep_overは次の選択肢に進んでしまうので連接と同じ扱いになる
+
+/// 規則名の代わりに使えるトークンの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Ident,
+    Integer,
+    Float,
+    String,
+    RawString,
+    Char,
+    Byte,
+    ByteString,
+    Label,
+    DocComment,
+    Attribute,
+}
+
+const TOKEN_CLASSES: [(&str, TokenClass); 11] = [
+    ("IDENT", TokenClass::Ident),
+    ("INTEGER", TokenClass::Integer),
+    ("FLOAT", TokenClass::Float),
+    ("STRING", TokenClass::String),
+    ("RAW_STRING", TokenClass::RawString),
+    ("CHAR", TokenClass::Char),
+    ("BYTE", TokenClass::Byte),
+    ("BYTE_STRING", TokenClass::ByteString),
+    ("LABEL", TokenClass::Label),
+    ("DOC_COMMENT", TokenClass::DocComment),
+    ("ATTRIBUTE", TokenClass::Attribute),
+];
+
+impl TokenClass {
+    pub fn from_name(name: &str) -> Option<Self> {
+        TOKEN_CLASSES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, class)| *class)
+    }
+
+    pub fn name(&self) -> &'static str {
+        TOKEN_CLASSES
+            .iter()
+            .find(|(_, class)| class == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    pub fn matches(&self, token_kind: &NagiProgramTokenKind) -> bool {
+        use NagiProgramTokenKind as K;
+        match token_kind {
+            K::Identifier(NagiIdentifier::Identifier(_)) => *self == TokenClass::Ident,
+            K::Identifier(NagiIdentifier::Keyword(_)) => false,
+            K::Literal(literal) => match literal {
+                NagiLiteral::Integer { .. } => *self == TokenClass::Integer,
+                NagiLiteral::Float { .. } => *self == TokenClass::Float,
+                NagiLiteral::String { .. } => *self == TokenClass::String,
+                NagiLiteral::RawString { .. } => *self == TokenClass::RawString,
+                NagiLiteral::Char { .. } => *self == TokenClass::Char,
+                NagiLiteral::Byte { .. } => *self == TokenClass::Byte,
+                NagiLiteral::ByteString { .. } => *self == TokenClass::ByteString,
+            },
+            K::Label(_) => *self == TokenClass::Label,
+            K::DocComment(_) => *self == TokenClass::DocComment,
+            K::Attribute(_) => *self == TokenClass::Attribute,
+            K::Operator(_) | K::Symbol(_) => false,
+        }
+    }
+}
+
+/// Grammar::declare_terminals に渡す名前
+pub fn token_class_names() -> impl Iterator<Item = &'static str> {
+    TOKEN_CLASSES.iter().map(|(name, _)| *name)
+}
+
+/// 規則名のノードとトークンからなる構文木
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree {
+    Node {
+        rule: String,
+        children: Vec<ParseTree>,
+        span: Range<usize>, // 空の入力に一致した場合は次のトークンの位置の空の範囲
+    },
+    Token {
+        text: String,
+        span: Range<usize>,
+    },
+}
+
+impl ParseTree {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            ParseTree::Node { span, .. } | ParseTree::Token { span, .. } => span.clone(),
+        }
+    }
+
+    fn write_tree(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        match self {
+            ParseTree::Node {
+                rule,
+                children,
+                span,
+            } => {
+                writeln!(f, "{indent}{rule} {span:?}")?;
+                for child in children {
+                    child.write_tree(f, depth + 1)?;
+                }
+                Ok(())
+            }
+            ParseTree::Token { text, span } => writeln!(f, "{indent}{text:?} {span:?}"),
+        }
+    }
+}
+
+impl Display for ParseTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+#[derive(Debug)]
+pub enum GrammarParseError {
+    InvalidGrammar(Vec<GrammarDiagnostic>), // 解釈できない文法 (未定義の規則, 左再帰)
+    TokenStreamParse(TokenStreamParseError),
+    UnexpectedToken {
+        position: usize, // 最も先まで解析できた位置
+        expected: Vec<String>,
+    },
+}
+
+impl GrammarParseError {
+    pub fn error_message(&self, grammar_source: &str, source_code: &str) -> String {
+        match self {
+            GrammarParseError::InvalidGrammar(diagnostics) => diagnostics
+                .iter()
+                .map(|d| d.error_message(grammar_source))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            GrammarParseError::TokenStreamParse(e) => e.error_message(source_code),
+            GrammarParseError::UnexpectedToken { position, expected } => [
+                format!("unexpected token, expected: {}", expected.join(", ")),
+                point_at(source_code, *position),
+            ]
+            .join("\n"),
+        }
+    }
+}
+
+/// ソースコードを切り分け, 開始規則で構文解析する
+pub fn parse_source(
+    grammar: &Grammar,
+    source_code: &str,
+    default_locale: Option<KeywordLocale>,
+) -> Result<ParseTree, GrammarParseError> {
+    let token_list = nagi_lexer::tokenize(source_code)
+        .map_err(|e| GrammarParseError::TokenStreamParse(e.into()))?;
+    let mut lexer = tokenize_program_with_keywords(&token_list, default_locale)
+        .map_err(GrammarParseError::TokenStreamParse)?;
+    parse_tokens(grammar, source_code, &mut lexer)
+}
+
+/// トークン列全体を開始規則で構文解析する
+pub(crate) fn parse_tokens(
+    grammar: &Grammar,
+    source_code: &str,
+    lexer: &mut Lexer<NagiProgramToken>,
+) -> Result<ParseTree, GrammarParseError> {
+    check_grammar(grammar)?;
+
+    let mut interpreter = Interpreter {
+        grammar,
+        source_code,
+        farthest: 0,
+        expected: BTreeSet::new(),
+    };
+    let mut memo = Memo::new();
+    let tree = interpreter.parse_rule(lexer, &mut memo, grammar.start);
+
+    match tree {
+        Ok(tree) if lexer.peek().is_none() => Ok(tree),
+        Ok(_) => {
+            let position = interpreter.current_position(lexer);
+            interpreter.fail(position, "EOF".to_string());
+            Err(interpreter.error())
+        }
+        Err(()) => Err(interpreter.error()),
+    }
+}
+
+// 未定義の規則と, 止まらなくなる左再帰を解釈前に弾く
+fn check_grammar(grammar: &Grammar) -> Result<(), GrammarParseError> {
+    let diagnostics: Vec<_> = grammar
+        .validate()
+        .into_iter()
+        .filter(|d| match d {
+            GrammarDiagnostic::UndefinedRule { name, .. } => TokenClass::from_name(name).is_none(),
+            GrammarDiagnostic::LeftRecursion { .. } => true,
+            _ => false,
+        })
+        .collect();
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(GrammarParseError::InvalidGrammar(diagnostics))
+    }
+}
+
+type TokenLexer = Lexer<NagiProgramToken>;
+type TreeMemo = Memo<Result<ParseTree, ()>>;
+type Matched = Result<Vec<ParseTree>, ()>; // 一致したトークンと規則のノード
+
+struct Interpreter<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    source_code: &'g str,
+    farthest: usize,            // 失敗した中で最も先の位置
+    expected: BTreeSet<String>, // farthestで期待していたもの
+}
+
+impl Interpreter<'_, '_> {
+    fn parse_rule(
+        &mut self,
+        lexer: &mut TokenLexer,
+        memo: &mut TreeMemo,
+        index: usize,
+    ) -> Result<ParseTree, ()> {
+        lexer.memoize(memo, index as RuleId, |lexer, memo| {
+            let rule = &self.grammar.rules[index];
+            let start = self.current_position(lexer);
+            let children =
+                lexer.speculate(|lexer| self.parse_node(lexer, memo, &rule.ebnf.expr))?;
+
+            let span = match (children.first(), children.last()) {
+                (Some(first), Some(last)) => first.span().start..last.span().end,
+                _ => start..start,
+            };
+
+            Ok(ParseTree::Node {
+                rule: rule.ebnf.name.to_string(),
+                children,
+                span,
+            })
+        })
+    }
+
+    fn parse_node(
+        &mut self,
+        lexer: &mut TokenLexer,
+        memo: &mut TreeMemo,
+        node: &EBNFNode,
+    ) -> Matched {
+        match node {
+            EBNFNode::Expansion(name) => {
+                if let Some(&index) = self.grammar.rule_map.get(name) {
+                    return Ok(vec![self.parse_rule(lexer, memo, index)?]);
+                }
+
+                let Some(class) = TokenClass::from_name(name) else {
+                    return Err(()); // check_grammarで弾いている
+                };
+                self.match_token(lexer, class.name().to_string(), |_, token| {
+                    class.matches(&token.token_kind)
+                })
+            }
+            EBNFNode::Literal(literal) => self.match_literal(lexer, &unescape_literal(literal)),
+            EBNFNode::Class { text, class } => {
+                self.match_token(lexer, text.to_string(), |source_code, token| {
+                    let mut chars = source_code[token.position..token.end].chars();
+                    matches!((chars.next(), chars.next()), (Some(c), None) if class.contains(c))
+                })
+            }
+            EBNFNode::Exception { node, except } => {
+                // 除外する側は先読みするだけなので, 失敗しても期待するものには含めない
+                let checkpoint = lexer.checkpoint();
+                let (farthest, expected) = (self.farthest, self.expected.clone());
+                let except_end = lexer
+                    .speculate(|lexer| self.parse_node(lexer, memo, except))
+                    .ok()
+                    .map(|_| self.current_position(lexer));
+                (self.farthest, self.expected) = (farthest, expected);
+                lexer.rewind(checkpoint);
+
+                // 同じ範囲に一致する場合は除外する
+                let trees = self.parse_node(lexer, memo, node)?;
+                if except_end == Some(self.current_position(lexer)) {
+                    return Err(());
+                }
+                Ok(trees)
+            }
+            EBNFNode::Concat(nodes) => lexer.speculate(|lexer| {
+                let mut trees = vec![];
+                for node in nodes {
+                    trees.extend(self.parse_node(lexer, memo, node)?);
+                }
+                Ok(trees)
+            }),
+            EBNFNode::Or(nodes) => {
+                for node in nodes {
+                    if let Ok(trees) = lexer.speculate(|lexer| self.parse_node(lexer, memo, node)) {
+                        return Ok(trees);
+                    }
+                }
+                Err(())
+            }
+            EBNFNode::Repeat { node, min, max } => {
+                let mut trees = vec![];
+                let mut count = 0;
+                while max.is_none_or(|max| count < max) {
+                    let position = lexer.get_token_position();
+                    let Ok(matched) = lexer.speculate(|lexer| self.parse_node(lexer, memo, node))
+                    else {
+                        break;
+                    };
+                    trees.extend(matched);
+                    count += 1;
+
+                    // 入力を消費しない繰り返しは何回でも一致するので残りの回数も満たす
+                    if lexer.get_token_position() == position {
+                        count = count.max(*min);
+                        break;
+                    }
+                }
+
+                if count < *min {
+                    return Err(());
+                }
+                Ok(trees)
+            }
+            EBNFNode::Group(node) => self.parse_node(lexer, memo, node),
+        }
+    }
+
+    fn match_literal(&mut self, lexer: &mut TokenLexer, literal: &str) -> Matched {
+        if literal.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Vec<Vec<i32>> の >> のように接着された > は1つずつ使う
+        if literal == ">" {
+            let position = self.current_position(lexer);
+            let Some(span) = lexer.eat_greater_than() else {
+                self.fail(position, "\">\"".to_string());
+                return Err(());
+            };
+            return Ok(vec![ParseTree::Token {
+                text: literal.to_string(),
+                span,
+            }]);
+        }
+
+        self.match_token(lexer, escape_literal(literal), |source_code, token| {
+            if &source_code[token.position..token.end] == literal {
+                return true;
+            }
+
+            // 日本語のキーワードなど, 表記が違っても同じキーワードであれば一致する
+            matches!(
+                (&token.token_kind, NagiCodeKeyword::from_str_with_locale(literal)),
+                (
+                    NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(keyword)),
+                    Some((literal_keyword, _)),
+                ) if *keyword == literal_keyword
+            )
+        })
+    }
+
+    fn match_token<F>(&mut self, lexer: &mut TokenLexer, expected: String, condition: F) -> Matched
+    where
+        F: Fn(&str, &NagiProgramToken) -> bool,
+    {
+        let position = self.current_position(lexer);
+        let Some(token) = lexer.next_if(|token| condition(self.source_code, token)) else {
+            self.fail(position, expected);
+            return Err(());
+        };
+
+        Ok(vec![ParseTree::Token {
+            text: self.source_code[token.position..token.end].to_string(),
+            span: token.position..token.end,
+        }])
+    }
+
+    fn current_position(&self, lexer: &TokenLexer) -> usize {
+        lexer
+            .peek()
+            .map_or(self.source_code.len(), |token| token.position)
+    }
+
+    fn fail(&mut self, position: usize, expected: String) {
+        if position > self.farthest {
+            self.farthest = position;
+            self.expected.clear();
+        }
+        if position == self.farthest {
+            self.expected.insert(expected);
+        }
+    }
+
+    fn error(&self) -> GrammarParseError {
+        GrammarParseError::UnexpectedToken {
+            position: self.farthest,
+            expected: self.expected.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_source, token_class_names, GrammarParseError, ParseTree};
+    use crate::ebnf::validate::GrammarDiagnostic;
+    use crate::ebnf::Grammar;
+    use crate::ebnf_parser::parse_grammar;
+
+    const GRAMMAR: &str = "Program ::= Item*;
+Item ::= \"fn\" IDENT \"(\" Params? \")\" Block;
+Params ::= IDENT \":\" Type (\",\" IDENT \":\" Type)*;
+Type ::= IDENT (\"<\" Type \">\")?;
+Block ::= \"{\" Stmt* \"}\";
+Stmt ::= \"let\" IDENT \"=\" Expr \";\" | Expr \";\";
+Expr ::= Atom (\"+\" Atom)*;
+Atom ::= INTEGER | STRING | IDENT;";
+
+    fn grammar(source: &str) -> Grammar<'_> {
+        let mut grammar = parse_grammar(source).unwrap();
+        grammar.declare_terminals(token_class_names());
+        grammar
+    }
+
+    fn expected_at(source_code: &str) -> (usize, Vec<String>) {
+        match parse_source(&grammar(GRAMMAR), source_code, None) {
+            Err(GrammarParseError::UnexpectedToken { position, expected }) => (position, expected),
+            result => panic!("{result:?}"),
+        }
+    }
+
+    fn invalid_grammar(source: &str) -> Vec<GrammarDiagnostic> {
+        match parse_source(&grammar(source), "fn f() {}", None) {
+            Err(GrammarParseError::InvalidGrammar(diagnostics)) => diagnostics,
+            result => panic!("{result:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_tree() {
+        let source_code = "fn f(a: i32) { let x = 1 + a; \"s\"; }";
+        let tree = parse_source(&grammar(GRAMMAR), source_code, None).unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "Program 0..36
+  Item 0..36
+    \"fn\" 0..2
+    \"f\" 3..4
+    \"(\" 4..5
+    Params 5..11
+      \"a\" 5..6
+      \":\" 6..7
+      Type 8..11
+        \"i32\" 8..11
+    \")\" 11..12
+    Block 13..36
+      \"{\" 13..14
+      Stmt 15..29
+        \"let\" 15..18
+        \"x\" 19..20
+        \"=\" 21..22
+        Expr 23..28
+          Atom 23..24
+            \"1\" 23..24
+          \"+\" 25..26
+          Atom 27..28
+            \"a\" 27..28
+        \";\" 28..29
+      Stmt 30..34
+        Expr 30..33
+          Atom 30..33
+            \"\\\"s\\\"\" 30..33
+        \";\" 33..34
+      \"}\" 35..36
+"
+        );
+
+        // 規則の範囲は前後の空白を含まない
+        let source_code = "\n fn f() {}\n\nfn g() {} ";
+        let ParseTree::Node {
+            rule,
+            children,
+            span,
+        } = parse_source(&grammar(GRAMMAR), source_code, None).unwrap()
+        else {
+            panic!();
+        };
+        assert_eq!(rule, "Program");
+        assert_eq!(span, 2..22);
+        let spans: Vec<_> = children
+            .iter()
+            .map(|child| match child {
+                ParseTree::Node { span, .. } => span.clone(),
+                ParseTree::Token { .. } => panic!("{child}"),
+            })
+            .collect();
+        assert_eq!(spans, [2..11, 13..22]);
+    }
+
+    #[test]
+    fn token_classes() {
+        let class_grammar = grammar("S ::= IDENT INTEGER FLOAT STRING CHAR;");
+        let tree = parse_source(&class_grammar, "x 42 1.5 \"s\" 'c'", None).unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "S 0..16
+  \"x\" 0..1
+  \"42\" 2..4
+  \"1.5\" 5..8
+  \"\\\"s\\\"\" 9..12
+  \"'c'\" 13..16
+"
+        );
+
+        // キーワードは識別子に一致しない
+        assert!(parse_source(&class_grammar, "fn 42 1.5 \"s\" 'c'", None).is_err());
+        assert!(parse_source(&class_grammar, "x y 1.5 \"s\" 'c'", None).is_err());
+
+        // 予約語のリテラルは識別子に一致しない
+        let keyword_grammar = grammar("S ::= \"let\" | IDENT;");
+        for source_code in ["let", "lets"] {
+            let tree = parse_source(&keyword_grammar, source_code, None).unwrap();
+            assert_eq!(
+                tree.to_string(),
+                format!("S 0..{0}\n  \"{source_code}\" 0..{0}\n", source_code.len())
+            );
+        }
+    }
+
+    #[test]
+    fn split_greater_than() {
+        let source_code = "fn f(a: Vec<Vec<i32>>) {}";
+        let tree = parse_source(&grammar(GRAMMAR), source_code, None)
+            .unwrap()
+            .to_string();
+        assert!(tree.contains("\">\" 20..21\n"), "{tree}");
+        assert!(tree.contains("\">\" 19..20\n"), "{tree}");
+
+        let greater_grammar = grammar("S ::= \">\" \">\" \">=\" | \">>\";");
+        let tree = parse_source(&greater_grammar, ">>", None).unwrap();
+        assert_eq!(tree.to_string(), "S 0..2\n  \">>\" 0..2\n");
+        let tree = parse_source(&greater_grammar, ">>>=", None).unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "S 0..4\n  \">\" 0..1\n  \">\" 1..2\n  \">=\" 2..4\n"
+        );
+    }
+
+    #[test]
+    fn expected_tokens() {
+        assert_eq!(
+            expected_at("fn f() { let = 1; }"),
+            (13, vec!["IDENT".to_string()])
+        );
+        assert_eq!(
+            expected_at("fn f() {} }"),
+            (10, vec!["\"fn\"".to_string(), "EOF".to_string()])
+        );
+        assert_eq!(
+            expected_at("fn f(a: i32 b"),
+            (
+                12,
+                vec![
+                    "\")\"".to_string(),
+                    "\",\"".to_string(),
+                    "\"<\"".to_string()
+                ]
+            )
+        );
+        // 最も先まで進んだ位置の候補だけを報告する
+        assert_eq!(
+            expected_at("fn f() { 1 + ; }"),
+            (
+                13,
+                vec![
+                    "IDENT".to_string(),
+                    "INTEGER".to_string(),
+                    "STRING".to_string()
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_grammars() {
+        assert_eq!(
+            invalid_grammar("S ::= A \"fn\";"),
+            [GrammarDiagnostic::UndefinedRule {
+                name: "A".to_string(),
+                span: 6..7,
+            }]
+        );
+        assert_eq!(
+            invalid_grammar("E ::= E \"+\" X | X;\nX ::= IDENT;"),
+            [GrammarDiagnostic::LeftRecursion {
+                cycle: vec!["E".to_string(), "E".to_string()],
+                span: 0..18,
+            }]
+        );
+    }
+}
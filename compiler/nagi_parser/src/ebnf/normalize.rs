@@ -0,0 +1,275 @@
+use super::{escape_literal, literal_value, EBNFNode, Grammar, GrammarRule, EBNF};
+use std::borrow::Cow;
+use std::rc::Rc;
+
+// 文法の正規化
+// 意味を変えずに木を単純にする
+// - Group を除く (括弧は表示するときに必要な所にだけ付ける)
+// - 要素が1つの Concat と Or を除き, 入れ子になった同じ種類のものを平らにする
+// - Concat の中の空のリテラルを除く
+// - (x?)* → x* のように ? * + を重ねた繰り返しを1つにし, x{1} → x にする
+// - 隣接するリテラルを連結する (NormalizeOptions::merge_literals)
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeOptions {
+    // リテラルを1つのトークンとして扱う構文の文法では, 連結すると意味が変わる
+    pub merge_literals: bool,
+}
+
+impl<'a> EBNFNode<'a> {
+    pub fn normalize(&self, options: NormalizeOptions) -> EBNFNode<'a> {
+        match self {
+            EBNFNode::Expansion(_) | EBNFNode::Literal(_) | EBNFNode::Class { .. } => self.clone(),
+            EBNFNode::Group(node) => node.normalize(options),
+            EBNFNode::Concat(nodes) => {
+                let mut items = vec![];
+                for node in nodes {
+                    match node.normalize(options) {
+                        EBNFNode::Concat(inner) => items.extend(inner),
+                        EBNFNode::Literal(literal) if literal.is_empty() => (),
+                        node => items.push(Rc::new(node)),
+                    }
+                }
+
+                if options.merge_literals {
+                    items = merge_literals(items);
+                }
+
+                match items.len() {
+                    0 => EBNFNode::Literal(Cow::Owned(String::new())),
+                    1 => unwrap_rc(items.pop().unwrap()),
+                    _ => EBNFNode::Concat(items),
+                }
+            }
+            EBNFNode::Or(nodes) => {
+                let mut items = vec![];
+                for node in nodes {
+                    match node.normalize(options) {
+                        EBNFNode::Or(inner) => items.extend(inner),
+                        node => items.push(Rc::new(node)),
+                    }
+                }
+
+                if items.len() == 1 {
+                    return unwrap_rc(items.pop().unwrap());
+                }
+                EBNFNode::Or(items)
+            }
+            EBNFNode::Repeat { node, min, max } => {
+                let node = node.normalize(options);
+                if (*min, *max) == (1, Some(1)) {
+                    return node;
+                }
+
+                // 内側と外側がどちらも ? * + であれば1つの繰り返しにできる
+                if let EBNFNode::Repeat {
+                    node: inner,
+                    min: inner_min,
+                    max: inner_max,
+                } = &node
+                    && is_simple_quantifier(*min, *max)
+                    && is_simple_quantifier(*inner_min, *inner_max)
+                {
+                    return EBNFNode::Repeat {
+                        node: inner.clone(),
+                        min: (*min).min(*inner_min),
+                        max: if *max == Some(1) && *inner_max == Some(1) {
+                            Some(1)
+                        } else {
+                            None
+                        },
+                    };
+                }
+
+                EBNFNode::Repeat {
+                    node: Rc::new(node),
+                    min: *min,
+                    max: *max,
+                }
+            }
+            EBNFNode::Exception { node, except } => EBNFNode::Exception {
+                node: Rc::new(node.normalize(options)),
+                except: Rc::new(except.normalize(options)),
+            },
+        }
+    }
+}
+
+impl<'a> EBNF<'a> {
+    pub fn normalize(&self, options: NormalizeOptions) -> EBNF<'a> {
+        EBNF::new(self.name, self.expr.normalize(options))
+    }
+}
+
+impl<'a> Grammar<'a> {
+    /// 全ての規則を正規化した文法
+    /// 規則の範囲はもとのソースコードのものを引き継ぐ
+    pub fn normalize(&self, options: NormalizeOptions) -> Grammar<'a> {
+        let rules = self
+            .rules
+            .iter()
+            .map(|rule| GrammarRule {
+                ebnf: rule.ebnf.normalize(options),
+                span: rule.span.clone(),
+            })
+            .collect();
+
+        Grammar {
+            source: self.source,
+            rules,
+            rule_map: self.rule_map.clone(),
+            start: self.start,
+            terminals: self.terminals.clone(),
+        }
+    }
+}
+
+// ? * + のいずれか
+fn is_simple_quantifier(min: u64, max: Option<u64>) -> bool {
+    matches!((min, max), (0, Some(1)) | (0, None) | (1, None))
+}
+
+fn merge_literals<'a>(items: Vec<Rc<EBNFNode<'a>>>) -> Vec<Rc<EBNFNode<'a>>> {
+    let mut merged: Vec<Rc<EBNFNode<'a>>> = vec![];
+    for item in items {
+        if let (Some(EBNFNode::Literal(previous)), EBNFNode::Literal(literal)) =
+            (merged.last().map(|node| &**node), &*item)
+        {
+            // 一重引用符の中の " などが混ざるので, 値を連結してからエスケープし直す
+            let value = literal_value(previous) + literal_value(literal);
+            let quoted = escape_literal(&value);
+            let raw = quoted[1..quoted.len() - 1].to_string();
+            *merged.last_mut().unwrap() = Rc::new(EBNFNode::Literal(Cow::Owned(raw)));
+            continue;
+        }
+        merged.push(item);
+    }
+    merged
+}
+
+fn unwrap_rc<'a>(node: Rc<EBNFNode<'a>>) -> EBNFNode<'a> {
+    Rc::try_unwrap(node).unwrap_or_else(|node| (*node).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NormalizeOptions;
+    use crate::ebnf::validate::GrammarDiagnostic;
+    use crate::ebnf::EBNFNode;
+    use crate::ebnf_parser::{parse_ebnf, parse_grammar};
+    use std::borrow::Cow;
+
+    fn normalized(source: &str, merge_literals: bool) -> String {
+        let ebnf = parse_ebnf(source).unwrap();
+        ebnf.normalize(NormalizeOptions { merge_literals })
+            .to_string()
+    }
+
+    #[test]
+    fn repeats() {
+        for (source, expected) in [
+            ("S ::= (x?)*;", "S ::= x*;"),
+            ("S ::= (x*)?;", "S ::= x*;"),
+            ("S ::= (x+)?;", "S ::= x*;"),
+            ("S ::= (x?)+;", "S ::= x*;"),
+            ("S ::= (x+)+;", "S ::= x+;"),
+            ("S ::= (x?)?;", "S ::= x?;"),
+            ("S ::= ((\"x\")?)*;", "S ::= \"x\"*;"),
+            ("S ::= x{1};", "S ::= x;"),
+            ("S ::= (x{1}){1};", "S ::= x;"),
+            // {n,m} は重ねても1つにできない
+            ("S ::= (x?){2};", "S ::= (x?){2};"),
+            ("S ::= (x{2,3})*;", "S ::= (x{2,3})*;"),
+        ] {
+            assert_eq!(normalized(source, false), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn flatten() {
+        for (source, expected) in [
+            ("S ::= ((a));", "S ::= a;"),
+            ("S ::= a (b c) (d);", "S ::= a b c d;"),
+            ("S ::= a | (b | c) | (d);", "S ::= a | b | c | d;"),
+            (
+                "S ::= \"b\" | (\"c\" | \"d\") | (\"e\" \"f\") \"g\";",
+                "S ::= \"b\" | \"c\" | \"d\" | \"e\" \"f\" \"g\";",
+            ),
+            // 種類の違うものは平らにしない
+            ("S ::= (a | b) c;", "S ::= (a | b) c;"),
+            ("S ::= (a b) - c;", "S ::= (a b) - c;"),
+            // Concat の中の空のリテラルを除く
+            ("S ::= a \"\" b '';", "S ::= a b;"),
+            ("S ::= \"\" | a;", "S ::= \"\" | a;"),
+        ] {
+            assert_eq!(normalized(source, false), expected, "{source}");
+        }
+
+        let ebnf = parse_ebnf("S ::= ((a) (b (c)));").unwrap();
+        let normalized = ebnf.normalize(NormalizeOptions::default());
+        let EBNFNode::Concat(nodes) = &*normalized.expr else {
+            panic!("{normalized}");
+        };
+        assert_eq!(nodes.len(), 3);
+    }
+
+    #[test]
+    fn merge_literals() {
+        for (source, expected) in [
+            ("S ::= \"a\" \"b\" c \"d\";", "S ::= \"ab\" c \"d\";"),
+            (
+                "S ::= \"e\" (\"f\" \"g\") | \"h\";",
+                "S ::= \"efg\" | \"h\";",
+            ),
+            // 引用符が混ざる場合は値を連結してエスケープし直す
+            ("S ::= \"\\\"\" 'it\\'s' \"\\x41\";", "S ::= \"\\\"it'sA\";"),
+            ("S ::= \"a\" \"\" \"b\";", "S ::= \"ab\";"),
+        ] {
+            assert_eq!(normalized(source, true), expected, "{source}");
+        }
+
+        // 連結しない場合は書いたまま
+        assert_eq!(
+            normalized("S ::= \"a\" \"b\";", false),
+            "S ::= \"a\" \"b\";"
+        );
+
+        let ebnf = parse_ebnf("S ::= \"a\" \"b\";").unwrap();
+        let merged = ebnf.normalize(NormalizeOptions {
+            merge_literals: true,
+        });
+        assert_eq!(
+            *merged.expr,
+            EBNFNode::Literal(Cow::Owned("ab".to_string()))
+        );
+    }
+
+    // 空になった Concat のリテラルはソースコードの一部ではないので, 範囲を持たない
+    #[test]
+    fn empty_concat_has_no_span() {
+        let source = "S ::= (\"\" \"\")* | \"a\";";
+        let grammar = parse_grammar(source).unwrap();
+        let normalized = grammar.normalize(NormalizeOptions::default());
+        assert_eq!(normalized.to_string(), "S ::= \"\"* | \"a\";\n");
+
+        let diagnostics = normalized.validate();
+        assert!(
+            matches!(
+                diagnostics[..],
+                [
+                    GrammarDiagnostic::NullableRule { .. },
+                    GrammarDiagnostic::NullableRepeat { .. }
+                ]
+            ),
+            "{diagnostics:?}"
+        );
+        let spans: Vec<_> = diagnostics.iter().map(|d| d.span()).collect();
+        assert_eq!(spans, [0..21, 0..21]);
+
+        let conflicts = normalized.ll1_conflicts();
+        assert!(
+            conflicts.iter().all(|c| c.span.end <= source.len()),
+            "{conflicts:?}"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use super::{EBNFNode, Grammar, EBNF};
+use std::fmt::{Display, Formatter, Result};
+use std::rc::Rc;
+
+// 文法の表示
+// 解析した文法を表示して再び解析すると同じ木になる
+// 括弧は Group と, 結合の強さから必要な所にだけ付ける (正規化で Group を除いた場合)
+
+impl Display for EBNFNode<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            EBNFNode::Expansion(name) => write!(f, "{name}"),
+            EBNFNode::Literal(raw) => write!(f, "{}", quote_literal(raw)),
+            EBNFNode::Class { text, .. } => write!(f, "{text}"),
+            EBNFNode::Group(node) => write!(f, "({node})"),
+            EBNFNode::Or(nodes) => write_list(f, nodes, " | ", Precedence::Concat),
+            EBNFNode::Concat(nodes) => write_list(f, nodes, " ", Precedence::Exception),
+            EBNFNode::Exception { node, except } => {
+                write_operand(f, node, Precedence::Repeat)?;
+                write!(f, " - ")?;
+                write_operand(f, except, Precedence::Repeat)
+            }
+            EBNFNode::Repeat { node, min, max } => {
+                write_operand(f, node, Precedence::Primary)?;
+                write!(f, "{}", quantifier(*min, *max))
+            }
+        }
+    }
+}
+
+impl Display for EBNF<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} ::= {};", self.name, self.expr)
+    }
+}
+
+/// 規則ごとに1行で, ::= の位置を揃えて表示する
+/// 開始規則を先頭にする
+impl Display for Grammar<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let width = self
+            .rules
+            .iter()
+            .map(|rule| rule.ebnf.name.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let start = self.start_rule();
+        let rest = self.rules.iter().filter(|rule| !std::ptr::eq(*rule, start));
+        for rule in std::iter::once(start).chain(rest) {
+            writeln!(f, "{:width$} ::= {};", rule.ebnf.name, rule.ebnf.expr)?;
+        }
+        Ok(())
+    }
+}
+
+// 結合の強さ (後ろほど強い)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Precedence {
+    Or,
+    Concat,
+    Exception,
+    Repeat,
+    Primary,
+}
+
+pub(super) fn precedence(node: &EBNFNode) -> Precedence {
+    match node {
+        EBNFNode::Or(_) => Precedence::Or,
+        EBNFNode::Concat(_) => Precedence::Concat,
+        EBNFNode::Exception { .. } => Precedence::Exception,
+        EBNFNode::Repeat { .. } => Precedence::Repeat,
+        EBNFNode::Expansion(_)
+        | EBNFNode::Literal(_)
+        | EBNFNode::Class { .. }
+        | EBNFNode::Group(_) => Precedence::Primary,
+    }
+}
+
+fn write_list(
+    f: &mut Formatter<'_>,
+    nodes: &[Rc<EBNFNode>],
+    separator: &str,
+    min: Precedence,
+) -> Result {
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{separator}")?;
+        }
+        write_operand(f, node, min)?;
+    }
+    Ok(())
+}
+
+// minより弱いノードは括弧で囲む
+// 同じ種類の Or や Concat も囲まなければ再び解析したときに平らになる
+fn write_operand(f: &mut Formatter<'_>, node: &EBNFNode, min: Precedence) -> Result {
+    if precedence(node) < min {
+        write!(f, "({node})")
+    } else {
+        write!(f, "{node}")
+    }
+}
+
+// Literalを書いたままの文字列で表示する
+// 二重引用符をエスケープせずに含む場合は一重引用符で囲む
+fn quote_literal(raw: &str) -> String {
+    let mut escaped = false;
+    let has_double_quote = raw.chars().any(|c| {
+        let found = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        found
+    });
+
+    let quote = if has_double_quote { '\'' } else { '"' };
+    format!("{quote}{raw}{quote}")
+}
+
+// Repeat の min と max を ? * + {n} {n,} {n,m} で表す
+fn quantifier(min: u64, max: Option<u64>) -> String {
+    match (min, max) {
+        (0, Some(1)) => "?".to_string(),
+        (0, None) => "*".to_string(),
+        (1, None) => "+".to_string(),
+        (min, None) => format!("{{{min},}}"),
+        (min, Some(max)) if min == max => format!("{{{min}}}"),
+        (min, Some(max)) => format!("{{{min},{max}}}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ebnf::normalize::NormalizeOptions;
+    use crate::ebnf::Grammar;
+    use crate::ebnf_parser::parse_grammar;
+
+    const SOURCES: [&str; 4] = [
+        r#"S ::= ("a" | B)* - "x" C{2,} D{3} E{1,4} '"' 'it\'s' "\x41" [^\n\]-] (F - G)+ ((H));
+B ::= "b" | ("c" | "d") | ("e" "f") "g"; C ::= (("x")?)*; D ::= "q"; E ::= ""; F ::= "f"; G ::= "g"; H ::= "h";"#,
+        r#"A ::= (B - C) - D; B ::= B1 - (C | D)?; C ::= "c"; D ::= "d"; B1 ::= "b";"#,
+        r#"Expr ::= Term (("+" | "-") Term)*; Term ::= [0-9]+ | "(" Expr ")";"#,
+        r#"S ::= "a" ("b" "c") | ("d" | "e") "f" | ("g")* ("h" "i")? (("j"));"#,
+    ];
+
+    fn assert_same_rules(grammar: &Grammar, reparsed: &Grammar, printed: &str) {
+        let names: Vec<_> = grammar.rules().iter().map(|rule| rule.ebnf.name).collect();
+        let reparsed_names: Vec<_> = reparsed.rules().iter().map(|rule| rule.ebnf.name).collect();
+        assert_eq!(names, reparsed_names, "{printed}");
+        for (rule, reparsed) in grammar.rules().iter().zip(reparsed.rules()) {
+            assert_eq!(rule.ebnf.expr, reparsed.ebnf.expr, "{printed}");
+        }
+    }
+
+    // 表示して解析し直すと同じ木になる
+    #[test]
+    fn round_trip() {
+        for source in SOURCES {
+            let grammar = parse_grammar(source).unwrap();
+            let printed = grammar.to_string();
+            let reparsed = parse_grammar(&printed).unwrap_or_else(|e| panic!("{printed}\n{e}"));
+            assert_same_rules(&grammar, &reparsed, &printed);
+            assert_eq!(reparsed.to_string(), printed);
+        }
+    }
+
+    // 正規化で Group を除いた木も, 必要な括弧を付けて表示する
+    #[test]
+    fn normalized_round_trip() {
+        for source in SOURCES {
+            for merge_literals in [false, true] {
+                let options = NormalizeOptions { merge_literals };
+                let normalized = parse_grammar(source).unwrap().normalize(options);
+                let printed = normalized.to_string();
+                let reparsed = parse_grammar(&printed)
+                    .unwrap_or_else(|e| panic!("{printed}\n{e}"))
+                    .normalize(options);
+                assert_same_rules(&normalized, &reparsed, &printed);
+            }
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let grammar = parse_grammar(
+            "Expr ::= Term+;\nTerm ::= \"(\" Expr \")\" | [0-9];\nS ::= Expr - \"0\";",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.to_string(),
+            "Expr ::= Term+;\nTerm ::= \"(\" Expr \")\" | [0-9];\nS    ::= Expr - \"0\";\n"
+        );
+
+        // 開始規則を先頭にする
+        let mut grammar = grammar;
+        grammar.set_start_rule("S").unwrap();
+        assert_eq!(
+            grammar.to_string(),
+            "S    ::= Expr - \"0\";\nExpr ::= Term+;\nTerm ::= \"(\" Expr \")\" | [0-9];\n"
+        );
+
+        // 正規化した木には結合の強さから括弧を付ける
+        let grammar = parse_grammar(
+            "S ::= ((\"a\" | \"b\") \"c\")* | \"d\" (\"e\" - \"f\") | (\"g\" | \"h\"){2,3} | 'x\"y';",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.normalize(NormalizeOptions::default()).to_string(),
+            "S ::= ((\"a\" | \"b\") \"c\")* | \"d\" \"e\" - \"f\" | (\"g\" | \"h\"){2,3} | 'x\"y';\n"
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use super::{EBNFNode, Grammar, GrammarRule};
+use crate::ebnf_parser::point_at;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::ops::Range;
+
+// 文法の静的な検査
+// 規則の参照を解決し, 構文解析が止まらなくなる規則などを報告する
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GrammarDiagnostic {
+    // 定義されていない規則の参照
+    UndefinedRule {
+        name: String,
+        span: Range<usize>,
+    },
+    // 開始規則から辿れない規則
+    UnusedRule {
+        name: String,
+        span: Range<usize>,
+    },
+    // 入力を消費せずに自分自身に戻る規則 (cycleは先頭と末尾が同じ規則)
+    LeftRecursion {
+        cycle: Vec<String>,
+        span: Range<usize>,
+    },
+    // 空の入力に一致する規則
+    NullableRule {
+        name: String,
+        span: Range<usize>,
+    },
+    // 中身が空の入力に一致する上限のない繰り返し
+    NullableRepeat {
+        rule: String,
+        span: Range<usize>, // 繰り返しの中身の範囲
+    },
+    // {5,2} のように下限が上限より大きい繰り返し
+    InvalidQuantifier {
+        rule: String,
+        min: u64,
+        max: u64,
+        span: Range<usize>, // 繰り返しの中身の範囲
+    },
+}
+
+impl GrammarDiagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            GrammarDiagnostic::UnusedRule { .. } | GrammarDiagnostic::NullableRule { .. } => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+
+    /// 参照や繰り返しの位置, もしくは規則全体の範囲
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            GrammarDiagnostic::UndefinedRule { span, .. }
+            | GrammarDiagnostic::UnusedRule { span, .. }
+            | GrammarDiagnostic::LeftRecursion { span, .. }
+            | GrammarDiagnostic::NullableRule { span, .. }
+            | GrammarDiagnostic::NullableRepeat { span, .. }
+            | GrammarDiagnostic::InvalidQuantifier { span, .. } => span.clone(),
+        }
+    }
+
+    pub fn error_message(&self, source: &str) -> String {
+        let severity = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        [
+            format!("{severity}: {self}"),
+            point_at(source, self.span().start),
+        ]
+        .join("\n")
+    }
+}
+
+impl Display for GrammarDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrammarDiagnostic::UndefinedRule { name, .. } => {
+                write!(f, "undefined rule: {name}")
+            }
+            GrammarDiagnostic::UnusedRule { name, .. } => {
+                write!(f, "rule is never used: {name}")
+            }
+            GrammarDiagnostic::LeftRecursion { cycle, .. } => {
+                write!(f, "left recursion: {}", cycle.join(" -> "))
+            }
+            GrammarDiagnostic::NullableRule { name, .. } => {
+                write!(f, "rule matches empty input: {name}")
+            }
+            GrammarDiagnostic::NullableRepeat { rule, .. } => {
+                write!(
+                    f,
+                    "repetition body matches empty input and never terminates in rule: {rule}"
+                )
+            }
+            GrammarDiagnostic::InvalidQuantifier { rule, min, max, .. } => {
+                write!(
+                    f,
+                    "quantifier {{{min},{max}}} has min greater than max in rule: {rule}"
+                )
+            }
+        }
+    }
+}
+
+impl<'a> Grammar<'a> {
+    /// 文法を検査し, 見つかった問題を種類ごとに規則の定義順で返す
+    pub fn validate(&self) -> Vec<GrammarDiagnostic> {
+        let mut diagnostics = vec![];
+
+        for rule in &self.rules {
+            let mut references = vec![];
+            collect_references(&rule.ebnf.expr, &mut references);
+            for name in references {
+                if self.rule(name).is_none() && !self.is_terminal(name) {
+                    diagnostics.push(GrammarDiagnostic::UndefinedRule {
+                        name: name.to_string(),
+                        span: self.span_of(name).unwrap_or(rule.span.clone()),
+                    });
+                }
+            }
+        }
+
+        let reachable = self.reachable_rules();
+        for rule in &self.rules {
+            if !reachable.contains(rule.ebnf.name) {
+                diagnostics.push(GrammarDiagnostic::UnusedRule {
+                    name: rule.ebnf.name.to_string(),
+                    span: rule.span.clone(),
+                });
+            }
+        }
+
+        diagnostics.extend(self.left_recursions());
+
+        let nullable = self.nullable_rules();
+        for rule in &self.rules {
+            let name = rule.ebnf.name;
+            if nullable.contains(name) {
+                diagnostics.push(GrammarDiagnostic::NullableRule {
+                    name: name.to_string(),
+                    span: rule.span.clone(),
+                });
+            }
+
+            let mut repeats = vec![];
+            collect_repeats(&rule.ebnf.expr, &mut repeats);
+            for node in repeats {
+                let EBNFNode::Repeat { node, min, max } = node else {
+                    continue;
+                };
+
+                if let Some(max) = max
+                    && min > max
+                {
+                    diagnostics.push(GrammarDiagnostic::InvalidQuantifier {
+                        rule: name.to_string(),
+                        min: *min,
+                        max: *max,
+                        span: self.repeat_span(node, rule),
+                    });
+                }
+
+                if max.is_none() && is_nullable(node, &nullable) {
+                    diagnostics.push(GrammarDiagnostic::NullableRepeat {
+                        rule: name.to_string(),
+                        span: self.repeat_span(node, rule),
+                    });
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    // 繰り返しの中身の範囲 (ソースコードにない場合は規則全体)
+    fn repeat_span(&self, node: &EBNFNode<'a>, rule: &GrammarRule<'a>) -> Range<usize> {
+        self.source_span(node).unwrap_or(rule.span.clone())
+    }
+
+    /// 空の入力に一致する規則
+    pub fn nullable_rules(&self) -> HashSet<&'a str> {
+        // 変化がなくなるまで繰り返す
+        let mut nullable = HashSet::new();
+        loop {
+            let mut changed = false;
+            for rule in &self.rules {
+                if !nullable.contains(rule.ebnf.name) && is_nullable(&rule.ebnf.expr, &nullable) {
+                    nullable.insert(rule.ebnf.name);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return nullable;
+            }
+        }
+    }
+
+    // 開始規則から参照を辿れる規則
+    fn reachable_rules(&self) -> HashSet<&'a str> {
+        let start = self.start_rule().ebnf.name;
+        let mut reachable = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(name) = stack.pop() {
+            let Some(rule) = self.rule(name) else {
+                continue;
+            };
+
+            let mut references = vec![];
+            collect_references(&rule.ebnf.expr, &mut references);
+            for reference in references {
+                if reachable.insert(reference) {
+                    stack.push(reference);
+                }
+            }
+        }
+
+        reachable
+    }
+
+    // 左端で参照する規則を辺とするグラフの閉路を探す
+    // 同じ閉路を重複して報告しないよう, 閉路の中で最初に定義された規則から始まるものだけを報告する
+    fn left_recursions(&self) -> Vec<GrammarDiagnostic> {
+        let nullable = self.nullable_rules();
+        let edges: Vec<Vec<usize>> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let mut references = vec![];
+                collect_left_references(&rule.ebnf.expr, &nullable, &mut references);
+                let mut edges: Vec<usize> = references
+                    .into_iter()
+                    .filter_map(|name| self.rule_map.get(name).copied())
+                    .collect();
+                edges.dedup();
+                edges
+            })
+            .collect();
+
+        let mut diagnostics = vec![];
+        for start in 0..self.rules.len() {
+            let Some(path) = shortest_cycle(&edges, start) else {
+                continue;
+            };
+
+            let rule = &self.rules[start];
+            diagnostics.push(GrammarDiagnostic::LeftRecursion {
+                cycle: path
+                    .into_iter()
+                    .map(|i| self.rules[i].ebnf.name.to_string())
+                    .collect(),
+                span: rule.span.clone(),
+            });
+        }
+
+        diagnostics
+    }
+}
+
+// startからstartに戻る最短の経路を, start以降の規則だけを通って探す
+fn shortest_cycle(edges: &[Vec<usize>], start: usize) -> Option<Vec<usize>> {
+    let mut previous: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        for &next in &edges[current] {
+            if next == start {
+                let mut path = vec![start, current];
+                let mut node = current;
+                while let Some(&prev) = previous.get(&node) {
+                    path.push(prev);
+                    node = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            if next > start && !previous.contains_key(&next) {
+                previous.insert(next, current);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+pub(super) fn is_nullable(node: &EBNFNode, nullable: &HashSet<&str>) -> bool {
+    match node {
+        EBNFNode::Expansion(name) => nullable.contains(name),
+        EBNFNode::Literal(literal) => literal.is_empty(),
+        EBNFNode::Class { .. } => false,
+        EBNFNode::Concat(nodes) => nodes.iter().all(|n| is_nullable(n, nullable)),
+        EBNFNode::Or(nodes) => nodes.iter().any(|n| is_nullable(n, nullable)),
+        EBNFNode::Repeat { node, min, .. } => *min == 0 || is_nullable(node, nullable),
+        EBNFNode::Group(node) | EBNFNode::Exception { node, .. } => is_nullable(node, nullable),
+    }
+}
+
+fn collect_references<'a>(node: &EBNFNode<'a>, references: &mut Vec<&'a str>) {
+    match node {
+        EBNFNode::Expansion(name) => references.push(name),
+        EBNFNode::Literal(_) | EBNFNode::Class { .. } => (),
+        EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => {
+            for node in nodes {
+                collect_references(node, references);
+            }
+        }
+        EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => {
+            collect_references(node, references)
+        }
+        EBNFNode::Exception { node, except } => {
+            collect_references(node, references);
+            collect_references(except, references);
+        }
+    }
+}
+
+// 入力を消費する前に参照されうる規則
+// 除外する側も同じ位置から読むので含める
+fn collect_left_references<'a>(
+    node: &EBNFNode<'a>,
+    nullable: &HashSet<&str>,
+    references: &mut Vec<&'a str>,
+) {
+    match node {
+        EBNFNode::Expansion(name) => references.push(name),
+        EBNFNode::Literal(_) | EBNFNode::Class { .. } => (),
+        EBNFNode::Concat(nodes) => {
+            for node in nodes {
+                collect_left_references(node, nullable, references);
+                if !is_nullable(node, nullable) {
+                    break;
+                }
+            }
+        }
+        EBNFNode::Or(nodes) => {
+            for node in nodes {
+                collect_left_references(node, nullable, references);
+            }
+        }
+        EBNFNode::Repeat { node, .. } | EBNFNode::Group(node) => {
+            collect_left_references(node, nullable, references)
+        }
+        EBNFNode::Exception { node, except } => {
+            collect_left_references(node, nullable, references);
+            collect_left_references(except, nullable, references);
+        }
+    }
+}
+
+fn collect_repeats<'a, 'b>(node: &'b EBNFNode<'a>, repeats: &mut Vec<&'b EBNFNode<'a>>) {
+    match node {
+        EBNFNode::Expansion(_) | EBNFNode::Literal(_) | EBNFNode::Class { .. } => (),
+        EBNFNode::Concat(nodes) | EBNFNode::Or(nodes) => {
+            for node in nodes {
+                collect_repeats(node, repeats);
+            }
+        }
+        EBNFNode::Exception { node, except } => {
+            collect_repeats(node, repeats);
+            collect_repeats(except, repeats);
+        }
+        EBNFNode::Repeat { node: inner, .. } => {
+            repeats.push(node);
+            collect_repeats(inner, repeats);
+        }
+        EBNFNode::Group(node) => collect_repeats(node, repeats),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GrammarDiagnostic;
+    use crate::ebnf_parser::parse_grammar;
+
+    // 繰り返しの診断は規則全体ではなく, それぞれの繰り返しの位置を指す
+    #[test]
+    fn repeat_spans() {
+        let source = "S ::= A{5,2} B;\nA ::= \"a\";\nB ::= \"b\" (\"c\"?)* \"d\"{3,1};";
+        let grammar = parse_grammar(source).unwrap();
+
+        let spans: Vec<_> = grammar
+            .validate()
+            .into_iter()
+            .filter_map(|d| match d {
+                GrammarDiagnostic::InvalidQuantifier { span, .. } => {
+                    Some(("quantifier", &source[span]))
+                }
+                GrammarDiagnostic::NullableRepeat { span, .. } => Some(("repeat", &source[span])),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("quantifier", "A"),
+                ("repeat", "\"c\""),
+                ("quantifier", "\"d\""),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ebnf::*;
+use std::borrow::Cow;
 use std::iter::from_fn;
 use std::iter::Peekable;
 use std::rc::Rc;
@@ -8,16 +9,97 @@ type ParserIterator<'a> = Peekable<CharIndices<'a>>;
 
 const EOF: &str = "EOF";
 
+// Grammar ::= Rule { Rule } ;
+// Rule ::= Name "::=" Expression ";" ;
+// Name ::= Letter { Letter | Digit | "_" } ;
+// Comment ::= "(*" { any-char } "*)" | "//" { any-char-except-line-break } ;
+
+/// 1つの規則を解析する (末尾の ; は省略できる)
 pub fn parse_ebnf<'a>(source: &'a str) -> Result<EBNF<'a>, String> {
     let mut iter = source.char_indices().peekable();
     parse_define(source, &mut iter).map_err(|e| e.error_message(source))
 }
 
+/// 複数の規則が書かれたファイルを解析する
+/// 最初の規則が開始規則になる
+pub fn parse_grammar<'a>(source: &'a str) -> Result<Grammar<'a>, String> {
+    let mut iter = source.char_indices().peekable();
+    parse_rules(source, &mut iter).map_err(|e| e.error_message(source))
+}
+
+fn parse_rules<'a>(
+    source: &'a str,
+    iter: &mut ParserIterator,
+) -> Result<Grammar<'a>, EBNFParseError> {
+    let mut rules: Vec<GrammarRule<'a>> = vec![];
+
+    loop {
+        skip_space(iter)?;
+        if iter.peek().is_none() {
+            break;
+        }
+
+        let start = get_position(iter, source.len());
+        let (name, expr) = parse_rule(source, iter)?;
+
+        skip_space(iter)?;
+        if iter.next_if(|c| matches!(c.1, ';')).is_none() {
+            return Err(EBNFParseError::UnexpectedToken {
+                expect_token: ';',
+                unexpected_token: get_token(iter),
+                position: get_position(iter, source.len()),
+            });
+        }
+        let end = get_position(iter, source.len());
+
+        if let Some(rule) = rules.iter().find(|r| r.ebnf.name == name) {
+            return Err(EBNFParseError::DuplicateRule {
+                name: name.to_string(),
+                position: start,
+                first_position: rule.span.start,
+            });
+        }
+
+        rules.push(GrammarRule {
+            ebnf: EBNF::new(name, expr),
+            span: start..end,
+        });
+    }
+
+    if rules.is_empty() {
+        return Err(EBNFParseError::EmptyGrammar);
+    }
+
+    Ok(Grammar::new(source, rules))
+}
+
 fn parse_define<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNF<'a>, EBNFParseError> {
-    skip_space(iter);
+    let (name, expr) = parse_rule(source, iter)?;
+
+    skip_space(iter)?;
+    iter.next_if(|c| matches!(c.1, ';'));
+    skip_space(iter)?;
+
+    // ルールの穴により末端まで解析できなかった場合
+    if iter.peek().is_some() {
+        return Err(EBNFParseError::UnmatchToken {
+            current_token: get_token(iter),
+            position: get_position(iter, source.len()),
+        });
+    }
+
+    Ok(EBNF::new(name, expr))
+}
+
+// Name "::=" Expression
+fn parse_rule<'a>(
+    source: &'a str,
+    iter: &mut ParserIterator,
+) -> Result<(&'a str, EBNFNode<'a>), EBNFParseError> {
+    skip_space(iter)?;
     let Some(c) = iter.peek() else {
         return Err(EBNFParseError::UnexpectedEOF);
     };
@@ -27,9 +109,9 @@ fn parse_define<'a>(
         });
     }
 
-    let name = parse_and_slice(source, iter, |c| c.is_alphabetic() || c.is_ascii_digit())?;
+    let name = parse_and_slice(source, iter, is_name_char)?;
 
-    skip_space(iter);
+    skip_space(iter)?;
     for expected_char in "::=".chars() {
         if iter.next_if(|c| c.1 == expected_char).is_none() {
             return Err(EBNFParseError::UnexpectedToken {
@@ -42,15 +124,7 @@ fn parse_define<'a>(
 
     let expr = parse_expression(source, iter)?;
 
-    // ルールの穴により末端まで解析できなかった場合
-    if iter.peek().is_some() {
-        return Err(EBNFParseError::UnmatchToken {
-            current_token: get_token(iter),
-            position: get_position(iter, source.len()),
-        });
-    }
-
-    Ok(EBNF::new(name, expr))
+    Ok((name, expr))
 }
 
 // Expression ::= Or ;
@@ -58,7 +132,7 @@ fn parse_expression<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
-    skip_space(iter);
+    skip_space(iter)?;
     parse_or(source, iter)
 }
 
@@ -68,14 +142,14 @@ fn parse_or<'a>(
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
     // Concat
-    skip_space(iter);
+    skip_space(iter)?;
     let mut nodes = vec![parse_concat(source, iter)?];
 
     //
-    skip_space(iter);
+    skip_space(iter)?;
     while iter.next_if(|c| matches!(c.1, '|')).is_some() {
         nodes.push(parse_concat(source, iter)?);
-        skip_space(iter);
+        skip_space(iter)?;
     }
 
     if nodes.len() == 1 {
@@ -85,21 +159,21 @@ fn parse_or<'a>(
     Ok(EBNFNode::Or(nodes.into_iter().map(Rc::new).collect()))
 }
 
-// Concat ::= Repeat { Repeat } ;
+// Concat ::= Exception { Exception } ;
 fn parse_concat<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
-    let mut nodes = vec![parse_repeat(source, iter)?];
+    let mut nodes = vec![parse_exception(source, iter)?];
 
     loop {
-        skip_space(iter);
+        skip_space(iter)?;
         let Some((_, c)) = iter.peek() else {
             break;
         };
 
-        if matches!(c, '"' | '(') || c.is_alphabetic() {
-            nodes.push(parse_repeat(source, iter)?);
+        if matches!(c, '"' | '\'' | '(' | '[') || (c.is_alphabetic() && !is_rule_start(iter)) {
+            nodes.push(parse_exception(source, iter)?);
         } else {
             break;
         }
@@ -112,6 +186,40 @@ fn parse_concat<'a>(
     Ok(EBNFNode::Concat(nodes.into_iter().map(Rc::new).collect()))
 }
 
+// 次の規則の Name "::=" であれば ; の書き忘れなので連結しない
+fn is_rule_start(iter: &ParserIterator) -> bool {
+    let mut clone_iter = iter.clone();
+    while clone_iter.next_if(|c| is_name_char(c.1)).is_some() {}
+    while clone_iter.next_if(|c| c.1.is_whitespace()).is_some() {}
+
+    "::="
+        .chars()
+        .all(|expected| clone_iter.next_if(|c| c.1 == expected).is_some())
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphabetic() || c.is_ascii_digit() || c == '_'
+}
+
+// Exception ::= Repeat [ "-" Repeat ] ;
+fn parse_exception<'a>(
+    source: &'a str,
+    iter: &mut ParserIterator,
+) -> Result<EBNFNode<'a>, EBNFParseError> {
+    let node = parse_repeat(source, iter)?;
+
+    skip_space(iter)?;
+    if iter.next_if(|c| matches!(c.1, '-')).is_none() {
+        return Ok(node);
+    }
+
+    let except = parse_repeat(source, iter)?;
+    Ok(EBNFNode::Exception {
+        node: Rc::new(node),
+        except: Rc::new(except),
+    })
+}
+
 // Repeat ::= Primary [ Quantifier ] ;
 fn parse_repeat<'a>(
     source: &'a str,
@@ -119,7 +227,7 @@ fn parse_repeat<'a>(
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
     let node = parse_primary(source, iter)?;
 
-    skip_space(iter);
+    skip_space(iter)?;
     let Some(c) = iter.peek() else {
         return Ok(node);
     };
@@ -152,7 +260,7 @@ fn parse_repeat<'a>(
 
 // Quantifier ::= "?" | "*" | "+" | "{" Integer [ "," [ Integer ] ] "}" ;
 fn parse_quantifier(iter: &mut ParserIterator, source: &str) -> Result<Quantifier, EBNFParseError> {
-    skip_space(iter);
+    skip_space(iter)?;
     let t = iter.peek().ok_or(EBNFParseError::UnexpectedEOF)?;
     let res = match t.1 {
         '?' => {
@@ -173,9 +281,9 @@ fn parse_quantifier(iter: &mut ParserIterator, source: &str) -> Result<Quantifie
             let start = parse_integer(iter, source)?;
             let mut end = Some(start);
 
-            skip_space(iter);
+            skip_space(iter)?;
             if iter.next_if(|t| matches!(t.1, ',')).is_some() {
-                skip_space(iter);
+                skip_space(iter)?;
                 let Some((_, c)) = iter.peek() else {
                     return Err(EBNFParseError::UnexpectedEOF);
                 };
@@ -187,7 +295,7 @@ fn parse_quantifier(iter: &mut ParserIterator, source: &str) -> Result<Quantifie
                 };
             }
 
-            skip_space(iter);
+            skip_space(iter)?;
             if iter.next_if(|t| matches!(t.1, '}')).is_none() {
                 return Err(EBNFParseError::UnexpectedToken {
                     expect_token: '}',
@@ -210,18 +318,19 @@ fn parse_quantifier(iter: &mut ParserIterator, source: &str) -> Result<Quantifie
     Ok(res)
 }
 
-// Primary ::= Literal | Group | Expansion;
+// Primary ::= Literal | CharClass | Group | Expansion;
 fn parse_primary<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
-    skip_space(iter);
+    skip_space(iter)?;
     let Some(&(_, c)) = iter.peek() else {
         return Err(EBNFParseError::UnexpectedEOF);
     };
 
     match c {
-        '"' => parse_literal(source, iter),
+        '"' | '\'' => parse_literal(source, iter),
+        '[' => parse_char_class(source, iter),
         '(' => parse_group(source, iter),
         _ if c.is_alphabetic() => parse_expansion(source, iter),
         _ => Err(EBNFParseError::UnmatchToken {
@@ -236,7 +345,7 @@ fn parse_group<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
-    skip_space(iter);
+    skip_space(iter)?;
     if iter.next_if(|c| matches!(c.1, '(')).is_none() {
         return Err(EBNFParseError::UnexpectedToken {
             expect_token: '(',
@@ -247,7 +356,7 @@ fn parse_group<'a>(
 
     let node = parse_or(source, iter)?;
 
-    skip_space(iter);
+    skip_space(iter)?;
     if iter.next_if(|c| matches!(c.1, ')')).is_none() {
         return Err(EBNFParseError::UnexpectedToken {
             expect_token: ')',
@@ -263,7 +372,7 @@ fn parse_expansion<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
-    skip_space(iter);
+    skip_space(iter)?;
 
     let Some(c) = iter.peek() else {
         return Err(EBNFParseError::UnexpectedEOF);
@@ -274,42 +383,126 @@ fn parse_expansion<'a>(
         });
     }
 
-    let name = parse_and_slice(source, iter, |c| c.is_alphabetic() || c.is_ascii_digit())?;
+    let name = parse_and_slice(source, iter, is_name_char)?;
 
     Ok(EBNFNode::Expansion(name))
 }
 
-// Literal ::= "\"" { any-char-except-quote } "\"" ;
+// Literal ::= "\"" { Escape | any-char-except-quote } "\""
+//           | "'" { Escape | any-char-except-quote } "'" ;
 fn parse_literal<'a>(
     source: &'a str,
     iter: &mut ParserIterator,
 ) -> Result<EBNFNode<'a>, EBNFParseError> {
-    skip_space(iter);
-    if iter.next_if(|c| matches!(c.1, '"')).is_none() {
+    skip_space(iter)?;
+    let Some((_, quote)) = iter.next_if(|c| matches!(c.1, '"' | '\'')) else {
         return Err(EBNFParseError::UnexpectedToken {
             expect_token: '"',
             unexpected_token: get_token(iter),
             position: get_position(iter, source.len()),
         });
+    };
+
+    let start = get_position(iter, source.len());
+    loop {
+        match iter.peek() {
+            Some(&(_, c)) if c == quote => break,
+            Some(&(position, '\\')) => {
+                iter.next();
+                read_escape_at(iter, position)?;
+            }
+            Some(_) => {
+                iter.next();
+            }
+            None => {
+                return Err(EBNFParseError::UnexpectedToken {
+                    expect_token: quote,
+                    unexpected_token: EOF.to_string(),
+                    position: source.len(),
+                });
+            }
+        }
     }
+    let end = get_position(iter, source.len());
+    iter.next();
 
-    let literal = parse_and_slice(source, iter, |c| !matches!(c, '"'))?;
+    Ok(EBNFNode::Literal(Cow::Borrowed(&source[start..end])))
+}
 
-    if iter.next_if(|c| matches!(c.1, '"')).is_none() {
+// CharClass ::= "[" [ "^" ] ClassItem { ClassItem } "]" ;
+// ClassItem ::= ClassChar [ "-" ClassChar ] ;
+// ClassChar ::= Escape | any-char-except-bracket ;
+fn parse_char_class<'a>(
+    source: &'a str,
+    iter: &mut ParserIterator,
+) -> Result<EBNFNode<'a>, EBNFParseError> {
+    skip_space(iter)?;
+    let start = get_position(iter, source.len());
+    if iter.next_if(|c| matches!(c.1, '[')).is_none() {
         return Err(EBNFParseError::UnexpectedToken {
-            expect_token: '"',
+            expect_token: '[',
             unexpected_token: get_token(iter),
-            position: get_position(iter, source.len()),
+            position: start,
         });
     }
 
-    Ok(EBNFNode::Literal(literal))
+    let negated = iter.next_if(|c| matches!(c.1, '^')).is_some();
+    let mut ranges = vec![];
+    while iter.next_if(|c| matches!(c.1, ']')).is_none() {
+        let position = get_position(iter, source.len());
+        let first = parse_class_char(source, iter)?;
+
+        // [a-] の - は文字として扱う
+        let mut clone_iter = iter.clone();
+        let is_range = matches!(
+            (clone_iter.next(), clone_iter.next()),
+            (Some((_, '-')), Some((_, c))) if c != ']'
+        );
+        let last = if is_range {
+            iter.next();
+            parse_class_char(source, iter)?
+        } else {
+            first
+        };
+
+        if first > last {
+            return Err(EBNFParseError::InvalidCharClass { position });
+        }
+        ranges.push(first..=last);
+    }
+    let end = get_position(iter, source.len());
+
+    if ranges.is_empty() {
+        return Err(EBNFParseError::InvalidCharClass { position: start });
+    }
+
+    Ok(EBNFNode::Class {
+        text: &source[start..end],
+        class: CharClass { negated, ranges },
+    })
+}
+
+fn parse_class_char(source: &str, iter: &mut ParserIterator) -> Result<char, EBNFParseError> {
+    match iter.next() {
+        Some((position, '\\')) => read_escape_at(iter, position),
+        Some((_, c)) => Ok(c),
+        None => Err(EBNFParseError::UnexpectedToken {
+            expect_token: ']',
+            unexpected_token: EOF.to_string(),
+            position: source.len(),
+        }),
+    }
+}
+
+// \ の後ろを読む (positionは \ の位置)
+fn read_escape_at(iter: &mut ParserIterator, position: usize) -> Result<char, EBNFParseError> {
+    read_escape(&mut iter.by_ref().map(|c| c.1)).ok_or(EBNFParseError::InvalidEscape { position })
 }
 
 // Integer ::= Digit { Digit } ;
 // Digit ::= "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" ;
 fn parse_integer(iter: &mut ParserIterator, source: &str) -> Result<u64, EBNFParseError> {
-    skip_space(iter);
+    skip_space(iter)?;
     let position = get_position(iter, source.len());
     let token = from_fn(|| iter.next_if(|c| c.1.is_ascii_digit()))
         .map(|c| c.1)
@@ -349,8 +542,29 @@ fn parse_and_slice<'a>(
     Ok(&source[start..end])
 }
 
-fn skip_space(iter: &mut ParserIterator) {
-    while iter.next_if(|c| c.1.is_whitespace()).is_some() {}
+// 空白とコメントを読み飛ばす
+fn skip_space(iter: &mut ParserIterator) -> Result<(), EBNFParseError> {
+    loop {
+        while iter.next_if(|c| c.1.is_whitespace()).is_some() {}
+
+        let mut clone_iter = iter.clone();
+        match (clone_iter.next(), clone_iter.next()) {
+            (Some((_, '/')), Some((_, '/'))) => {
+                while iter.next_if(|c| !matches!(c.1, '\n')).is_some() {}
+            }
+            (Some((position, '(')), Some((_, '*'))) => {
+                iter.nth(1);
+                loop {
+                    match iter.next() {
+                        Some((_, '*')) if iter.next_if(|c| matches!(c.1, ')')).is_some() => break,
+                        Some(_) => (),
+                        None => return Err(EBNFParseError::UnterminatedComment { position }),
+                    }
+                }
+            }
+            _ => return Ok(()),
+        }
+    }
 }
 
 fn get_token(iter: &mut ParserIterator) -> String {
@@ -382,6 +596,22 @@ enum EBNFParseError {
     ParseExpansionError {
         position: usize,
     },
+    UnterminatedComment {
+        position: usize,
+    },
+    InvalidEscape {
+        position: usize,
+    },
+    // 空のクラスや [z-a] のように逆順の範囲
+    InvalidCharClass {
+        position: usize,
+    },
+    DuplicateRule {
+        name: String,
+        position: usize,
+        first_position: usize,
+    },
+    EmptyGrammar,
 }
 
 impl EBNFParseError {
@@ -393,8 +623,7 @@ impl EBNFParseError {
                 position,
             } => [
                 format!("unexpected token: {unexpected_token}"),
-                input.to_string(),
-                format!("{}^", " ".repeat(*position)),
+                point_at(input, *position),
                 format!("expect token: {expect_token}"),
             ]
             .join("\n"),
@@ -404,32 +633,297 @@ impl EBNFParseError {
                 position,
             } => [
                 format!("unmatch token: {current_token}"),
-                input.to_string(),
-                format!("{}^", " ".repeat(*position)),
+                point_at(input, *position),
             ]
             .join("\n"),
 
             EBNFParseError::UnexpectedEOF => "unexpected EOF".to_string(),
             EBNFParseError::ParseIntError { position } => [
                 "can not parse integer".to_string(),
-                input.to_string(),
-                format!("{}^", " ".repeat(*position)),
+                point_at(input, *position),
             ]
             .join("\n"),
 
             EBNFParseError::ParseExpansionError { position } => [
                 "can not parse expansion".to_string(),
-                input.to_string(),
-                format!("{}^", " ".repeat(*position)),
+                point_at(input, *position),
             ]
             .join("\n"),
 
             EBNFParseError::ParseDefineError { position } => [
                 "can not parse define".to_string(),
-                input.to_string(),
-                format!("{}^", " ".repeat(*position)),
+                point_at(input, *position),
+            ]
+            .join("\n"),
+
+            EBNFParseError::UnterminatedComment { position } => [
+                "unterminated comment".to_string(),
+                point_at(input, *position),
+            ]
+            .join("\n"),
+
+            EBNFParseError::InvalidEscape { position } => [
+                "invalid escape sequence".to_string(),
+                point_at(input, *position),
+            ]
+            .join("\n"),
+
+            EBNFParseError::InvalidCharClass { position } => [
+                "invalid character class".to_string(),
+                point_at(input, *position),
+            ]
+            .join("\n"),
+
+            EBNFParseError::DuplicateRule {
+                name,
+                position,
+                first_position,
+            } => [
+                format!("duplicate rule: {name}"),
+                point_at(input, *position),
+                format!("first defined at {}", line_column(input, *first_position)),
             ]
             .join("\n"),
+
+            EBNFParseError::EmptyGrammar => "grammar has no rules".to_string(),
+        }
+    }
+}
+
+// positionの行と列 (1始まり)
+fn line_column(input: &str, position: usize) -> String {
+    let line_start = input[..position].rfind('\n').map_or(0, |p| p + 1);
+    let line = input[..line_start].matches('\n').count() + 1;
+    let column = input[line_start..position].chars().count() + 1;
+    format!("line {line}, column {column}")
+}
+
+// positionを含む行と, その位置を指す ^ を返す
+pub(crate) fn point_at(input: &str, position: usize) -> String {
+    let line_start = input[..position].rfind('\n').map_or(0, |p| p + 1);
+    let line_end = input[position..]
+        .find('\n')
+        .map_or(input.len(), |p| position + p);
+    let column = input[line_start..position].chars().count();
+
+    [
+        line_column(input, position),
+        input[line_start..line_end]
+            .trim_end_matches('\r')
+            .to_string(),
+        format!("{}^", " ".repeat(column)),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ebnf, parse_grammar};
+    use crate::ebnf::interpreter::{parse_source, token_class_names};
+    use crate::ebnf::{literal_value, CharClass, EBNFNode, EBNF};
+    use std::borrow::Cow;
+
+    fn root<'a, 'b>(ebnf: &'b EBNF<'a>) -> &'b EBNFNode<'a> {
+        ebnf.get_node(&ebnf.root()).unwrap()
+    }
+
+    #[test]
+    fn multiple_rules() {
+        let source = "(* top *)\nExpr ::= Term ( \"+\" Term )* ; // c\nTerm ::= Num | \"(\" Expr \")\";\n\n(* multi\n line *) Num ::= \"1\"+;\n";
+        let mut grammar = parse_grammar(source).unwrap();
+        let names: Vec<_> = grammar.rules().iter().map(|rule| rule.ebnf.name).collect();
+        assert_eq!(names, ["Expr", "Term", "Num"]);
+        assert_eq!(grammar.start_rule().ebnf.name, "Expr");
+
+        // 規則の範囲は名前から ; まで
+        let spans: Vec<_> = grammar
+            .rules()
+            .iter()
+            .map(|rule| &source[rule.span.clone()])
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                "Expr ::= Term ( \"+\" Term )* ;",
+                "Term ::= Num | \"(\" Expr \")\";",
+                "Num ::= \"1\"+;",
+            ]
+        );
+
+        grammar.set_start_rule("Num").unwrap();
+        assert_eq!(grammar.start_rule().ebnf.name, "Num");
+        assert!(grammar.set_start_rule("Nope").is_err());
+    }
+
+    // 1つの規則では最後の ; とコメントを省略できる
+    #[test]
+    fn single_rule_terminator() {
+        for source in [
+            "A ::= \"a\"",
+            "A ::= \"a\" ;",
+            "A ::= \"a\" (* x *)",
+            "A ::= \"a\"; // x",
+        ] {
+            assert_eq!(parse_ebnf(source).unwrap().name, "A", "{source}");
+        }
+    }
+
+    #[test]
+    fn grammar_errors() {
+        assert_eq!(
+            parse_grammar("A ::= \"a\";\nB ::= \"b\";\nA ::= B;").unwrap_err(),
+            "duplicate rule: A\nline 3, column 1\nA ::= B;\n^\nfirst defined at line 1, column 1"
+        );
+        assert_eq!(
+            parse_grammar("A ::= \"a\";\nB ::= \"b\"\nC ::= A;").unwrap_err(),
+            "unexpected token: C\nline 3, column 1\nC ::= A;\n^\nexpect token: ;"
+        );
+        assert_eq!(
+            parse_grammar("A ::= \"a\"; (* open").unwrap_err(),
+            "unterminated comment\nline 1, column 12\nA ::= \"a\"; (* open\n           ^"
+        );
+        for source in ["", "// only\n", "(* a *) (* b *)"] {
+            assert_eq!(
+                parse_grammar(source).unwrap_err(),
+                "grammar has no rules",
+                "{source:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn literals() {
+        let ebnf = parse_ebnf(r#"S ::= "a\"b" '"' 'it\'s' "\x41\u{3042}\n" "";"#).unwrap();
+        let EBNFNode::Concat(nodes) = root(&ebnf) else {
+            panic!("{ebnf}");
+        };
+        // リテラルはエスケープを残したまま保持する
+        let raws: Vec<_> = nodes
+            .iter()
+            .map(|node| match &**node {
+                EBNFNode::Literal(raw) => raw.clone(),
+                node => panic!("{node:?}"),
+            })
+            .collect();
+        assert_eq!(raws, [r#"a\"b"#, "\"", r"it\'s", r"\x41\u{3042}\n", ""]);
+        assert!(raws.iter().all(|raw| matches!(raw, Cow::Borrowed(_))));
+
+        let values: Vec<_> = raws.iter().map(literal_value).collect();
+        assert_eq!(values, ["a\"b", "\"", "it's", "Aあ\n", ""]);
+    }
+
+    #[test]
+    fn char_classes() {
+        let class = |source| match root(&parse_ebnf(source).unwrap()) {
+            EBNFNode::Class { text, class } => (text.to_string(), class.clone()),
+            node => panic!("{node:?}"),
+        };
+
+        assert_eq!(
+            class(r"S ::= [a-c_\]];"),
+            (
+                r"[a-c_\]]".to_string(),
+                CharClass {
+                    negated: false,
+                    ranges: vec!['a'..='c', '_'..='_', ']'..=']'],
+                }
+            )
+        );
+
+        // 先頭の ^ は否定, 末尾の - は文字そのもの
+        let (text, negated) = class(r"S ::= [^\n\]a-c-];");
+        assert_eq!(text, r"[^\n\]a-c-]");
+        assert!(negated.negated);
+        for c in ['a', 'b', 'c', '-', '\n', ']'] {
+            assert!(!negated.contains(c), "{c:?}");
+        }
+        for c in ['d', 'z', '^', 'あ'] {
+            assert!(negated.contains(c), "{c:?}");
         }
+
+        let (_, trailing_minus) = class("S ::= [a-];");
+        assert_eq!(trailing_minus.ranges, ['a'..='a', '-'..='-']);
+        let (_, escaped) = class(r"S ::= [\x41-\u{3042}\t];");
+        assert_eq!(escaped.ranges, ['A'..='あ', '\t'..='\t']);
+    }
+
+    #[test]
+    fn dialect_errors() {
+        for (source, message) in [
+            (
+                r#"S ::= "\q";"#,
+                "invalid escape sequence\nline 1, column 8\nS ::= \"\\q\";\n       ^",
+            ),
+            (
+                r#"S ::= "\x80";"#,
+                "invalid escape sequence\nline 1, column 8\nS ::= \"\\x80\";\n       ^",
+            ),
+            (
+                r"S ::= '\u{110000}';",
+                "invalid escape sequence\nline 1, column 8\nS ::= '\\u{110000}';\n       ^",
+            ),
+            (
+                r"S ::= [\q];",
+                "invalid escape sequence\nline 1, column 8\nS ::= [\\q];\n       ^",
+            ),
+            (
+                "S ::= [z-a];",
+                "invalid character class\nline 1, column 8\nS ::= [z-a];\n       ^",
+            ),
+            (
+                "S ::= [];",
+                "invalid character class\nline 1, column 7\nS ::= [];\n      ^",
+            ),
+            (
+                "S ::= [^];",
+                "invalid character class\nline 1, column 7\nS ::= [^];\n      ^",
+            ),
+        ] {
+            assert_eq!(parse_ebnf(source).unwrap_err(), message, "{source}");
+        }
+
+        for source in ["S ::= 'a", "S ::= \"a", "S ::= [a"] {
+            assert!(parse_ebnf(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn exception() {
+        // - は繰り返しより弱く, 連接より強く結合する
+        let ebnf = parse_ebnf(r#"S ::= "a" B* - C "d";"#).unwrap();
+        let EBNFNode::Concat(nodes) = root(&ebnf) else {
+            panic!("{ebnf}");
+        };
+        assert_eq!(nodes.len(), 3);
+        let EBNFNode::Exception { node, except } = &*nodes[1] else {
+            panic!("{:?}", nodes[1]);
+        };
+        assert!(matches!(
+            &**node,
+            EBNFNode::Repeat {
+                min: 0,
+                max: None,
+                ..
+            }
+        ));
+        assert_eq!(**except, EBNFNode::Expansion("C"));
+
+        // - は連ねられないので括弧で囲む
+        assert!(parse_ebnf("S ::= A - B - C;").is_err());
+        let ebnf = parse_ebnf("S ::= (A - B) - C;").unwrap();
+        let EBNFNode::Exception { node, except } = root(&ebnf) else {
+            panic!("{ebnf}");
+        };
+        assert!(matches!(&**node, EBNFNode::Group(_)));
+        assert_eq!(**except, EBNFNode::Expansion("C"));
+
+        // exceptに一致する入力では失敗する
+        let source = r#"S ::= Item*; Item ::= (IDENT - "skip") | [+\-*/];"#;
+        let mut grammar = parse_grammar(source).unwrap();
+        grammar.declare_terminals(token_class_names());
+        let tree = parse_source(&grammar, "a + b - c * d", None).unwrap();
+        assert_eq!(tree.to_string().matches("Item").count(), 7, "{tree}");
+        assert!(parse_source(&grammar, "a skip", None).is_err());
+        assert!(parse_source(&grammar, "a skipped", None).is_ok());
     }
 }
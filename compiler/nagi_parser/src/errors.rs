@@ -1,4 +1,6 @@
-use std::{error::Error, fmt::Display};
+use crate::lexer::code::number::NagiNumberSuffix;
+use nagi_lexer::errors::TokenizeError;
+use std::{error::Error, fmt::Display, ops::Range};
 
 #[derive(Debug)]
 pub enum ParserError {
@@ -23,6 +25,104 @@ pub enum TokenStreamParseError {
     UnusableCharacters { position: usize },
     CannotConvertTextToNumbers { position: usize },
     NotKeyword,
+    InvalidEscape { position: usize, error: EscapeError },
+    UnterminatedString { position: usize }, // 開始の " の位置
+    UnterminatedChar { position: usize },   // 開始の ' の位置
+    EmptyCharLiteral { position: usize },
+    MultiCharCharLiteral { position: usize },
+    UnterminatedBlockComment { position: usize }, // 開始の /* の位置
+    UnterminatedRawString { position: usize, hashes: usize }, // 開始の r の位置
+    RawStringHashMismatch { span: Range<usize>, expected: usize }, // 閉じの " と # の範囲
+    NonAsciiInByteLiteral { position: usize },
+    InvalidNumber { position: usize, error: NumberError },
+    InvalidRawIdentifier { position: usize }, // 開始の r の位置
+    MixedKeywordLocale { position: usize },   // 別の言語のキーワードの位置
+    UnterminatedAttribute { position: usize }, // 開始の # の位置
+    NotAttribute,
+    Tokenize(TokenizeError), // fused_lexerで文字を切り分けた際のエラー (nagi_lexer::tokenize と同じ)
+}
+
+#[derive(Debug)]
+pub enum EscapeError {
+    UnknownEscape(char),
+    UnterminatedEscape,
+    InvalidHexEscape,
+    OutOfRangeHexEscape,
+    InvalidUnicodeEscape,
+    OutOfRangeUnicodeEscape,
+    UnicodeEscapeInByteLiteral,
+}
+
+#[derive(Debug)]
+pub enum NumberError {
+    MissingDigits,
+    InvalidDigit {
+        digit: char,
+        radix: u32,
+    },
+    UnknownSuffix(String),
+    IntegerSuffixOnFloat(NagiNumberSuffix),
+    FloatSuffixOnNonDecimal,
+    OutOfRange {
+        literal: String,
+        suffix: NagiNumberSuffix,
+    },
+}
+
+impl TokenStreamParseError {
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            TokenStreamParseError::UnexpectedToken { position }
+            | TokenStreamParseError::UnmatchedToken { position }
+            | TokenStreamParseError::UnusableCharacters { position }
+            | TokenStreamParseError::CannotConvertTextToNumbers { position }
+            | TokenStreamParseError::InvalidEscape { position, .. }
+            | TokenStreamParseError::UnterminatedString { position }
+            | TokenStreamParseError::UnterminatedChar { position }
+            | TokenStreamParseError::EmptyCharLiteral { position }
+            | TokenStreamParseError::MultiCharCharLiteral { position }
+            | TokenStreamParseError::UnterminatedBlockComment { position }
+            | TokenStreamParseError::UnterminatedRawString { position, .. }
+            | TokenStreamParseError::NonAsciiInByteLiteral { position }
+            | TokenStreamParseError::InvalidNumber { position, .. }
+            | TokenStreamParseError::InvalidRawIdentifier { position }
+            | TokenStreamParseError::MixedKeywordLocale { position }
+            | TokenStreamParseError::UnterminatedAttribute { position } => Some(*position),
+            TokenStreamParseError::RawStringHashMismatch { span, .. } => Some(span.start),
+            TokenStreamParseError::Tokenize(
+                TokenizeError::InvalidCharacters { position, .. }
+                | TokenizeError::UnusableWhitespace { position, .. },
+            ) => Some(*position),
+            TokenStreamParseError::Tokenize(TokenizeError::SuspiciousCharacters(_))
+            | TokenStreamParseError::UnexpectedEOF
+            | TokenStreamParseError::NotKeyword
+            | TokenStreamParseError::NotAttribute => None,
+        }
+    }
+
+    /// エラー位置の行を表示し ^ で位置を示したメッセージを返す
+    pub fn error_message(&self, source_code: &str) -> String {
+        let Some(position) = self.position() else {
+            return self.to_string();
+        };
+
+        let line_start = source_code[..position].rfind('\n').map_or(0, |p| p + 1);
+        let line_end = source_code[position..]
+            .find(['\r', '\n'])
+            .map_or(source_code.len(), |p| position + p);
+        let line_number = source_code[..position].matches('\n').count() + 1;
+        let column = source_code[line_start..position].chars().count();
+
+        [
+            self.to_string(),
+            format!("{line_number}: {}", &source_code[line_start..line_end]),
+            format!(
+                "{}^",
+                " ".repeat(line_number.to_string().len() + 2 + column)
+            ),
+        ]
+        .join("\n")
+    }
 }
 
 impl Display for TokenStreamParseError {
@@ -42,6 +142,132 @@ impl Display for TokenStreamParseError {
                 write!(f, "Cannot convert text to numbers. position: {position}")
             }
             TokenStreamParseError::NotKeyword => write!(f, "Not a keyword"),
+            TokenStreamParseError::InvalidEscape { position, error } => {
+                write!(f, "{error}. position: {position}")
+            }
+            TokenStreamParseError::UnterminatedString { position } => {
+                write!(
+                    f,
+                    "Unterminated string literal starting at position {position}"
+                )
+            }
+            TokenStreamParseError::UnterminatedChar { position } => {
+                write!(
+                    f,
+                    "Unterminated char literal starting at position {position}"
+                )
+            }
+            TokenStreamParseError::EmptyCharLiteral { position } => {
+                write!(f, "Empty char literal at position {position}")
+            }
+            TokenStreamParseError::MultiCharCharLiteral { position } => write!(
+                f,
+                "Char literal must contain exactly one character. position: {position}"
+            ),
+            TokenStreamParseError::UnterminatedBlockComment { position } => {
+                write!(
+                    f,
+                    "Unterminated block comment opened at position {position}"
+                )
+            }
+            TokenStreamParseError::UnterminatedRawString { position, hashes } => write!(
+                f,
+                "Unterminated raw string starting at position {position}, expected \"{}",
+                "#".repeat(*hashes)
+            ),
+            TokenStreamParseError::RawStringHashMismatch { span, expected } => write!(
+                f,
+                "Raw string must be terminated with {expected} '#', found {}. position: {}..{}",
+                span.len() - 1,
+                span.start,
+                span.end
+            ),
+            TokenStreamParseError::NonAsciiInByteLiteral { position } => {
+                write!(
+                    f,
+                    "Non-ASCII character in byte literal at position {position}"
+                )
+            }
+            TokenStreamParseError::InvalidNumber { position, error } => {
+                write!(f, "{error}. position: {position}")
+            }
+            TokenStreamParseError::InvalidRawIdentifier { position } => write!(
+                f,
+                "`_`, `self` and `Self` cannot be raw identifiers. position: {position}"
+            ),
+            TokenStreamParseError::MixedKeywordLocale { position } => write!(
+                f,
+                "Keywords of another language cannot be mixed in one file. position: {position}"
+            ),
+            TokenStreamParseError::UnterminatedAttribute { position } => {
+                write!(f, "Unterminated attribute starting at position {position}")
+            }
+            TokenStreamParseError::NotAttribute => write!(f, "Not an attribute"),
+            TokenStreamParseError::Tokenize(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<TokenizeError> for TokenStreamParseError {
+    fn from(value: TokenizeError) -> Self {
+        TokenStreamParseError::Tokenize(value)
+    }
+}
+
+impl Display for EscapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EscapeError::UnknownEscape(c) => write!(f, "Unknown escape sequence: \\{c}"),
+            EscapeError::UnterminatedEscape => write!(f, "Escape sequence is not terminated"),
+            EscapeError::InvalidHexEscape => {
+                write!(f, "\\x escape must be followed by 2 hex digits")
+            }
+            EscapeError::OutOfRangeHexEscape => {
+                write!(f, "\\x escape must be in the range [\\x00-\\x7F]")
+            }
+            EscapeError::InvalidUnicodeEscape => {
+                write!(f, "\\u escape must be of the form \\u{{1-6 hex digits}}")
+            }
+            EscapeError::OutOfRangeUnicodeEscape => {
+                write!(f, "\\u escape is not a valid unicode scalar value")
+            }
+            EscapeError::UnicodeEscapeInByteLiteral => {
+                write!(f, "\\u escape cannot be used in byte literals")
+            }
+        }
+    }
+}
+
+impl Display for NumberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberError::MissingDigits => write!(f, "Number literal has no digits"),
+            NumberError::InvalidDigit { digit, radix } => {
+                write!(f, "Invalid digit '{digit}' for a base {radix} literal")
+            }
+            NumberError::UnknownSuffix(suffix) => {
+                write!(f, "Invalid suffix `{suffix}` for number literal")
+            }
+            NumberError::IntegerSuffixOnFloat(suffix) => {
+                write!(
+                    f,
+                    "Integer suffix `{suffix}` cannot be used on a float literal"
+                )
+            }
+            NumberError::FloatSuffixOnNonDecimal => {
+                write!(f, "Float suffix can only be used on decimal literals")
+            }
+            NumberError::OutOfRange { literal, suffix } => match suffix.integer_range() {
+                Some((0, max)) => write!(
+                    f,
+                    "Literal `{literal}` is out of range for {suffix} (0..={max})"
+                ),
+                Some((min, max)) => write!(
+                    f,
+                    "Literal `{literal}` is out of range for {suffix} (-{min}..={max})"
+                ),
+                None => write!(f, "Literal `{literal}` is out of range for {suffix}"),
+            },
         }
     }
 }
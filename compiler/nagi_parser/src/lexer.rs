@@ -1,12 +1,48 @@
 use std::{collections::HashMap, hash::Hash};
 
-pub(crate) mod code;
+pub mod code;
 pub(crate) mod spec;
 
 #[derive(Debug)]
-pub(crate) struct Lexer<T> {
+pub struct Lexer<T> {
     token_list: Vec<T>,
     token_position: usize,
+    replaced_list: Vec<(usize, T)>, // 置き換えたトークンの位置と元のトークン (rewindで戻すため)
+}
+
+/// 巻き戻し用に保存したLexerの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    token_position: usize,
+    replaced_count: usize,
+}
+
+/// 規則の識別子
+pub type RuleId = u32;
+
+/// packrat方式の構文解析用のメモ
+/// (規則, 開始位置) ごとに解析結果と終了位置を保存する
+#[derive(Debug)]
+pub struct Memo<V> {
+    table: HashMap<(RuleId, usize), (V, usize)>,
+}
+
+impl<V> Memo<V> {
+    pub fn new() -> Self {
+        Self {
+            table: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+}
+
+impl<V> Default for Memo<V> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> Lexer<T> {
@@ -14,6 +50,7 @@ impl<T> Lexer<T> {
         Self {
             token_list,
             token_position: 0,
+            replaced_list: vec![],
         }
     }
 
@@ -21,6 +58,13 @@ impl<T> Lexer<T> {
         self.token_list.get(self.token_position)
     }
 
+    /// n個先のトークンを見る (peek_nth(0) は peek と同じ)
+    pub fn peek_nth(&self, n: usize) -> Option<&T> {
+        self.token_list.get(self.token_position.checked_add(n)?)
+    }
+
+    // トークンを自身から借用して返すのでIteratorは実装できない
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&T> {
         let position = self.token_position;
         self.token_position += 1;
@@ -45,6 +89,83 @@ impl<T> Lexer<T> {
     pub fn set_token_position(&mut self, position: usize) {
         self.token_position = position;
     }
+
+    /// 現在のトークンを置き換える
+    /// 元のトークンは保存され, 置き換える前のCheckpointにrewindすると元に戻る
+    pub fn replace_token(&mut self, token: T) {
+        let Some(current) = self.token_list.get_mut(self.token_position) else {
+            return;
+        };
+        let original = std::mem::replace(current, token);
+        self.replaced_list.push((self.token_position, original));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            token_position: self.token_position,
+            replaced_count: self.replaced_list.len(),
+        }
+    }
+
+    /// checkpointを作成した時点の状態に戻す
+    /// それ以降に置き換えたトークンも元に戻る
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        debug_assert!(
+            checkpoint.replaced_count <= self.replaced_list.len(),
+            "Checkpoint is newer than the current state"
+        );
+
+        while self.replaced_list.len() > checkpoint.replaced_count {
+            let Some((position, original)) = self.replaced_list.pop() else {
+                break;
+            };
+            self.token_list[position] = original;
+        }
+        self.token_position = checkpoint.token_position;
+    }
+
+    /// fを試し, 失敗した場合は呼び出す前の状態に戻す
+    pub fn speculate<U, E, F>(&mut self, f: F) -> Result<U, E>
+    where
+        F: FnOnce(&mut Self) -> Result<U, E>,
+    {
+        let checkpoint = self.checkpoint();
+        let result = f(self);
+        if result.is_err() {
+            self.rewind(checkpoint);
+        }
+        result
+    }
+
+    /// 同じ位置で同じ規則を解析済みであればその結果を返し, 終了位置まで進める
+    /// 解析済みでなければfで解析して結果をメモする
+    ///
+    /// メモは元のトークン列に対する結果なので,
+    /// 開始位置以降のトークンが置き換えられている場合はメモを使わずに解析する
+    ///
+    /// fには再帰的に memoize を呼べるようにmemoも渡す
+    pub fn memoize<V, F>(&mut self, memo: &mut Memo<V>, rule: RuleId, f: F) -> V
+    where
+        V: Clone,
+        F: FnOnce(&mut Self, &mut Memo<V>) -> V,
+    {
+        let start = self.token_position;
+        let replaced_after = |lexer: &Self| lexer.replaced_list.iter().any(|(p, _)| *p >= start);
+
+        if !replaced_after(self)
+            && let Some((value, end)) = memo.table.get(&(rule, start))
+        {
+            self.token_position = *end;
+            return value.clone();
+        }
+
+        let value = f(self, memo);
+        if !replaced_after(self) {
+            memo.table
+                .insert((rule, start), (value.clone(), self.token_position));
+        }
+        value
+    }
 }
 
 pub(crate) type PatternType<T, U> = Vec<(Vec<T>, U)>;
@@ -70,3 +191,116 @@ where
 
     hash_map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, Memo};
+    use crate::lexer::code::nagi_code_lexer::{tokenize_program, NagiProgramToken};
+
+    fn peek_text(lexer: &Lexer<NagiProgramToken>) -> Option<String> {
+        lexer
+            .peek()
+            .map(|token| format!("{:?}@{}", token.token_kind, token.position))
+    }
+
+    #[test]
+    fn peek_nth() {
+        let lexer = Lexer::new(vec![1, 2, 3]);
+        assert_eq!(lexer.peek_nth(0), lexer.peek());
+        assert_eq!(lexer.peek_nth(2), Some(&3));
+        assert_eq!(lexer.peek_nth(3), None);
+        assert_eq!(lexer.peek_nth(usize::MAX), None);
+    }
+
+    // 置き換えたトークンも巻き戻しで元に戻る
+    #[test]
+    fn rewind_restores_replaced_tokens() {
+        let token_list = nagi_lexer::tokenize("a >>= b").unwrap();
+        let mut lexer = tokenize_program(&token_list).unwrap();
+        lexer.next();
+        let checkpoint = lexer.checkpoint();
+        let before = peek_text(&lexer);
+
+        lexer.eat_greater_than().unwrap();
+        assert_eq!(
+            peek_text(&lexer).as_deref(),
+            Some("Operator(GreaterEqual)@3")
+        );
+        lexer.rewind(checkpoint);
+        assert_eq!(peek_text(&lexer), before);
+
+        let result: Result<(), ()> = lexer.speculate(|lexer| {
+            lexer.eat_greater_than().unwrap();
+            lexer.eat_greater_than().unwrap();
+            Err(())
+        });
+        assert!(result.is_err());
+        assert_eq!(peek_text(&lexer), before);
+
+        let result: Result<(), ()> = lexer.speculate(|lexer| {
+            lexer.eat_greater_than().unwrap();
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(
+            peek_text(&lexer).as_deref(),
+            Some("Operator(GreaterEqual)@3")
+        );
+    }
+
+    #[test]
+    fn memoize() {
+        let mut lexer = Lexer::new(vec![1, 2, 3]);
+        let mut memo = Memo::new();
+        let mut calls = 0;
+        let mut parse = |lexer: &mut Lexer<i32>, _: &mut Memo<i32>| {
+            calls += 1;
+            lexer.next();
+            lexer.next().copied().unwrap()
+        };
+
+        assert_eq!(lexer.memoize(&mut memo, 1, &mut parse), 2);
+        assert_eq!(lexer.get_token_position(), 2);
+
+        // 同じ位置と規則では解析せずに終了位置まで進める
+        lexer.set_token_position(0);
+        assert_eq!(lexer.memoize(&mut memo, 1, &mut parse), 2);
+        assert_eq!(lexer.get_token_position(), 2);
+
+        // 規則か位置が異なれば解析する
+        lexer.set_token_position(0);
+        assert_eq!(lexer.memoize(&mut memo, 2, &mut parse), 2);
+        lexer.set_token_position(1);
+        assert_eq!(lexer.memoize(&mut memo, 1, &mut parse), 3);
+
+        memo.clear();
+        lexer.set_token_position(0);
+        assert_eq!(lexer.memoize(&mut memo, 1, &mut parse), 2);
+        assert_eq!(calls, 4);
+    }
+
+    // 開始位置以降のトークンを置き換えている間はメモを使わない
+    #[test]
+    fn memoize_skips_replaced_tokens() {
+        let token_list = nagi_lexer::tokenize("a >>= b").unwrap();
+        let mut lexer = tokenize_program(&token_list).unwrap();
+        let mut memo = Memo::new();
+        lexer.next();
+        let checkpoint = lexer.checkpoint();
+
+        let value = lexer.memoize(&mut memo, 1, |lexer, _| {
+            lexer.next();
+            7
+        });
+        assert_eq!(value, 7);
+
+        lexer.rewind(checkpoint);
+        lexer.eat_greater_than();
+        let value = lexer.memoize(&mut memo, 1, |_, _| 9);
+        assert_eq!(value, 9);
+
+        lexer.rewind(checkpoint);
+        let value = lexer.memoize(&mut memo, 1, |_, _| 9);
+        assert_eq!(value, 7);
+    }
+}
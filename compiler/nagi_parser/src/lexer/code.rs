@@ -1,4 +1,11 @@
-pub(crate) mod keywords;
-pub(crate) mod nagi_code_lexer;
+pub mod attribute;
+pub(crate) mod escape;
+pub mod fused_lexer;
+pub mod keyword_format;
+pub mod keywords;
+pub mod nagi_code_lexer;
+pub(crate) mod number;
 pub(crate) mod operators;
+pub mod security;
 pub(crate) mod symbols;
+pub mod syntax_tree;
@@ -0,0 +1,266 @@
+use super::keywords::{KeywordLocale, NagiCodeKeyword};
+use super::nagi_code_lexer::{
+    tokenize_program_with_keywords, NagiIdentifier, NagiLiteral, NagiOperator, NagiProgramToken,
+    NagiProgramTokenKind, NagiSymbol,
+};
+use crate::errors::TokenStreamParseError;
+use nagi_ast::{
+    Attribute, AttributeArg, AttributeArgs, AttributeStyle, Literal, Path, PathSegment,
+};
+use nagi_lexer::token::Token;
+use std::{fmt::Display, iter::Peekable, ops::Range, slice::Iter};
+
+// 属性の中身の解釈
+// 括弧の対応が取れるまで接着したトークンを集めてから, 属性の構文として解釈する
+//
+// ATTRIBUTE         ::= "#" "!"? "[" ATTRIBUTE_PATH ATTRIBUTE_INPUT? "]"
+// ATTRIBUTE_PATH    ::= IDENTIFIER ( "::" IDENTIFIER )*
+// ATTRIBUTE_INPUT   ::= "(" ATTRIBUTE_ARGS? ")" | "=" ATTRIBUTE_LITERAL
+// ATTRIBUTE_ARGS    ::= ATTRIBUTE_ARG ( "," ATTRIBUTE_ARG )* ","?
+// ATTRIBUTE_ARG     ::= ATTRIBUTE_LITERAL
+//                     | ATTRIBUTE_PATH ( "=" ATTRIBUTE_LITERAL | "(" ATTRIBUTE_ARGS? ")" )?
+// ATTRIBUTE_LITERAL ::= STRING_LITERAL | RAW_STRING_LITERAL | CHAR_LITERAL
+//                     | INTEGER_LITERAL | FLOAT_LITERAL | "true" | "false"
+
+// 未知の属性は警告する
+// allowはnagi_lexer::securityの不審な文字の許可に使う
+const KNOWN_ATTRIBUTES: [&str; 5] = ["test", "inline", "deprecated", "cfg", "allow"];
+
+type AttributeIter<'a> = Peekable<Iter<'a, NagiProgramToken>>;
+
+#[derive(Debug)]
+pub struct UnknownAttributeWarning {
+    pub name: String,
+    pub span: Range<usize>,
+}
+
+impl Display for UnknownAttributeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown attribute `{}`. position: {}",
+            self.name, self.span.start
+        )
+    }
+}
+
+/// ファイル内の全ての属性を検査し, 未知の属性の警告を返す
+pub fn check_attributes(
+    token_list: &[Token],
+    default_locale: Option<KeywordLocale>,
+) -> Result<Vec<UnknownAttributeWarning>, TokenStreamParseError> {
+    let mut program = tokenize_program_with_keywords(token_list, default_locale)?;
+    let mut warnings = vec![];
+    while let Some(token) = program.next() {
+        if let NagiProgramTokenKind::Attribute(attribute) = &token.token_kind {
+            warnings.extend(check_attribute(attribute));
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// 未知の属性であれば警告を返す
+pub fn check_attribute(attribute: &Attribute) -> Option<UnknownAttributeWarning> {
+    let name = path_name(&attribute.path);
+    if KNOWN_ATTRIBUTES.contains(&name.as_str()) {
+        return None;
+    }
+
+    Some(UnknownAttributeWarning {
+        name,
+        span: attribute.span.clone(),
+    })
+}
+
+/// 属性の [ と ] の間のトークンを解釈する
+/// spanは # から ] までの範囲
+pub(crate) fn parse_attribute(
+    style: AttributeStyle,
+    token_list: &[NagiProgramToken],
+    span: Range<usize>,
+) -> Result<Attribute, TokenStreamParseError> {
+    // ] の位置
+    let end = span.end - 1;
+    let mut iter = token_list.iter().peekable();
+
+    let path = parse_path(&mut iter, end)?;
+    let args = if eat_symbol(&mut iter, |s| matches!(s, NagiSymbol::LeftParenthesis)) {
+        AttributeArgs::List(parse_args(&mut iter, end)?)
+    } else if eat_assign(&mut iter) {
+        AttributeArgs::Value(parse_literal(&mut iter, end)?)
+    } else {
+        AttributeArgs::Empty
+    };
+
+    if let Some(token) = iter.next() {
+        return Err(TokenStreamParseError::UnexpectedToken {
+            position: token.position,
+        });
+    }
+
+    Ok(Attribute {
+        style,
+        path,
+        args,
+        span,
+    })
+}
+
+// ( の直後から ) までを解釈する
+fn parse_args(
+    iter: &mut AttributeIter,
+    end: usize,
+) -> Result<Vec<AttributeArg>, TokenStreamParseError> {
+    let mut args = vec![];
+    loop {
+        if eat_symbol(iter, |s| matches!(s, NagiSymbol::RightParenthesis)) {
+            return Ok(args);
+        }
+
+        args.push(parse_arg(iter, end)?);
+
+        if eat_symbol(iter, |s| matches!(s, NagiSymbol::Comma)) {
+            continue;
+        }
+        if eat_symbol(iter, |s| matches!(s, NagiSymbol::RightParenthesis)) {
+            return Ok(args);
+        }
+
+        return Err(unexpected_token(iter, end));
+    }
+}
+
+fn parse_arg(iter: &mut AttributeIter, end: usize) -> Result<AttributeArg, TokenStreamParseError> {
+    let is_path = iter.peek().is_some_and(|t| {
+        matches!(
+            t.token_kind,
+            NagiProgramTokenKind::Identifier(NagiIdentifier::Identifier(_))
+        )
+    });
+    if !is_path {
+        return Ok(AttributeArg::Literal(parse_literal(iter, end)?));
+    }
+
+    let path = parse_path(iter, end)?;
+    if eat_symbol(iter, |s| matches!(s, NagiSymbol::LeftParenthesis)) {
+        return Ok(AttributeArg::List {
+            path,
+            args: parse_args(iter, end)?,
+        });
+    }
+    if eat_assign(iter) {
+        return Ok(AttributeArg::KeyValue {
+            key: path,
+            value: parse_literal(iter, end)?,
+        });
+    }
+
+    Ok(AttributeArg::Path(path))
+}
+
+fn parse_path(iter: &mut AttributeIter, end: usize) -> Result<Path, TokenStreamParseError> {
+    let mut segments = vec![];
+    loop {
+        let Some(NagiProgramToken {
+            token_kind: NagiProgramTokenKind::Identifier(NagiIdentifier::Identifier(ident)),
+            ..
+        }) = iter.peek()
+        else {
+            return Err(unexpected_token(iter, end));
+        };
+        iter.next();
+
+        segments.push(PathSegment {
+            ident: ident.clone(),
+            arguments: vec![],
+        });
+
+        if !eat_symbol(iter, |s| matches!(s, NagiSymbol::DoubleColon)) {
+            return Ok(Path { segments });
+        }
+    }
+}
+
+fn parse_literal(iter: &mut AttributeIter, end: usize) -> Result<Literal, TokenStreamParseError> {
+    let literal = match iter.peek().map(|t| &t.token_kind) {
+        Some(NagiProgramTokenKind::Literal(literal)) => match literal {
+            NagiLiteral::Integer { value, .. } => Literal::Integer(value.to_string()),
+            NagiLiteral::Float { value, .. } => Literal::Float(value.clone()),
+            NagiLiteral::String { value, .. } | NagiLiteral::RawString { value, .. } => {
+                Literal::String(value.clone())
+            }
+            NagiLiteral::Char { value, .. } => Literal::Char(value.to_string()),
+            NagiLiteral::ByteString { .. } | NagiLiteral::Byte { .. } => {
+                return Err(unexpected_token(iter, end));
+            }
+        },
+        Some(NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(NagiCodeKeyword::True))) => {
+            Literal::Bool(true)
+        }
+        Some(NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(NagiCodeKeyword::False))) => {
+            Literal::Bool(false)
+        }
+        _ => return Err(unexpected_token(iter, end)),
+    };
+    iter.next();
+
+    Ok(literal)
+}
+
+fn eat_symbol<F>(iter: &mut AttributeIter, condition: F) -> bool
+where
+    F: Fn(&NagiSymbol) -> bool,
+{
+    iter.next_if(|t| matches!(&t.token_kind, NagiProgramTokenKind::Symbol(s) if condition(s)))
+        .is_some()
+}
+
+fn eat_assign(iter: &mut AttributeIter) -> bool {
+    iter.next_if(|t| {
+        matches!(
+            t.token_kind,
+            NagiProgramTokenKind::Operator(NagiOperator::Assign)
+        )
+    })
+    .is_some()
+}
+
+// トークンが尽きた場合は ] の位置を示す
+fn unexpected_token(iter: &mut AttributeIter, end: usize) -> TokenStreamParseError {
+    TokenStreamParseError::UnexpectedToken {
+        position: iter.peek().map_or(end, |t| t.position),
+    }
+}
+
+fn path_name(path: &Path) -> String {
+    path.segments
+        .iter()
+        .map(|s| s.ident.as_str())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_attributes;
+
+    fn unknown_attributes(source_code: &str) -> Vec<String> {
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        check_attributes(&token_list, None)
+            .unwrap()
+            .into_iter()
+            .map(|w| w.name)
+            .collect()
+    }
+
+    #[test]
+    fn known_attributes() {
+        let source_code = "#![allow(bidi_chars)]\n#[test]\n#[inline]\n#[deprecated]\n#[cfg(test)]\n#[allow(invisible_chars)]\nfn f() {}";
+        assert!(unknown_attributes(source_code).is_empty());
+        assert_eq!(
+            unknown_attributes("#[derive(Debug)]\n#[a::b]"),
+            ["derive", "a::b"]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::errors::{EscapeError, TokenStreamParseError};
+use nagi_lexer::token::{LineBreak, Space, Symbol, Token, TokenKind};
+use std::iter::{from_fn, Peekable};
+use std::slice::Iter;
+
+// 文字列・文字リテラルのエスケープシーケンスの解釈
+// 切り分け済みのトークンは1文字単位ではないので, 位置付きの文字列に戻してから処理する
+//
+// ESCAPE         ::= "\\" ( "n" | "t" | "r" | "0" | "\\" | "\"" | "'" )
+//                  | "\\x" HEX_DIGIT HEX_DIGIT
+//                  | "\\u{" HEX_DIGIT+ "}"
+// LINE_CONTINUE  ::= "\\" LINE_BREAK WHITE_SPACE*
+//
+// バイト文字列・バイト文字ではASCIIのみ使え, \x は \xFF まで, \u は使えない
+
+pub(crate) type CharIter<'a> = Peekable<Iter<'a, (usize, char)>>;
+
+const MAX_ASCII_ESCAPE: u32 = 0x7F;
+const MAX_BYTE_ESCAPE: u32 = 0xFF;
+const MAX_UNICODE_ESCAPE_DIGITS: usize = 6;
+
+/// トークンを位置付きの文字の列に戻す
+pub(crate) fn token_chars(token: &Token) -> Vec<(usize, char)> {
+    let position = token.token_pos;
+    match &token.token_kind {
+        TokenKind::Identifier(text) | TokenKind::Number(text) => text
+            .char_indices()
+            .map(|(pos, c)| (position + pos, c))
+            .collect(),
+        TokenKind::Symbol(symbol) => vec![(position, symbol_char(symbol))],
+        TokenKind::WhiteSpace(spaces) => spaces
+            .iter()
+            .enumerate()
+            .map(|(pos, space)| match space {
+                Space::Space => (position + pos, ' '),
+                Space::Tab => (position + pos, '\t'),
+            })
+            .collect(),
+        TokenKind::LineBreak(line_breaks) => line_breaks
+            .iter()
+            .enumerate()
+            .map(|(pos, line_break)| match line_break {
+                LineBreak::CR => (position + pos, '\r'),
+                LineBreak::LF => (position + pos, '\n'),
+            })
+            .collect(),
+    }
+}
+
+/// エスケープを解釈した文字列を返す
+pub(crate) fn unescape(chars: &[(usize, char)]) -> Result<String, TokenStreamParseError> {
+    let mut iter = chars.iter().peekable();
+    let mut text = String::with_capacity(chars.len());
+
+    while let Some(&(position, c)) = iter.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+
+        if let Some(c) = eat_escape(&mut iter, position)? {
+            text.push(c);
+        }
+    }
+
+    Ok(text)
+}
+
+/// バイト列として解釈する
+pub(crate) fn unescape_bytes(chars: &[(usize, char)]) -> Result<Vec<u8>, TokenStreamParseError> {
+    let mut iter = chars.iter().peekable();
+    let mut bytes = Vec::with_capacity(chars.len());
+
+    while let Some(&(position, c)) = iter.next() {
+        if !c.is_ascii() {
+            return Err(TokenStreamParseError::NonAsciiInByteLiteral { position });
+        }
+
+        if c != '\\' {
+            bytes.push(c as u8);
+            continue;
+        }
+
+        let byte = match iter.peek() {
+            Some((_, 'x')) => {
+                iter.next();
+                Some(eat_hex_escape(&mut iter, position, MAX_BYTE_ESCAPE)?)
+            }
+            Some((_, 'u')) => {
+                return Err(escape_error(
+                    position,
+                    EscapeError::UnicodeEscapeInByteLiteral,
+                ))
+            }
+            _ => eat_escape(&mut iter, position)?.map(|c| c as u32),
+        };
+
+        if let Some(byte) = byte {
+            bytes.push(byte as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// \ の直後から1つのエスケープを解釈する
+/// 行継続の場合はNoneを返す
+pub(crate) fn eat_escape(
+    iter: &mut CharIter,
+    position: usize,
+) -> Result<Option<char>, TokenStreamParseError> {
+    let Some(&(_, c)) = iter.next() else {
+        return Err(escape_error(position, EscapeError::UnterminatedEscape));
+    };
+
+    let c = match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' => '\\',
+        '"' => '"',
+        '\'' => '\'',
+        'x' => {
+            let value = eat_hex_escape(iter, position, MAX_ASCII_ESCAPE)?;
+            char::from_u32(value).unwrap()
+        }
+        'u' => eat_unicode_escape(iter, position)?,
+        '\r' | '\n' => {
+            // 改行と次の行の先頭の空白を読み飛ばす
+            from_fn(|| iter.next_if(|(_, c)| matches!(c, ' ' | '\t' | '\r' | '\n'))).count();
+            return Ok(None);
+        }
+        _ => return Err(escape_error(position, EscapeError::UnknownEscape(c))),
+    };
+
+    Ok(Some(c))
+}
+
+// \x7F
+fn eat_hex_escape(
+    iter: &mut CharIter,
+    position: usize,
+    max: u32,
+) -> Result<u32, TokenStreamParseError> {
+    let mut value = 0;
+    for _ in 0..2 {
+        let Some((_, digit)) = iter.next_if(|(_, c)| c.is_ascii_hexdigit()) else {
+            return Err(escape_error(position, EscapeError::InvalidHexEscape));
+        };
+        value = value * 16 + digit.to_digit(16).unwrap();
+    }
+
+    if value > max {
+        return Err(escape_error(position, EscapeError::OutOfRangeHexEscape));
+    }
+
+    Ok(value)
+}
+
+// \u{3042}
+fn eat_unicode_escape(iter: &mut CharIter, position: usize) -> Result<char, TokenStreamParseError> {
+    if iter.next_if(|(_, c)| *c == '{').is_none() {
+        return Err(escape_error(position, EscapeError::InvalidUnicodeEscape));
+    }
+
+    let digits: String = from_fn(|| iter.next_if(|(_, c)| c.is_ascii_hexdigit() || *c == '_'))
+        .map(|(_, c)| *c)
+        .filter(|c| *c != '_')
+        .collect();
+
+    if iter.next_if(|(_, c)| *c == '}').is_none()
+        || digits.is_empty()
+        || digits.len() > MAX_UNICODE_ESCAPE_DIGITS
+    {
+        return Err(escape_error(position, EscapeError::InvalidUnicodeEscape));
+    }
+
+    u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(escape_error(position, EscapeError::OutOfRangeUnicodeEscape))
+}
+
+fn escape_error(position: usize, error: EscapeError) -> TokenStreamParseError {
+    TokenStreamParseError::InvalidEscape { position, error }
+}
+
+pub(crate) fn symbol_char(symbol: &Symbol) -> char {
+    match symbol {
+        Symbol::LeftParenthesis => '(',
+        Symbol::RightParenthesis => ')',
+        Symbol::LeftBrackets => '[',
+        Symbol::RightBrackets => ']',
+        Symbol::LeftBrace => '{',
+        Symbol::RightBrace => '}',
+        Symbol::Plus => '+',
+        Symbol::Minus => '-',
+        Symbol::Star => '*',
+        Symbol::Slash => '/',
+        Symbol::Percent => '%',
+        Symbol::Equal => '=',
+        Symbol::Caret => '^',
+        Symbol::Not => '!',
+        Symbol::And => '&',
+        Symbol::Or => '|',
+        Symbol::GreaterThan => '>',
+        Symbol::LessThan => '<',
+        Symbol::At => '@',
+        Symbol::Dot => '.',
+        Symbol::Comma => ',',
+        Symbol::Colon => ':',
+        Symbol::Semicolon => ';',
+        Symbol::Underscore => '_',
+        Symbol::Pound => '#',
+        Symbol::Dollar => '$',
+        Symbol::Question => '?',
+        Symbol::Tilde => '~',
+        Symbol::SingleQuotation => '\'',
+        Symbol::DoubleQuotation => '"',
+        Symbol::BackSlash => '\\',
+        Symbol::Backtick => '`',
+    }
+}
@@ -0,0 +1,870 @@
+use super::{
+    attribute::parse_attribute,
+    escape::{unescape, unescape_bytes},
+    keywords::{KeywordKind, KeywordLocale, NagiCodeKeyword},
+    nagi_code_lexer::{
+        parse_keyword_locale_directive, NagiDocComment, NagiIdentifier, NagiLiteral, NagiOperator,
+        NagiProgramToken, NagiProgramTokenKind, NagiSymbol,
+    },
+    number::parse_number,
+};
+use crate::{errors::TokenStreamParseError, lexer::Lexer};
+use nagi_ast::AttributeStyle;
+use nagi_lexer::{errors::TokenizeError, is_identifier_char};
+use std::ops::Range;
+
+// ソースコードから直接NagiProgramTokenを作る1段階の切り分け
+// nagi_lexer::tokenize と tokenize_program の2段階と同じ結果になるようにしている
+// 2段階の方は文字の塊のトークンを作ってから接着し直すため, イテレータの複製や記号ごとの表の検索,
+// 識別子ごとの文字列の確保が必要になるが, こちらはバイト列を1回走査するだけで済む
+//
+// 2段階の方に合わせるため
+// - 使えない文字は文字列やコメントの中であっても最初に nagi_lexer::tokenize と同じ TokenizeError にする
+// - 識別子は nagi_lexer::is_identifier_char の文字で始まり, その文字と数字と_が続くもの
+//   (_で始まるものは記号として扱われエラーになる)
+// - 数値は数字で始まり, 英字と数字と_が続くもの
+//
+// 2段階の方との一致は下のテストで確かめている
+
+/// 1段階で切り分ける
+/// 結果は tokenize_program_with_keywords と同じになる
+/// エラーも同じで, nagi_lexer::tokenize のエラーは TokenStreamParseError::Tokenize で返す
+pub fn tokenize_program_fused(
+    source_code: &str,
+    default_locale: Option<KeywordLocale>,
+) -> Result<Lexer<NagiProgramToken>, TokenStreamParseError> {
+    check_characters(source_code)?;
+
+    let locale = keyword_locale_directive(source_code)
+        .map(|(locale, _)| locale)
+        .or(default_locale);
+
+    let mut lexer = FusedLexer {
+        source_code,
+        bytes: source_code.as_bytes(),
+        position: 0,
+        locale,
+    };
+
+    let mut token_list = vec![];
+    loop {
+        lexer.skip_trivia()?;
+
+        let position = lexer.position;
+        if position >= lexer.bytes.len() {
+            break;
+        }
+
+        let token_kind = lexer.glue_token()?;
+        token_list.push(NagiProgramToken {
+            token_kind,
+            position,
+            end: lexer.position,
+        });
+    }
+
+    Ok(Lexer::new(token_list))
+}
+
+// nagi_lexer::tokenize が受け付ける文字か
+// エラーも nagi_lexer::tokenize と同じく先頭から最初に見つかった文字のものを返す
+fn check_characters(source_code: &str) -> Result<(), TokenizeError> {
+    for (position, c) in source_code.char_indices() {
+        if c.is_ascii_whitespace() && !matches!(c, ' ' | '\t' | '\r' | '\n') {
+            return Err(TokenizeError::UnusableWhitespace { c, position });
+        }
+
+        if !(c.is_ascii_digit()
+            || c.is_ascii_whitespace()
+            || c.is_ascii_punctuation()
+            || is_identifier_char(c))
+        {
+            return Err(TokenizeError::InvalidCharacters { c, position });
+        }
+    }
+
+    Ok(())
+}
+
+/// nagi_code_lexer::keyword_locale_directive の文字列版
+fn keyword_locale_directive(source_code: &str) -> Option<(KeywordLocale, Range<usize>)> {
+    let mut position = 0;
+    loop {
+        position += source_code[position..]
+            .bytes()
+            .take_while(|b| is_white_space(*b))
+            .count();
+
+        let rest = &source_code[position..];
+        if doc_comment_style(rest.as_bytes()).is_some() || !rest.starts_with("//") {
+            return None;
+        }
+        position += 2;
+
+        let end = source_code[position..]
+            .find(['\r', '\n'])
+            .map_or(source_code.len(), |p| position + p);
+        let comment = source_code[position..end]
+            .char_indices()
+            .map(|(p, c)| (position + p, c));
+
+        if let Some(directive) = parse_keyword_locale_directive(comment) {
+            return Some(directive);
+        }
+        position = end;
+    }
+}
+
+// ドキュメントコメントの開始であれば内部ドキュメントかどうかを返す
+fn doc_comment_style(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [b'/', b'/', b'!', ..] => Some(true),
+        [b'/', b'/', b'/', b'/', ..] => None,
+        [b'/', b'/', b'/', ..] => Some(false),
+        _ => None,
+    }
+}
+
+fn is_white_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn is_word_char(c: char) -> bool {
+    is_identifier_char(c) || c.is_ascii_digit() || c == '_'
+}
+
+struct FusedLexer<'a> {
+    source_code: &'a str,
+    bytes: &'a [u8],
+    position: usize, // 次に読むバイトの位置
+    locale: Option<KeywordLocale>,
+}
+
+impl<'a> FusedLexer<'a> {
+    fn peek_byte(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn char_at(&self, position: usize) -> Option<char> {
+        match self.bytes.get(position) {
+            Some(byte) if byte.is_ascii() => Some(*byte as char),
+            Some(_) => self.source_code[position..].chars().next(),
+            None => None,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.char_at(self.position)
+    }
+
+    fn next(&mut self) -> Option<(usize, char)> {
+        let c = self.peek()?;
+        let position = self.position;
+        self.position += c.len_utf8();
+        Some((position, c))
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        self.bytes[self.position..].starts_with(pattern.as_bytes())
+    }
+
+    fn eat(&mut self, pattern: &str) -> bool {
+        if !self.starts_with(pattern) {
+            return false;
+        }
+        self.position += pattern.len();
+        true
+    }
+
+    // 条件を満たす文字が続く位置の終わり
+    fn end_of<F>(&self, start: usize, condition: F) -> usize
+    where
+        F: Fn(char) -> bool,
+    {
+        let mut position = start;
+        while let Some(c) = self.char_at(position)
+            && condition(c)
+        {
+            position += c.len_utf8();
+        }
+        position
+    }
+
+    fn eat_identifier_chars(&mut self) -> &'a str {
+        let start = self.position;
+        self.position = self.end_of(start, is_word_char);
+        &self.source_code[start..self.position]
+    }
+
+    fn chars(&self, range: Range<usize>) -> impl Iterator<Item = (usize, char)> + 'a {
+        let start = range.start;
+        self.source_code[range]
+            .char_indices()
+            .map(move |(p, c)| (start + p, c))
+    }
+
+    /// 空白と通常のコメントを読み飛ばす
+    fn skip_trivia(&mut self) -> Result<(), TokenStreamParseError> {
+        loop {
+            match self.peek_byte() {
+                Some(byte) if is_white_space(byte) => self.position += 1,
+                Some(b'/') if doc_comment_style(&self.bytes[self.position..]).is_some() => {
+                    return Ok(());
+                }
+                Some(b'/') if self.starts_with("//") => {
+                    self.position = self.end_of(self.position, |c| !matches!(c, '\r' | '\n'));
+                }
+                Some(b'/') if self.starts_with("/*") => self.eat_block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn eat_block_comment(&mut self) -> Result<(), TokenStreamParseError> {
+        let position = self.position;
+        self.position += 2;
+
+        let mut depth = 1;
+        while depth > 0 {
+            if self.eat("/*") {
+                depth += 1;
+            } else if self.eat("*/") {
+                depth -= 1;
+            } else if self.next().is_none() {
+                return Err(TokenStreamParseError::UnterminatedBlockComment { position });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn glue_token(&mut self) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let Some(c) = self.peek() else {
+            return Err(TokenStreamParseError::UnexpectedEOF);
+        };
+
+        if c.is_ascii_digit() {
+            self.glue_number()
+        } else if is_identifier_char(c) {
+            self.glue_identifier()
+        } else {
+            self.glue_symbol()
+        }
+    }
+
+    fn glue_identifier(&mut self) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let start = self.position;
+        let word_end = self.end_of(start, is_identifier_char);
+        let word = &self.source_code[start..word_end];
+        let next = self.bytes.get(word_end).copied();
+
+        // 接頭辞付きのリテラル
+        match (word, next) {
+            ("r", Some(b'"' | b'#')) if self.is_raw_string_start(word_end) => {
+                self.position = word_end;
+                return self.glue_raw_string_literal(start);
+            }
+            ("b", Some(b'"')) => {
+                self.position = word_end + 1;
+                return self.glue_byte_string_literal(start);
+            }
+            ("b", Some(b'\'')) => {
+                self.position = word_end + 1;
+                return self.glue_byte_literal(start);
+            }
+            _ => (),
+        }
+
+        // r#識別子
+        if word == "r"
+            && next == Some(b'#')
+            && self
+                .char_at(word_end + 1)
+                .is_some_and(|c| is_identifier_char(c) || c == '_')
+        {
+            self.position = word_end + 1;
+            let ident = self.eat_identifier_chars();
+            if ident == "_"
+                || NagiCodeKeyword::from_str_with_locale(ident)
+                    .is_some_and(|(k, _)| !k.is_raw_identifier_allowed())
+            {
+                return Err(TokenStreamParseError::InvalidRawIdentifier { position: start });
+            }
+
+            return Ok(NagiProgramTokenKind::Identifier(
+                NagiIdentifier::Identifier(ident.to_string()),
+            ));
+        }
+
+        let ident = self.eat_identifier_chars();
+        let selected_locale = self.locale.unwrap_or(KeywordLocale::English);
+        match NagiCodeKeyword::from_str_with_locale(ident) {
+            Some((keyword, keyword_locale)) if keyword.kind() == KeywordKind::Strict => {
+                if keyword_locale == selected_locale {
+                    return Ok(NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(
+                        keyword,
+                    )));
+                }
+
+                if self.locale.is_some() {
+                    return Err(TokenStreamParseError::MixedKeywordLocale { position: start });
+                }
+
+                Ok(NagiProgramTokenKind::Identifier(
+                    NagiIdentifier::Identifier(ident.to_string()),
+                ))
+            }
+            _ => Ok(NagiProgramTokenKind::Identifier(
+                NagiIdentifier::Identifier(ident.to_string()),
+            )),
+        }
+    }
+
+    /// 解釈は2段階の方と同じく number::parse_number で行う
+    fn glue_number(&mut self) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let start = self.position;
+        let end = self.end_of(start, is_word_char);
+        let mut chars: Vec<(usize, char)> = self.chars(start..end).collect();
+        self.position = end;
+
+        let is_decimal = !matches!(chars.get(..2), Some([(_, '0'), (_, 'b' | 'o' | 'x')]));
+
+        // 1.foo や 1..2 のように . の後に識別子や . が続く場合は小数点として扱わない
+        if is_decimal
+            && chars.iter().all(|(_, c)| c.is_ascii_digit() || *c == '_')
+            && self.peek_byte() == Some(b'.')
+            && !self
+                .char_at(self.position + 1)
+                .is_some_and(|c| is_identifier_char(c) || c == '.' || c == '_')
+        {
+            chars.push((self.position, '.'));
+            self.position += 1;
+            let end = self.end_of(self.position, is_word_char);
+            chars.extend(self.chars(self.position..end));
+            self.position = end;
+        }
+
+        // 指数の符号
+        if is_decimal
+            && chars.last().is_some_and(|(_, c)| matches!(c, 'e' | 'E'))
+            && matches!(self.peek_byte(), Some(b'+' | b'-'))
+            && self
+                .bytes
+                .get(self.position + 1)
+                .is_some_and(u8::is_ascii_digit)
+        {
+            chars.push((self.position, self.bytes[self.position] as char));
+            self.position += 1;
+            let end = self.end_of(self.position, is_word_char);
+            chars.extend(self.chars(self.position..end));
+            self.position = end;
+        }
+
+        Ok(NagiProgramTokenKind::Literal(parse_number(&chars)?))
+    }
+
+    fn glue_symbol(&mut self) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        match self.peek_byte() {
+            Some(b'"') => return self.glue_string_literal(),
+            Some(b'\'') => return self.glue_char_literal_or_label(),
+            _ => (),
+        }
+
+        if let Some(inner) = doc_comment_style(&self.bytes[self.position..]) {
+            return Ok(self.glue_doc_comment(inner));
+        }
+
+        if let Some(style) = self.peek_attribute() {
+            return self.glue_attribute(style);
+        }
+
+        self.glue_from_pattern()
+    }
+
+    fn glue_string_literal(&mut self) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let start = self.position;
+        self.position += 1;
+
+        let (chars, end) = self.eat_quoted_chars(
+            '"',
+            true,
+            TokenStreamParseError::UnterminatedString { position: start },
+        )?;
+
+        Ok(NagiProgramTokenKind::Literal(NagiLiteral::String {
+            value: unescape(&chars)?,
+            span: start..end + 1,
+        }))
+    }
+
+    // r の後に # が0個以上続き " が続くか
+    fn is_raw_string_start(&self, position: usize) -> bool {
+        let hashes = self.bytes[position..]
+            .iter()
+            .take_while(|b| **b == b'#')
+            .count();
+        self.bytes.get(position + hashes) == Some(&b'"')
+    }
+
+    /// startは r の位置
+    fn glue_raw_string_literal(
+        &mut self,
+        start: usize,
+    ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let hashes = self.count_hashes(self.position);
+        self.position += hashes + 1; // # と " の消費
+
+        let mut chars = vec![];
+        let mut closest: Option<Range<usize>> = None; // # が足りなかった閉じの候補のうち最も近いもの
+        let end = loop {
+            let Some((position, c)) = self.next() else {
+                return Err(match closest {
+                    Some(span) => TokenStreamParseError::RawStringHashMismatch {
+                        span,
+                        expected: hashes,
+                    },
+                    None => TokenStreamParseError::UnterminatedRawString {
+                        position: start,
+                        hashes,
+                    },
+                });
+            };
+
+            if c != '"' {
+                chars.push(c);
+                continue;
+            }
+
+            let found = self.count_hashes(self.position);
+            let span = position..position + 1 + found;
+
+            if found == hashes {
+                self.position += found;
+                break span.end;
+            }
+
+            if found > hashes {
+                return Err(TokenStreamParseError::RawStringHashMismatch {
+                    span,
+                    expected: hashes,
+                });
+            }
+
+            // # が足りない " は中身として扱う
+            if closest.as_ref().is_none_or(|c| c.len() < span.len()) {
+                closest = Some(span);
+            }
+            chars.push(c);
+        };
+
+        Ok(NagiProgramTokenKind::Literal(NagiLiteral::RawString {
+            value: chars.into_iter().collect(),
+            span: start..end,
+        }))
+    }
+
+    fn count_hashes(&self, position: usize) -> usize {
+        self.bytes[position..]
+            .iter()
+            .take_while(|b| **b == b'#')
+            .count()
+    }
+
+    /// startは b の位置
+    fn glue_byte_string_literal(
+        &mut self,
+        start: usize,
+    ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let (chars, end) = self.eat_quoted_chars(
+            '"',
+            true,
+            TokenStreamParseError::UnterminatedString { position: start },
+        )?;
+
+        Ok(NagiProgramTokenKind::Literal(NagiLiteral::ByteString {
+            value: unescape_bytes(&chars)?,
+            span: start..end + 1,
+        }))
+    }
+
+    /// startは b の位置
+    fn glue_byte_literal(
+        &mut self,
+        start: usize,
+    ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let (chars, end) = self.eat_quoted_chars(
+            '\'',
+            false,
+            TokenStreamParseError::UnterminatedChar { position: start },
+        )?;
+
+        let value = match unescape_bytes(&chars)?[..] {
+            [byte] => byte,
+            [] => return Err(TokenStreamParseError::EmptyCharLiteral { position: start }),
+            _ => return Err(TokenStreamParseError::MultiCharCharLiteral { position: start }),
+        };
+
+        Ok(NagiProgramTokenKind::Literal(NagiLiteral::Byte {
+            value,
+            span: start..end + 1,
+        }))
+    }
+
+    fn glue_char_literal_or_label(
+        &mut self,
+    ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let start = self.position;
+        self.position += 1;
+
+        let Some(c) = self.peek() else {
+            return Err(TokenStreamParseError::UnterminatedChar { position: start });
+        };
+
+        if !(is_identifier_char(c) || c == '_') {
+            return self.glue_quoted_char(start);
+        }
+
+        let ident = self.eat_identifier_chars();
+        if self.peek_byte() != Some(b'\'') {
+            return Ok(NagiProgramTokenKind::Label(ident.to_string()));
+        }
+        let end = self.position;
+        self.position += 1;
+
+        let mut chars = ident.chars();
+        let (Some(value), None) = (chars.next(), chars.next()) else {
+            return Err(TokenStreamParseError::MultiCharCharLiteral { position: start });
+        };
+
+        Ok(NagiProgramTokenKind::Literal(NagiLiteral::Char {
+            value,
+            span: start..end + 1,
+        }))
+    }
+
+    /// startは開始の ' の位置
+    fn glue_quoted_char(
+        &mut self,
+        start: usize,
+    ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let (chars, end) = self.eat_quoted_chars(
+            '\'',
+            false,
+            TokenStreamParseError::UnterminatedChar { position: start },
+        )?;
+
+        let value = unescape(&chars)?;
+        let mut value_chars = value.chars();
+        let value = match (value_chars.next(), value_chars.next()) {
+            (Some(c), None) => c,
+            (None, _) => return Err(TokenStreamParseError::EmptyCharLiteral { position: start }),
+            (Some(_), Some(_)) => {
+                return Err(TokenStreamParseError::MultiCharCharLiteral { position: start })
+            }
+        };
+
+        Ok(NagiProgramTokenKind::Literal(NagiLiteral::Char {
+            value,
+            span: start..end + 1,
+        }))
+    }
+
+    /// 開始の引用符は解析済み前提
+    /// 閉じの引用符までの位置付きの文字と, 閉じの引用符の位置を返す
+    /// multi_lineがfalseの場合は改行を跨がない
+    fn eat_quoted_chars(
+        &mut self,
+        quote: char,
+        multi_line: bool,
+        unterminated: TokenStreamParseError,
+    ) -> Result<(Vec<(usize, char)>, usize), TokenStreamParseError> {
+        let mut next = || {
+            let c = self.peek()?;
+            if !multi_line && matches!(c, '\r' | '\n') {
+                return None;
+            }
+            self.next()
+        };
+
+        // \ の直後の文字は必ず中身として扱う
+        let mut chars = vec![];
+        loop {
+            let Some((position, c)) = next() else {
+                return Err(unterminated);
+            };
+
+            if c == quote {
+                return Ok((chars, position));
+            }
+
+            chars.push((position, c));
+            if c == '\\' {
+                let Some(escaped) = next() else {
+                    return Err(unterminated);
+                };
+                chars.push(escaped);
+            }
+        }
+    }
+
+    fn glue_doc_comment(&mut self, inner: bool) -> NagiProgramTokenKind {
+        self.position += 3; // /// もしくは //! の消費
+        let start = self.position;
+        self.position = self.end_of(start, |c| !matches!(c, '\r' | '\n'));
+        let text = self.source_code[start..self.position].to_string();
+
+        let doc_comment = if inner {
+            NagiDocComment::Inner(text)
+        } else {
+            NagiDocComment::Outer(text)
+        };
+
+        NagiProgramTokenKind::DocComment(doc_comment)
+    }
+
+    /// 属性の開始であれば, 内部属性かどうかを返す
+    fn peek_attribute(&self) -> Option<AttributeStyle> {
+        if self.peek_byte() != Some(b'#') {
+            return None;
+        }
+
+        let mut position = self.skip_white_space(self.position + 1);
+        let style = if self.bytes.get(position) == Some(&b'!') {
+            position = self.skip_white_space(position + 1);
+            AttributeStyle::Inner
+        } else {
+            AttributeStyle::Outer
+        };
+
+        (self.bytes.get(position) == Some(&b'[')).then_some(style)
+    }
+
+    fn skip_white_space(&self, position: usize) -> usize {
+        position
+            + self.bytes[position..]
+                .iter()
+                .take_while(|b| is_white_space(**b))
+                .count()
+    }
+
+    fn glue_attribute(
+        &mut self,
+        style: AttributeStyle,
+    ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        let start = self.position;
+        // # ! [ の消費
+        self.position = self.skip_white_space(self.position + 1);
+        if style == AttributeStyle::Inner {
+            self.position = self.skip_white_space(self.position + 1);
+        }
+        self.position += 1;
+
+        let mut token_list = vec![];
+        let mut depth = 0;
+        loop {
+            self.skip_trivia()?;
+
+            let position = self.position;
+            if position >= self.bytes.len() {
+                return Err(TokenStreamParseError::UnterminatedAttribute { position: start });
+            }
+            let token_kind = self.glue_token()?;
+
+            match token_kind {
+                NagiProgramTokenKind::Symbol(NagiSymbol::RightBrackets) if depth == 0 => {
+                    let attribute = parse_attribute(style, &token_list, start..position + 1)?;
+                    return Ok(NagiProgramTokenKind::Attribute(attribute));
+                }
+                NagiProgramTokenKind::Symbol(
+                    NagiSymbol::LeftParenthesis | NagiSymbol::LeftBrackets | NagiSymbol::LeftBrace,
+                ) => depth += 1,
+                NagiProgramTokenKind::Symbol(
+                    NagiSymbol::RightParenthesis
+                    | NagiSymbol::RightBrackets
+                    | NagiSymbol::RightBrace,
+                ) => {
+                    if depth == 0 {
+                        return Err(TokenStreamParseError::UnexpectedToken { position });
+                    }
+                    depth -= 1;
+                }
+                _ => (),
+            }
+
+            token_list.push(NagiProgramToken {
+                token_kind,
+                position,
+                end: self.position,
+            });
+        }
+    }
+
+    /// symbols.rs と operators.rs の表の最長一致を直接書いたもの
+    fn glue_from_pattern(&mut self) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+        use NagiOperator as O;
+        use NagiProgramTokenKind::{Operator, Symbol};
+        use NagiSymbol as S;
+
+        let position = self.position;
+        let rest = &self.bytes[position..];
+        let (length, token_kind) = match rest {
+            [b'{', ..] => (1, Symbol(S::LeftBrace)),
+            [b'}', ..] => (1, Symbol(S::RightBrace)),
+            [b'[', ..] => (1, Symbol(S::LeftBrackets)),
+            [b']', ..] => (1, Symbol(S::RightBrackets)),
+            [b'(', ..] => (1, Symbol(S::LeftParenthesis)),
+            [b')', ..] => (1, Symbol(S::RightParenthesis)),
+            [b';', ..] => (1, Symbol(S::Semicolon)),
+            [b',', ..] => (1, Symbol(S::Comma)),
+            [b':', b':', ..] => (2, Symbol(S::DoubleColon)),
+            [b':', ..] => (1, Symbol(S::Colon)),
+            [b'#', ..] => (1, Symbol(S::Pound)),
+            [b'@', ..] => (1, Symbol(S::At)),
+            [b'-', b'>', ..] => (2, Symbol(S::Arrow)),
+            [b'-', b'=', ..] => (2, Operator(O::SubAssign)),
+            [b'-', ..] => (1, Operator(O::Sub)),
+            [b'=', b'>', ..] => (2, Symbol(S::FatArrow)),
+            [b'=', b'=', ..] => (2, Operator(O::Equal)),
+            [b'=', ..] => (1, Operator(O::Assign)),
+            [b'+', b'=', ..] => (2, Operator(O::AddAssign)),
+            [b'+', ..] => (1, Operator(O::Add)),
+            [b'*', b'=', ..] => (2, Operator(O::MulAssign)),
+            [b'*', ..] => (1, Operator(O::Mul)),
+            [b'/', b'=', ..] => (2, Operator(O::DivAssign)),
+            [b'/', ..] => (1, Operator(O::Div)),
+            [b'%', b'=', ..] => (2, Operator(O::ModAssign)),
+            [b'%', ..] => (1, Operator(O::Mod)),
+            [b'!', b'=', ..] => (2, Operator(O::NotEqual)),
+            [b'!', ..] => (1, Operator(O::Not)),
+            [b'>', b'>', b'=', ..] => (3, Operator(O::RightShiftAssign)),
+            [b'>', b'>', ..] => (2, Operator(O::RightShift)),
+            [b'>', b'=', ..] => (2, Operator(O::GreaterEqual)),
+            [b'>', ..] => (1, Operator(O::Greater)),
+            [b'<', b'<', b'=', ..] => (3, Operator(O::LeftShiftAssign)),
+            [b'<', b'<', ..] => (2, Operator(O::LeftShift)),
+            [b'<', b'=', ..] => (2, Operator(O::LessEqual)),
+            [b'<', ..] => (1, Operator(O::Less)),
+            [b'&', b'&', ..] => (2, Operator(O::And)),
+            [b'&', b'=', ..] => (2, Operator(O::BitwiseAndAssign)),
+            [b'&', ..] => (1, Operator(O::BitwiseAnd)),
+            [b'|', b'|', ..] => (2, Operator(O::Or)),
+            [b'|', b'=', ..] => (2, Operator(O::BitwiseOrAssign)),
+            [b'|', ..] => (1, Operator(O::BitwiseOr)),
+            [b'^', b'=', ..] => (2, Operator(O::BitwiseXorAssign)),
+            [b'^', ..] => (1, Operator(O::BitwiseXor)),
+            [b'~', ..] => (1, Operator(O::BitwiseNot)),
+            [b'?', ..] => (1, Operator(O::Question)),
+            [b'.', b'.', b'=', ..] => (3, Operator(O::RangeInclusive)),
+            [b'.', b'.', ..] => (2, Operator(O::Range)),
+            [b'.', ..] => (1, Operator(O::Dot)),
+            _ => return Err(TokenStreamParseError::UnmatchedToken { position }),
+        };
+
+        self.position += length;
+        Ok(token_kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tokenize_program_fused;
+    use crate::errors::TokenStreamParseError;
+    use crate::lexer::code::keywords::KeywordLocale;
+    use crate::lexer::code::nagi_code_lexer::{tokenize_program_with_keywords, NagiProgramToken};
+    use crate::lexer::Lexer;
+    use crate::test_util::Rng;
+    use std::time::Instant;
+
+    fn describe(result: Result<Lexer<NagiProgramToken>, TokenStreamParseError>) -> String {
+        let mut lexer = match result {
+            Ok(lexer) => lexer,
+            Err(e) => return format!("{e:?}"),
+        };
+
+        let mut tokens = vec![];
+        while let Some(token) = lexer.next() {
+            tokens.push(format!(
+                "{}..{} {:?}",
+                token.position, token.end, token.token_kind
+            ));
+        }
+        tokens.join("\n")
+    }
+
+    fn tokenize_two_stage(
+        source_code: &str,
+        default_locale: Option<KeywordLocale>,
+    ) -> Result<Lexer<NagiProgramToken>, TokenStreamParseError> {
+        let token_list = nagi_lexer::tokenize(source_code)?;
+        tokenize_program_with_keywords(&token_list, default_locale)
+    }
+
+    // 断片をランダムに連結したソースコードで, 2段階の切り分けと結果やエラーが一致することを確かめる
+    #[test]
+    fn matches_two_stage() {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        let mut succeeded = 0;
+        for _ in 0..20000 {
+            let source_code = rng.source_code(20);
+
+            for locale in [
+                None,
+                Some(KeywordLocale::English),
+                Some(KeywordLocale::Japanese),
+            ] {
+                let two_stage = tokenize_two_stage(&source_code, locale);
+                succeeded += two_stage.is_ok() as usize;
+                assert_eq!(
+                    describe(tokenize_program_fused(&source_code, locale)),
+                    describe(two_stage),
+                    "{source_code:?} {locale:?}"
+                );
+            }
+        }
+        assert!(succeeded > 5000, "only {succeeded} sources were tokenized");
+    }
+
+    #[test]
+    fn same_character_errors() {
+        for (source_code, expected) in [
+            (
+                "let a = 1;\u{3000}",
+                "InvalidCharacters { c: '\\u{3000}', position: 10 }",
+            ),
+            ("\"💥\"", "InvalidCharacters { c: '💥', position: 1 }"),
+            (
+                "// \u{000C}",
+                "UnusableWhitespace { c: '\\u{c}', position: 3 }",
+            ),
+        ] {
+            let expected = format!("Tokenize({expected})");
+            assert_eq!(
+                describe(tokenize_program_fused(source_code, None)),
+                expected
+            );
+            assert_eq!(describe(tokenize_two_stage(source_code, None)), expected);
+        }
+    }
+
+    // cargo test --release -- --ignored --nocapture bench で実行する
+    #[test]
+    #[ignore]
+    fn bench_against_two_stage() {
+        let unit = "// keywords: en\n#[inline]\nfn add(a: i32, b: i32) -> i32 {\n    /* c */ let x = 0x1f + 3.5e-2 + 'c' as i32; // t\n    if a >>= b { return r#\"raw\"#; } \"s\\n\"\n}\n";
+        let source_code = unit.repeat(20000);
+
+        let start = Instant::now();
+        let two_stage = tokenize_two_stage(&source_code, None).unwrap();
+        let two_stage_time = start.elapsed();
+
+        let start = Instant::now();
+        let fused = tokenize_program_fused(&source_code, None).unwrap();
+        let fused_time = start.elapsed();
+
+        drop((two_stage, fused));
+        eprintln!(
+            "{} bytes: two-stage {two_stage_time:?}, fused {fused_time:?}",
+            source_code.len()
+        );
+    }
+}
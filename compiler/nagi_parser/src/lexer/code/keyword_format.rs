@@ -0,0 +1,173 @@
+use super::keywords::{KeywordKind, KeywordLocale, NagiCodeKeyword};
+use super::nagi_code_lexer::keyword_locale_directive;
+use super::syntax_tree::{build_syntax_tree, red::SyntaxToken, SyntaxKind};
+use crate::errors::TokenStreamParseError;
+use nagi_lexer::token::Token;
+use std::ops::Range;
+
+// nagi fmt --to=en|ja で使うキーワードの言語の変換
+// 構文木の各トークンを置き換えるので, 空白やコメントはそのまま残る
+
+/// キーワードをtargetの言語の表記に書き換えたソースコードを返す
+///
+/// 変換先でキーワードになる識別子には r# を付ける
+/// 変換先が日本語でファイルに指定がない場合は先頭に指定のコメントを追加する
+pub fn convert_keyword_locale(
+    source_code: &str,
+    token_list: &[Token],
+    default_locale: Option<KeywordLocale>,
+    target: KeywordLocale,
+) -> Result<String, TokenStreamParseError> {
+    let directive = keyword_locale_directive(token_list);
+    let locale = directive
+        .as_ref()
+        .map(|(locale, _)| *locale)
+        .or(default_locale);
+    let source_locale = locale.unwrap_or(KeywordLocale::English);
+
+    let syntax_tree = build_syntax_tree(source_code, token_list, locale)?;
+
+    let mut replacements: Vec<(Range<usize>, String)> = vec![];
+    match directive {
+        Some((_, range)) => replacements.push((range, target.name().to_string())),
+        None if target == KeywordLocale::Japanese => {
+            replacements.push((0..0, format!("// keywords: {}\n", target.name())))
+        }
+        None => (),
+    }
+
+    let tokens = syntax_tree.tokens();
+    for (i, token) in tokens.iter().enumerate() {
+        let keyword = match token.kind() {
+            SyntaxKind::Keyword | SyntaxKind::Identifier => {
+                NagiCodeKeyword::from_str_with_locale(token.text())
+            }
+            _ => None,
+        };
+        let Some((keyword, keyword_locale)) = keyword else {
+            continue;
+        };
+
+        let text = match (token.kind(), keyword.kind()) {
+            (SyntaxKind::Keyword, _) => keyword.text(target).to_string(),
+            // 文脈キーワードは識別子として切り分けられているので, キーワードの位置にあるものだけを変換する
+            (_, KeywordKind::Contextual)
+                if keyword_locale == source_locale
+                    && is_contextual_keyword_position(keyword, tokens.get(i + 1)) =>
+            {
+                keyword.text(target).to_string()
+            }
+            // 変換前は識別子として使えていた変換先のキーワード
+            (_, KeywordKind::Strict) if keyword_locale == target => {
+                if !keyword.is_raw_identifier_allowed() {
+                    return Err(TokenStreamParseError::InvalidRawIdentifier {
+                        position: token.text_range().start,
+                    });
+                }
+                format!("r#{}", token.text())
+            }
+            _ => continue,
+        };
+
+        replacements.push((token.text_range(), text));
+    }
+
+    let mut result = String::with_capacity(source_code.len());
+    let mut last = 0;
+    for (range, text) in replacements {
+        result += &source_code[last..range.start];
+        result += &text;
+        last = range.end;
+    }
+    result += &source_code[last..];
+
+    Ok(result)
+}
+
+// 構文解析ができるまでは次のトークンで文脈キーワードの位置かを判定する
+// union Foo { .. } と where T: Trait, where 'a: 'b, where Self: Trait, where for<'a> ..
+// 変数名などの識別子の後に識別子が続くことはないので, let union = 1; などは変換しない
+fn is_contextual_keyword_position(keyword: NagiCodeKeyword, next: Option<&SyntaxToken>) -> bool {
+    let Some(next) = next else {
+        return false;
+    };
+
+    match (keyword, next.kind()) {
+        (_, SyntaxKind::Identifier) => true,
+        (NagiCodeKeyword::Where, SyntaxKind::Label) => true,
+        (NagiCodeKeyword::Where, SyntaxKind::Keyword) => matches!(
+            NagiCodeKeyword::from_str_with_locale(next.text()),
+            Some((NagiCodeKeyword::SelfType | NagiCodeKeyword::For, _))
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert_keyword_locale;
+    use crate::lexer::code::keywords::KeywordLocale;
+
+    fn convert(
+        source_code: &str,
+        default_locale: Option<KeywordLocale>,
+        target: KeywordLocale,
+    ) -> String {
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        convert_keyword_locale(source_code, &token_list, default_locale, target).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let source_code = "fn f<T>(x: T) where T: Copy { let y = x; }";
+        let ja = convert(source_code, None, KeywordLocale::Japanese);
+        assert_eq!(
+            ja,
+            "// keywords: ja\n関数 f<T>(x: T) 条件 T: Copy { 変数 y = x; }"
+        );
+        assert_eq!(
+            convert(&ja, None, KeywordLocale::English),
+            "// keywords: en\nfn f<T>(x: T) where T: Copy { let y = x; }"
+        );
+    }
+
+    // 文脈キーワードは識別子として使われている場合は変換しない
+    #[test]
+    fn contextual_keywords() {
+        assert_eq!(
+            convert(
+                "union U { a: i32 }\nlet union = 1; f(union, where);",
+                None,
+                KeywordLocale::Japanese
+            ),
+            "// keywords: ja\n共用体 U { a: i32 }\n変数 union = 1; f(union, where);"
+        );
+        assert_eq!(
+            convert(
+                "fn f() where Self: Sized, 'a: 'b {}",
+                None,
+                KeywordLocale::Japanese
+            ),
+            "// keywords: ja\n関数 f() 条件 自型: Sized, 'a: 'b {}"
+        );
+    }
+
+    // 変換元の言語はファイルの指定, なければdefault_localeに従う
+    #[test]
+    fn source_locale() {
+        let source_code = "関数 f() { 共用体 U {} 変数 x = 1; }";
+        assert_eq!(
+            convert(
+                source_code,
+                Some(KeywordLocale::Japanese),
+                KeywordLocale::English
+            ),
+            "fn f() { union U {} let x = 1; }"
+        );
+        // 英語のファイルでは日本語のキーワードは識別子なので r# を付ける
+        assert_eq!(
+            convert(source_code, None, KeywordLocale::Japanese),
+            "// keywords: ja\nr#関数 f() { 共用体 U {} r#変数 x = 1; }"
+        );
+    }
+}
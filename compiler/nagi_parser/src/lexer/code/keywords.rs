@@ -2,8 +2,20 @@ use std::str::FromStr;
 
 use crate::errors::TokenStreamParseError;
 
-#[derive(Debug)]
-pub(crate) enum NagiCodeKeyword {
+// 厳格キーワードは常にキーワードとして扱い, 識別子には r#match のように r# を付けた場合のみ使える
+// 文脈キーワードは識別子として切り分け, 構文解析が特定の位置でのみキーワードとして扱う
+//
+// キーワードは英語と日本語の組があり, ファイル単位でどちらか一方を選ぶ
+// 選ばれていない組のキーワードを使うとエラーになる
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordKind {
+    Strict,
+    Contextual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NagiCodeKeyword {
     Fn,
     Let,
     Ref,
@@ -27,39 +39,167 @@ pub(crate) enum NagiCodeKeyword {
     Match,
     Static,
     Extern,
+    True,
+    False,
+    As,
+    Use,
+    Mod,
+    Trait,
+    SelfValue, // self
+    SelfType,  // Self
+    Where,
+    Unsafe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordLocale {
+    English,
+    Japanese,
 }
 
+// キーワードと英語・日本語の表記
+const KEYWORD_TABLE: [(NagiCodeKeyword, &str, &str); 33] = [
+    (NagiCodeKeyword::Fn, "fn", "関数"),
+    (NagiCodeKeyword::Let, "let", "変数"),
+    (NagiCodeKeyword::Ref, "ref", "参照"),
+    (NagiCodeKeyword::Mut, "mut", "可変"),
+    (NagiCodeKeyword::Const, "const", "定数"),
+    (NagiCodeKeyword::Loop, "loop", "繰り返す"),
+    (NagiCodeKeyword::For, "for", "毎"),
+    (NagiCodeKeyword::While, "while", "間"),
+    (NagiCodeKeyword::If, "if", "もし"),
+    (NagiCodeKeyword::Else, "else", "でなければ"),
+    (NagiCodeKeyword::In, "in", "内"),
+    (NagiCodeKeyword::Impl, "impl", "実装"),
+    (NagiCodeKeyword::Return, "return", "戻る"),
+    (NagiCodeKeyword::Break, "break", "抜ける"),
+    (NagiCodeKeyword::Continue, "continue", "続ける"),
+    (NagiCodeKeyword::Struct, "struct", "構造体"),
+    (NagiCodeKeyword::Union, "union", "共用体"),
+    (NagiCodeKeyword::Enum, "enum", "列挙型"),
+    (NagiCodeKeyword::Pub, "pub", "公開"),
+    (NagiCodeKeyword::Type, "type", "型"),
+    (NagiCodeKeyword::Match, "match", "照合"),
+    (NagiCodeKeyword::Static, "static", "静的"),
+    (NagiCodeKeyword::Extern, "extern", "外部"),
+    (NagiCodeKeyword::True, "true", "真"),
+    (NagiCodeKeyword::False, "false", "偽"),
+    (NagiCodeKeyword::As, "as", "として"),
+    (NagiCodeKeyword::Use, "use", "使う"),
+    (NagiCodeKeyword::Mod, "mod", "モジュール"),
+    (NagiCodeKeyword::Trait, "trait", "特性"),
+    (NagiCodeKeyword::SelfValue, "self", "自身"),
+    (NagiCodeKeyword::SelfType, "Self", "自型"),
+    (NagiCodeKeyword::Where, "where", "条件"),
+    (NagiCodeKeyword::Unsafe, "unsafe", "危険"),
+];
+
+// 英語・日本語の全てのキーワードの完全ハッシュ表
+// 要素はKEYWORD_TABLEの添字 * 2 + 言語(英語0, 日本語1)に1を足したもので, 0は空き
+// シードは衝突しないように選んだ値で, キーワードを追加して衝突した場合はコンパイルエラーになる
+const KEYWORD_HASH_SEED: u32 = 0x811c9dd4;
+const KEYWORD_HASH_SIZE: usize = 512;
+const KEYWORD_HASH_TABLE: [u8; KEYWORD_HASH_SIZE] = make_keyword_hash_table();
+
+// FNV-1a
+const fn keyword_hash(bytes: &[u8]) -> usize {
+    let mut hash = KEYWORD_HASH_SEED;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(16777619);
+        i += 1;
+    }
+
+    hash as usize % KEYWORD_HASH_SIZE
+}
+
+const fn make_keyword_hash_table() -> [u8; KEYWORD_HASH_SIZE] {
+    let mut table = [0; KEYWORD_HASH_SIZE];
+    let mut i = 0;
+    while i < KEYWORD_TABLE.len() {
+        let (_, english, japanese) = KEYWORD_TABLE[i];
+        let texts = [english, japanese];
+        let mut locale = 0;
+        while locale < texts.len() {
+            let hash = keyword_hash(texts[locale].as_bytes());
+            if table[hash] != 0 {
+                panic!("keyword hash collision, change KEYWORD_HASH_SEED");
+            }
+            table[hash] = (i * 2 + locale + 1) as u8;
+            locale += 1;
+        }
+        i += 1;
+    }
+
+    table
+}
+
+impl NagiCodeKeyword {
+    /// 英語・日本語のどちらのキーワードかも合わせて返す
+    /// 識別子ごとに呼ばれるので完全ハッシュで1回の比較にする
+    pub fn from_str_with_locale(s: &str) -> Option<(Self, KeywordLocale)> {
+        let entry = KEYWORD_HASH_TABLE[keyword_hash(s.as_bytes())];
+        let index = entry.checked_sub(1)? as usize;
+        let (keyword, english, japanese) = KEYWORD_TABLE[index / 2];
+
+        let (text, locale) = match index % 2 {
+            0 => (english, KeywordLocale::English),
+            _ => (japanese, KeywordLocale::Japanese),
+        };
+
+        (text == s).then_some((keyword, locale))
+    }
+
+    pub fn text(&self, locale: KeywordLocale) -> &'static str {
+        let (_, english, japanese) = KEYWORD_TABLE
+            .iter()
+            .find(|(keyword, _, _)| keyword == self)
+            .unwrap();
+
+        match locale {
+            KeywordLocale::English => english,
+            KeywordLocale::Japanese => japanese,
+        }
+    }
+
+    pub fn kind(&self) -> KeywordKind {
+        match self {
+            NagiCodeKeyword::Union | NagiCodeKeyword::Where => KeywordKind::Contextual,
+            _ => KeywordKind::Strict,
+        }
+    }
+
+    /// r# を付けても識別子にできないキーワード
+    pub fn is_raw_identifier_allowed(&self) -> bool {
+        !matches!(self, NagiCodeKeyword::SelfValue | NagiCodeKeyword::SelfType)
+    }
+}
+
+/// 英語・日本語のどちらのキーワードも受け付ける
 impl FromStr for NagiCodeKeyword {
     type Err = TokenStreamParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let keyword = match s {
-            "let" => NagiCodeKeyword::Let,
-            "ref" => NagiCodeKeyword::Ref,
-            "mut" => NagiCodeKeyword::Mut,
-            "pub" => NagiCodeKeyword::Pub,
-            "fn" => NagiCodeKeyword::Fn,
-            "struct" => NagiCodeKeyword::Struct,
-            "enum" => NagiCodeKeyword::Enum,
-            "union" => NagiCodeKeyword::Union,
-            "const" => NagiCodeKeyword::Const,
-            "if" => NagiCodeKeyword::If,
-            "else" => NagiCodeKeyword::Else,
-            "match" => NagiCodeKeyword::Match,
-            "in" => NagiCodeKeyword::In,
-            "for" => NagiCodeKeyword::For,
-            "while" => NagiCodeKeyword::While,
-            "loop" => NagiCodeKeyword::Loop,
-            "break" => NagiCodeKeyword::Break,
-            "continue" => NagiCodeKeyword::Continue,
-            "return" => NagiCodeKeyword::Return,
-            "type" => NagiCodeKeyword::Type,
-            "static" => NagiCodeKeyword::Static,
-            "extern" => NagiCodeKeyword::Extern,
-            "impl" => NagiCodeKeyword::Impl,
-            _ => return Err(TokenStreamParseError::NotKeyword),
-        };
+        Self::from_str_with_locale(s)
+            .map(|(keyword, _)| keyword)
+            .ok_or(TokenStreamParseError::NotKeyword)
+    }
+}
+
+impl KeywordLocale {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeywordLocale::English => "en",
+            KeywordLocale::Japanese => "ja",
+        }
+    }
 
-        Ok(keyword)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "en" => Some(KeywordLocale::English),
+            "ja" => Some(KeywordLocale::Japanese),
+            _ => None,
+        }
     }
 }
@@ -1,13 +1,20 @@
 use super::{
-    keywords::NagiCodeKeyword, operators::OPERATOR_PATTERN_MAP, symbols::SYMBOL_PATTERN_MAP,
+    attribute::parse_attribute,
+    escape::{token_chars, unescape, unescape_bytes},
+    keywords::{KeywordKind, KeywordLocale, NagiCodeKeyword},
+    number::{parse_number, NagiNumberSuffix},
+    operators::OPERATOR_PATTERN_MAP,
+    symbols::SYMBOL_PATTERN_MAP,
 };
 use crate::{
     errors::TokenStreamParseError,
     lexer::{Lexer, PatternHashMap},
 };
+use nagi_ast::{Attribute, AttributeStyle};
 use nagi_lexer::token::{self, Symbol, Token, TokenKind};
 use std::{
     iter::{from_fn, Peekable},
+    ops::Range,
     slice::Iter,
     str::FromStr,
 };
@@ -17,6 +24,7 @@ use std::{
 pub struct NagiProgramToken {
     pub token_kind: NagiProgramTokenKind,
     pub position: usize,
+    pub end: usize, // トークンの直後の位置
 }
 
 #[derive(Debug)]
@@ -25,6 +33,21 @@ pub enum NagiProgramTokenKind {
     Literal(NagiLiteral),
     Operator(NagiOperator),
     Symbol(NagiSymbol),
+    Label(String), // 'outer の outer
+    DocComment(NagiDocComment),
+    Attribute(Attribute), // #[...] #![...]
+}
+
+#[derive(Debug)]
+pub enum NagiDocComment {
+    Outer(String), // /// 直後の要素に付く
+    Inner(String), // //! 囲んでいる要素に付く
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NagiCommentKind {
+    Line,
+    Block,
 }
 
 #[derive(Debug)]
@@ -35,10 +58,35 @@ pub enum NagiIdentifier {
 
 #[derive(Debug)]
 pub enum NagiLiteral {
-    Integer { value: u64, suffix: Option<String> },
-    Float { value: f64, suffix: Option<String> },
+    Integer {
+        value: u128,
+        suffix: Option<NagiNumberSuffix>,
+    },
+    Float {
+        value: String, // 10進数の文字列, _は除く
+        suffix: Option<NagiNumberSuffix>,
+    },
     //
-    String { first: usize, end: usize },
+    String {
+        value: String,
+        span: Range<usize>, // 両端の " を含む
+    },
+    Char {
+        value: char,
+        span: Range<usize>, // 両端の ' を含む
+    },
+    RawString {
+        value: String,
+        span: Range<usize>, // r と両端の # を含む
+    },
+    ByteString {
+        value: Vec<u8>,
+        span: Range<usize>, // b を含む
+    },
+    Byte {
+        value: u8,
+        span: Range<usize>, // b を含む
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -81,6 +129,8 @@ pub enum NagiOperator {
 
     Question,
     Dot,
+    Range,          // ..
+    RangeInclusive, // ..=
 }
 
 #[derive(Debug, Clone)]
@@ -93,141 +143,455 @@ pub enum NagiSymbol {
     RightBrace,       // }
     Semicolon,
     Comma,
+    Colon,       // :
+    DoubleColon, // ::
+    Arrow,       // ->
+    FatArrow,    // =>
+    Pound,       // #
+    At,          // @
 }
 
-type ParseIter<'a> = Peekable<Iter<'a, Token<'a>>>;
+impl NagiOperator {
+    /// 先頭の > を取り除いた残りの演算子を返す
+    /// >> >>= >= 以外はNone
+    pub fn split_greater(&self) -> Option<NagiOperator> {
+        match self {
+            NagiOperator::RightShift => Some(NagiOperator::Greater),
+            NagiOperator::RightShiftAssign => Some(NagiOperator::GreaterEqual),
+            NagiOperator::GreaterEqual => Some(NagiOperator::Assign),
+            _ => None,
+        }
+    }
+}
+
+// 構文解析向けの操作
+impl Lexer<NagiProgramToken> {
+    /// 型引数の閉じなど > が1つだけ必要な場面で > を1つ消費し, その範囲を返す
+    /// 接着済みの >> >>= >= は先頭の > だけを消費し, 残りを1文字後ろの位置のトークンに置き換える (rewindで元に戻る)
+    /// Vec<Vec<i32>> の >> を > と > に分けるために使う
+    pub fn eat_greater_than(&mut self) -> Option<Range<usize>> {
+        let token = self.peek()?;
+        let NagiProgramTokenKind::Operator(operator) = &token.token_kind else {
+            return None;
+        };
+        let position = token.position;
+        let end = token.end;
+
+        if let NagiOperator::Greater = operator {
+            self.next();
+            return Some(position..position + 1);
+        }
+
+        let rest = operator.split_greater()?;
+        self.replace_token(NagiProgramToken {
+            token_kind: NagiProgramTokenKind::Operator(rest),
+            position: position + 1,
+            end,
+        });
+
+        Some(position..position + 1)
+    }
+}
+
+impl NagiIdentifier {
+    /// 文脈キーワードの位置で使う
+    /// 識別子として切り分けられた文脈キーワードをキーワードとして返す
+    /// localeはファイルのキーワードの言語で, Noneの場合は英語のキーワードのみを受け付ける
+    pub fn contextual_keyword(&self, locale: Option<KeywordLocale>) -> Option<NagiCodeKeyword> {
+        let NagiIdentifier::Identifier(ident) = self else {
+            return None;
+        };
+
+        let selected_locale = locale.unwrap_or(KeywordLocale::English);
+        NagiCodeKeyword::from_str_with_locale(ident)
+            .filter(|(k, l)| k.kind() == KeywordKind::Contextual && *l == selected_locale)
+            .map(|(k, _)| k)
+    }
+}
+
+pub(crate) type ParseIter<'a> = Peekable<Iter<'a, Token<'a>>>;
+type QuotedChars<'a> = (Vec<(usize, char)>, &'a Token<'a>); // 中身の文字と閉じの引用符
+
+const KEYWORD_LOCALE_DIRECTIVE: &str = "keywords:";
 
 pub fn tokenize_program(
     token_list: &[Token],
 ) -> Result<Lexer<NagiProgramToken>, TokenStreamParseError> {
+    tokenize_program_with_keywords(token_list, None)
+}
+
+/// default_localeはファイルにキーワードの指定がない場合に使う
+/// どちらもない場合は英語のキーワードのみを使い, 日本語のキーワードは識別子として扱う
+pub fn tokenize_program_with_keywords(
+    token_list: &[Token],
+    default_locale: Option<KeywordLocale>,
+) -> Result<Lexer<NagiProgramToken>, TokenStreamParseError> {
+    let locale = keyword_locale_directive(token_list)
+        .map(|(locale, _)| locale)
+        .or(default_locale);
+
+    let source_end = token_list.last().map_or(0, token_end);
     let mut iter = token_list.iter().peekable();
     let mut token_list = vec![];
     while iter.peek().is_some() {
-        glue_comment(&mut iter); // 先にコメント処理
+        while glue_comment(&mut iter)?.is_some() {} // 先にコメント処理 (連続するコメントも読み飛ばす)
 
         let Some(token) = iter.peek() else {
             break;
         };
 
         let position = token.token_pos;
-        let token_kind = &token.token_kind;
 
-        match token_kind {
-            TokenKind::Identifier(_) => {
-                token_list.push(NagiProgramToken {
-                    token_kind: glue_identifier(&mut iter)?,
-                    position,
-                });
-            }
-            TokenKind::Number(_) => {
-                token_list.push(NagiProgramToken {
-                    token_kind: glue_literal(&mut iter)?,
-                    position,
-                });
-            }
-            TokenKind::Symbol(_) => {
-                token_list.push(NagiProgramToken {
-                    token_kind: glue_symbol_or_operator(&mut iter)?,
-                    position,
-                });
-            }
-            TokenKind::WhiteSpace(_) | TokenKind::LineBreak(_) => {
-                skip_white_space(&mut iter);
-            }
+        if matches!(
+            token.token_kind,
+            TokenKind::WhiteSpace(_) | TokenKind::LineBreak(_)
+        ) {
+            skip_white_space(&mut iter);
+            continue;
         }
+
+        let token_kind = glue_program_token(&mut iter, locale)?;
+        token_list.push(NagiProgramToken {
+            token_kind,
+            position,
+            end: iter.peek().map_or(source_end, |t| t.token_pos),
+        });
     }
 
     Ok(Lexer::new(token_list))
 }
 
+fn token_end(token: &Token) -> usize {
+    token_chars(token)
+        .last()
+        .map_or(token.token_pos, |(position, c)| position + c.len_utf8())
+}
+
+/// 空白とコメント以外のトークンを1つ接着する
+pub(crate) fn glue_program_token<'a>(
+    iter: &mut ParseIter<'a>,
+    locale: Option<KeywordLocale>,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let Some(token) = iter.peek() else {
+        return Err(TokenStreamParseError::UnexpectedEOF);
+    };
+
+    match token.token_kind {
+        TokenKind::Identifier(_) => glue_identifier(iter, locale),
+        TokenKind::Number(_) => glue_literal(iter),
+        TokenKind::Symbol(_) => glue_symbol_or_operator(iter, locale),
+        TokenKind::WhiteSpace(_) | TokenKind::LineBreak(_) => {
+            Err(TokenStreamParseError::UnexpectedToken {
+                position: token.token_pos,
+            })
+        }
+    }
+}
+
 /// キーワードもしくは識別子に変換する
+/// localeが指定されている場合, もう一方の言語の厳格キーワードはエラーにする
 fn glue_identifier<'a>(
     iter: &mut ParseIter<'a>,
+    locale: Option<KeywordLocale>,
 ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    expect_token(iter, |t| matches!(t.token_kind, TokenKind::Identifier(_)))?;
+    let token = expect_token(iter, |t| matches!(t.token_kind, TokenKind::Identifier(_)))?;
+
+    if let Some(token_kind) = glue_prefixed_literal(iter)? {
+        return Ok(token_kind);
+    }
+
+    if let Some(ident) = glue_raw_identifier(iter)? {
+        return Ok(NagiProgramTokenKind::Identifier(
+            NagiIdentifier::Identifier(ident),
+        ));
+    }
 
     let ident = glue_text_with_underscore(iter)?;
 
-    if let Ok(keyword) = NagiCodeKeyword::from_str(&ident) {
-        return Ok(NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(
-            keyword,
-        )));
+    let selected_locale = locale.unwrap_or(KeywordLocale::English);
+    match NagiCodeKeyword::from_str_with_locale(&ident) {
+        Some((keyword, keyword_locale)) if keyword.kind() == KeywordKind::Strict => {
+            if keyword_locale == selected_locale {
+                return Ok(NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(
+                    keyword,
+                )));
+            }
+
+            if locale.is_some() {
+                return Err(TokenStreamParseError::MixedKeywordLocale {
+                    position: token.token_pos,
+                });
+            }
+
+            Ok(NagiProgramTokenKind::Identifier(
+                NagiIdentifier::Identifier(ident),
+            ))
+        }
+        _ => Ok(NagiProgramTokenKind::Identifier(
+            NagiIdentifier::Identifier(ident),
+        )),
     }
+}
 
-    Ok(NagiProgramTokenKind::Identifier(
-        NagiIdentifier::Identifier(ident),
-    ))
+/// KEYWORD_LOCALE_DIRECTIVE ::= "//" WHITE_SPACE* "keywords:" WHITE_SPACE* ( "en" | "ja" ) WHITE_SPACE*
+///
+/// ファイル先頭のコメントからキーワードの言語の指定を探す
+/// 指定された言語と, その名前の範囲を返す
+pub(crate) fn keyword_locale_directive(
+    token_list: &[Token],
+) -> Option<(KeywordLocale, Range<usize>)> {
+    let mut iter = token_list.iter().peekable();
+    loop {
+        skip_white_space(&mut iter);
+
+        if peek_doc_comment(&iter).is_some()
+            || !match_symbols(&mut iter, &[Symbol::Slash, Symbol::Slash])
+        {
+            return None;
+        }
+
+        let comment =
+            from_fn(|| iter.next_if(|t| !matches!(t.token_kind, TokenKind::LineBreak(_))))
+                .flat_map(token_chars);
+
+        if let Some(directive) = parse_keyword_locale_directive(comment) {
+            return Some(directive);
+        }
+    }
 }
 
-fn glue_literal<'a>(
+/// 行コメントの // より後ろの文字を受け取り, キーワードの言語の指定であれば解釈する
+/// 書式が完全に一致しないコメントは指定として扱わない
+pub(crate) fn parse_keyword_locale_directive(
+    comment: impl Iterator<Item = (usize, char)>,
+) -> Option<(KeywordLocale, Range<usize>)> {
+    let chars: Vec<(usize, char)> = comment.collect();
+    let text: String = chars.iter().map(|(_, c)| c).collect();
+
+    let rest = text
+        .trim_start()
+        .strip_prefix(KEYWORD_LOCALE_DIRECTIVE)?
+        .trim_start();
+    let name = rest.trim_end();
+    let locale = KeywordLocale::from_name(name)?;
+
+    // textの中のバイト位置から元の位置に戻す
+    let offset = text.len() - rest.len();
+    let (start, _) = chars[text[..offset].chars().count()];
+    Some((locale, start..start + name.len()))
+}
+
+/// RAW_IDENTIFIER ::= "r#" IDENTIFIER
+///
+/// キーワードを識別子として使うためのもの
+/// r# が続かなければNoneを返す
+fn glue_raw_identifier<'a>(
     iter: &mut ParseIter<'a>,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    let token = expect_token(iter, |t| matches!(t.token_kind, TokenKind::Number(_)))?;
-    let TokenKind::Number(num) = token.token_kind else {
-        unreachable!()
+) -> Result<Option<String>, TokenStreamParseError> {
+    let mut clone_iter = iter.clone();
+    let Some(prefix) = clone_iter.next_if(|t| t.token_kind == TokenKind::Identifier("r")) else {
+        return Ok(None);
     };
 
-    // 0始まり
-    if matches!(num, "0") {
-        return eat_literal_with_prefix(iter);
+    if clone_iter
+        .next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::Pound))
+        .is_none()
+        || !clone_iter.peek().is_some_and(|t| {
+            matches!(
+                t.token_kind,
+                TokenKind::Identifier(_) | TokenKind::Symbol(Symbol::Underscore)
+            )
+        })
+    {
+        return Ok(None);
     }
 
-    let value = eat_dec_literal(iter)?;
-
-    if iter
-        .next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::Dot))
-        .is_some()
+    *iter = clone_iter;
+    let ident = glue_text_with_underscore(iter)?;
+    if ident == "_"
+        || NagiCodeKeyword::from_str(&ident).is_ok_and(|k| !k.is_raw_identifier_allowed())
     {
-        return eat_float_literal(iter, value);
+        return Err(TokenStreamParseError::InvalidRawIdentifier {
+            position: prefix.token_pos,
+        });
     }
 
-    let suffix = eat_suffix(iter);
+    Ok(Some(ident))
+}
 
-    Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-        value,
-        suffix,
-    }))
+/// 数値リテラルを構成するトークンを集める
+/// 解釈は number::parse_number で行う
+fn glue_literal<'a>(
+    iter: &mut ParseIter<'a>,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    expect_token(iter, |t| matches!(t.token_kind, TokenKind::Number(_)))?;
+
+    let mut chars = eat_number_chars(iter);
+    let is_decimal = !matches!(chars.get(..2), Some([(_, '0'), (_, 'b' | 'o' | 'x')]));
+
+    // 小数点
+    // 1.foo や 1..2 のように . の後に識別子や . が続く場合は小数点として扱わない
+    if is_decimal && chars.iter().all(|(_, c)| c.is_ascii_digit() || *c == '_') {
+        let mut clone_iter = iter.clone();
+        if let Some(dot) = clone_iter.next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::Dot)) {
+            let is_fraction = !matches!(
+                clone_iter.peek().map(|t| &t.token_kind),
+                Some(
+                    TokenKind::Identifier(_) | TokenKind::Symbol(Symbol::Dot | Symbol::Underscore)
+                )
+            );
+
+            if is_fraction {
+                *iter = clone_iter;
+                chars.push((dot.token_pos, '.'));
+                chars.extend(eat_number_chars(iter));
+            }
+        }
+    }
+
+    // 指数の符号
+    // 2.5E-3 は 2 . 5E - 3 に切り分けられているので符号と続く数字を繋げる
+    if is_decimal && chars.last().is_some_and(|(_, c)| matches!(c, 'e' | 'E')) {
+        let mut clone_iter = iter.clone();
+        let sign = clone_iter.next_if(|t| {
+            matches!(
+                t.token_kind,
+                TokenKind::Symbol(Symbol::Plus | Symbol::Minus)
+            )
+        });
+
+        if let Some(sign) = sign
+            && clone_iter
+                .peek()
+                .is_some_and(|t| matches!(t.token_kind, TokenKind::Number(_)))
+        {
+            *iter = clone_iter;
+            chars.extend(token_chars(sign));
+            chars.extend(eat_number_chars(iter));
+        }
+    }
+
+    Ok(NagiProgramTokenKind::Literal(parse_number(&chars)?))
 }
 
 fn glue_symbol_or_operator<'a>(
     iter: &mut ParseIter<'a>,
+    locale: Option<KeywordLocale>,
 ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
     let Some(token) = iter.peek() else {
         return Err(TokenStreamParseError::UnexpectedEOF);
     };
-    let position = token.token_pos;
 
-    if let Ok(token_kind) = glue_symbol(iter) {
-        return Ok(token_kind);
+    if token.token_kind == TokenKind::Symbol(Symbol::DoubleQuotation) {
+        return glue_string_literal(iter);
     }
 
-    if let Ok(token_kind) = glue_operator(iter) {
-        return Ok(token_kind);
+    if token.token_kind == TokenKind::Symbol(Symbol::SingleQuotation) {
+        return glue_char_literal_or_label(iter);
     }
 
-    if let Ok(token_kind) = glue_string_literal(iter) {
-        return Ok(token_kind);
+    if peek_doc_comment(iter).is_some() {
+        return glue_doc_comment(iter);
     }
 
-    Err(TokenStreamParseError::UnmatchedToken { position })
+    if peek_attribute(iter).is_some() {
+        return glue_attribute(iter, locale);
+    }
+
+    glue_from_pattern(iter)
 }
 
-fn glue_operator<'a>(
-    iter: &mut ParseIter<'a>,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    glue_from_pattern(iter, &OPERATOR_PATTERN_MAP, |t| {
-        NagiProgramTokenKind::Operator(t)
-    })
+/// 属性の開始であれば, 内部属性かどうかを返す
+/// # ! [ の間には空白を置ける
+fn peek_attribute<'a>(iter: &ParseIter<'a>) -> Option<AttributeStyle> {
+    let mut clone_iter = iter.clone();
+    if !match_symbols(&mut clone_iter, &[Symbol::Pound]) {
+        return None;
+    }
+    skip_white_space(&mut clone_iter);
+
+    let style = if match_symbols(&mut clone_iter, &[Symbol::Not]) {
+        skip_white_space(&mut clone_iter);
+        AttributeStyle::Inner
+    } else {
+        AttributeStyle::Outer
+    };
+
+    match_symbols(&mut clone_iter, &[Symbol::LeftBrackets]).then_some(style)
 }
 
-fn glue_symbol<'a>(
+/// ATTRIBUTE ::= "#" "!"? "[" any-token* "]"
+///
+/// 中身は括弧の対応が取れる ] までのトークンを接着し, attribute::parse_attribute で解釈する
+fn glue_attribute<'a>(
     iter: &mut ParseIter<'a>,
+    locale: Option<KeywordLocale>,
 ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    glue_from_pattern(iter, &SYMBOL_PATTERN_MAP, |t| {
-        NagiProgramTokenKind::Symbol(t)
-    })
+    let Some(style) = peek_attribute(iter) else {
+        return Err(TokenStreamParseError::NotAttribute);
+    };
+    let Some(first) = iter.next() else {
+        return Err(TokenStreamParseError::UnexpectedEOF);
+    };
+    // ! と [ の消費
+    skip_white_space(iter);
+    if style == AttributeStyle::Inner {
+        iter.next();
+        skip_white_space(iter);
+    }
+    iter.next();
+
+    let mut token_list = vec![];
+    let mut depth = 0;
+    loop {
+        while glue_comment(iter)?.is_some()
+            || iter
+                .next_if(|t| {
+                    matches!(
+                        t.token_kind,
+                        TokenKind::WhiteSpace(_) | TokenKind::LineBreak(_)
+                    )
+                })
+                .is_some()
+        {}
+
+        let Some(token) = iter.peek() else {
+            return Err(TokenStreamParseError::UnterminatedAttribute {
+                position: first.token_pos,
+            });
+        };
+        let position = token.token_pos;
+        let token_kind = glue_program_token(iter, locale)?;
+
+        match token_kind {
+            NagiProgramTokenKind::Symbol(NagiSymbol::RightBrackets) if depth == 0 => {
+                let attribute = parse_attribute(style, &token_list, first.token_pos..position + 1)?;
+                return Ok(NagiProgramTokenKind::Attribute(attribute));
+            }
+            NagiProgramTokenKind::Symbol(
+                NagiSymbol::LeftParenthesis | NagiSymbol::LeftBrackets | NagiSymbol::LeftBrace,
+            ) => depth += 1,
+            NagiProgramTokenKind::Symbol(
+                NagiSymbol::RightParenthesis | NagiSymbol::RightBrackets | NagiSymbol::RightBrace,
+            ) => {
+                if depth == 0 {
+                    return Err(TokenStreamParseError::UnexpectedToken { position });
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+
+        // ] がないまま末尾に達した場合は次の繰り返しでエラーになる
+        token_list.push(NagiProgramToken {
+            token_kind,
+            position,
+            end: iter.peek().map_or(position, |t| t.token_pos),
+        });
+    }
 }
 
+/// STRING_LITERAL ::= "\"" ( ESCAPE | LINE_CONTINUE | [^"\\] )* "\""
 fn glue_string_literal<'a>(
     iter: &mut ParseIter<'a>,
 ) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
@@ -236,74 +600,436 @@ fn glue_string_literal<'a>(
     })?;
     iter.next();
 
-    while iter
-        .next_if(|t| !matches!(t.token_kind, TokenKind::Symbol(Symbol::DoubleQuotation)))
+    let (chars, end) = eat_quoted_chars(
+        iter,
+        Symbol::DoubleQuotation,
+        true,
+        TokenStreamParseError::UnterminatedString {
+            position: first.token_pos,
+        },
+    )?;
+
+    Ok(NagiProgramTokenKind::Literal(NagiLiteral::String {
+        value: unescape(&chars)?,
+        span: first.token_pos..end.token_pos + 1,
+    }))
+}
+
+/// RAW_STRING_LITERAL  ::= "r" RAW_STRING_CONTENT
+/// RAW_STRING_CONTENT  ::= "\"" any-char* "\"" | "#" RAW_STRING_CONTENT "#"
+/// BYTE_STRING_LITERAL ::= "b" STRING_LITERAL
+/// BYTE_LITERAL        ::= "b" CHAR_LITERAL
+///
+/// 接頭辞の直後にリテラルが続かなければ識別子として扱うのでNoneを返す
+fn glue_prefixed_literal<'a>(
+    iter: &mut ParseIter<'a>,
+) -> Result<Option<NagiProgramTokenKind>, TokenStreamParseError> {
+    let mut clone_iter = iter.clone();
+    let Some(prefix) = clone_iter.next() else {
+        return Ok(None);
+    };
+    let Some(TokenKind::Symbol(next)) = clone_iter.peek().map(|t| &t.token_kind) else {
+        return Ok(None);
+    };
+
+    let token_kind = match (&prefix.token_kind, next) {
+        (TokenKind::Identifier("r"), Symbol::DoubleQuotation | Symbol::Pound)
+            if is_raw_string_start(&clone_iter) =>
+        {
+            iter.next();
+            glue_raw_string_literal(iter, prefix)?
+        }
+        (TokenKind::Identifier("b"), Symbol::DoubleQuotation) => {
+            iter.next();
+            glue_byte_string_literal(iter, prefix)?
+        }
+        (TokenKind::Identifier("b"), Symbol::SingleQuotation) => {
+            iter.next();
+            glue_byte_literal(iter, prefix)?
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(token_kind))
+}
+
+// r の後に # が0個以上続き " が続くか
+fn is_raw_string_start<'a>(iter: &ParseIter<'a>) -> bool {
+    let mut clone_iter = iter.clone();
+    from_fn(|| clone_iter.next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::Pound))).count();
+
+    clone_iter
+        .next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::DoubleQuotation))
         .is_some()
-    {}
+}
 
-    let end = expect_token(iter, |t| {
+/// 開始の r は解析済み前提
+/// エスケープは解釈せず, 開始と同じ数の # が続く " までを中身とする
+fn glue_raw_string_literal<'a>(
+    iter: &mut ParseIter<'a>,
+    prefix: &Token,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let hashes =
+        from_fn(|| iter.next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::Pound))).count();
+    expect_token(iter, |t| {
         matches!(t.token_kind, TokenKind::Symbol(Symbol::DoubleQuotation))
     })?;
     iter.next();
 
-    Ok(NagiProgramTokenKind::Literal(NagiLiteral::String {
-        first: first.token_pos + 1,
-        end: end.token_pos - 1,
+    let mut chars = vec![];
+    let mut closest: Option<Range<usize>> = None; // # が足りなかった閉じの候補のうち最も近いもの
+    let end = loop {
+        let Some(token) = iter.next() else {
+            return Err(match closest {
+                Some(span) => TokenStreamParseError::RawStringHashMismatch {
+                    span,
+                    expected: hashes,
+                },
+                None => TokenStreamParseError::UnterminatedRawString {
+                    position: prefix.token_pos,
+                    hashes,
+                },
+            });
+        };
+
+        if token.token_kind != TokenKind::Symbol(Symbol::DoubleQuotation) {
+            chars.extend(token_chars(token));
+            continue;
+        }
+
+        let mut clone_iter = iter.clone();
+        let found =
+            from_fn(|| clone_iter.next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::Pound)))
+                .count();
+        let span = token.token_pos..token.token_pos + 1 + found;
+
+        if found == hashes {
+            *iter = clone_iter;
+            break span.end;
+        }
+
+        if found > hashes {
+            return Err(TokenStreamParseError::RawStringHashMismatch {
+                span,
+                expected: hashes,
+            });
+        }
+
+        // # が足りない " は中身として扱う
+        if closest.as_ref().is_none_or(|c| c.len() < span.len()) {
+            closest = Some(span);
+        }
+        chars.extend(token_chars(token));
+    };
+
+    Ok(NagiProgramTokenKind::Literal(NagiLiteral::RawString {
+        value: chars.into_iter().map(|(_, c)| c).collect(),
+        span: prefix.token_pos..end,
     }))
 }
 
-fn glue_comment<'a>(iter: &mut ParseIter<'a>) {
-    let result = match_token(iter, &[Symbol::Slash, Symbol::Slash], |token, symbol| {
-        let TokenKind::Symbol(target_symbol) = &token.token_kind else {
-            return false;
+/// 開始の b は解析済み前提
+fn glue_byte_string_literal<'a>(
+    iter: &mut ParseIter<'a>,
+    prefix: &Token,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    iter.next(); // " の消費
+
+    let (chars, end) = eat_quoted_chars(
+        iter,
+        Symbol::DoubleQuotation,
+        true,
+        TokenStreamParseError::UnterminatedString {
+            position: prefix.token_pos,
+        },
+    )?;
+
+    Ok(NagiProgramTokenKind::Literal(NagiLiteral::ByteString {
+        value: unescape_bytes(&chars)?,
+        span: prefix.token_pos..end.token_pos + 1,
+    }))
+}
+
+/// 開始の b は解析済み前提
+fn glue_byte_literal<'a>(
+    iter: &mut ParseIter<'a>,
+    prefix: &Token,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let position = prefix.token_pos;
+    iter.next(); // ' の消費
+
+    let (chars, end) = eat_quoted_chars(
+        iter,
+        Symbol::SingleQuotation,
+        false,
+        TokenStreamParseError::UnterminatedChar { position },
+    )?;
+
+    let value = match unescape_bytes(&chars)?[..] {
+        [byte] => byte,
+        [] => return Err(TokenStreamParseError::EmptyCharLiteral { position }),
+        _ => return Err(TokenStreamParseError::MultiCharCharLiteral { position }),
+    };
+
+    Ok(NagiProgramTokenKind::Literal(NagiLiteral::Byte {
+        value,
+        span: position..end.token_pos + 1,
+    }))
+}
+
+/// CHAR_LITERAL ::= "'" ( ESCAPE | [^'\\] ) "'"
+/// LABEL        ::= "'" IDENTIFIER
+///
+/// ' の後に識別子が続き, 直後に ' がなければラベルとして扱う
+fn glue_char_literal_or_label<'a>(
+    iter: &mut ParseIter<'a>,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let first = expect_token(iter, |t| {
+        matches!(t.token_kind, TokenKind::Symbol(Symbol::SingleQuotation))
+    })?;
+    iter.next();
+
+    let Some(token) = iter.peek() else {
+        return Err(TokenStreamParseError::UnterminatedChar {
+            position: first.token_pos,
+        });
+    };
+
+    if !matches!(
+        token.token_kind,
+        TokenKind::Identifier(_) | TokenKind::Symbol(Symbol::Underscore)
+    ) {
+        return glue_quoted_char(iter, first);
+    }
+
+    let ident = glue_text_with_underscore(iter)?;
+    let Some(end) = iter.next_if(|t| t.token_kind == TokenKind::Symbol(Symbol::SingleQuotation))
+    else {
+        return Ok(NagiProgramTokenKind::Label(ident));
+    };
+
+    let mut chars = ident.chars();
+    let (Some(value), None) = (chars.next(), chars.next()) else {
+        return Err(TokenStreamParseError::MultiCharCharLiteral {
+            position: first.token_pos,
+        });
+    };
+
+    Ok(NagiProgramTokenKind::Literal(NagiLiteral::Char {
+        value,
+        span: first.token_pos..end.token_pos + 1,
+    }))
+}
+
+/// 開始の ' は解析済み前提
+/// 閉じの ' までを1文字として解釈する
+fn glue_quoted_char<'a>(
+    iter: &mut ParseIter<'a>,
+    first: &Token,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let (chars, end) = eat_quoted_chars(
+        iter,
+        Symbol::SingleQuotation,
+        false,
+        TokenStreamParseError::UnterminatedChar {
+            position: first.token_pos,
+        },
+    )?;
+
+    let value = unescape(&chars)?;
+    let mut value_chars = value.chars();
+    let value = match (value_chars.next(), value_chars.next()) {
+        (Some(c), None) => c,
+        (None, _) => {
+            return Err(TokenStreamParseError::EmptyCharLiteral {
+                position: first.token_pos,
+            })
+        }
+        (Some(_), Some(_)) => {
+            return Err(TokenStreamParseError::MultiCharCharLiteral {
+                position: first.token_pos,
+            })
+        }
+    };
+
+    Ok(NagiProgramTokenKind::Literal(NagiLiteral::Char {
+        value,
+        span: first.token_pos..end.token_pos + 1,
+    }))
+}
+
+/// 開始の引用符は解析済み前提
+/// 閉じの引用符までの位置付きの文字と, 閉じの引用符のトークンを返す
+/// multi_lineがfalseの場合は改行を跨がない
+fn eat_quoted_chars<'a>(
+    iter: &mut ParseIter<'a>,
+    quote: Symbol,
+    multi_line: bool,
+    unterminated: TokenStreamParseError,
+) -> Result<QuotedChars<'a>, TokenStreamParseError> {
+    let mut next =
+        || iter.next_if(|t| multi_line || !matches!(t.token_kind, TokenKind::LineBreak(_)));
+
+    // \ の直後のトークンはエスケープされた文字から始まるので必ず中身として扱う
+    let mut chars = vec![];
+    loop {
+        let Some(token) = next() else {
+            return Err(unterminated);
         };
 
-        target_symbol == symbol
-    });
+        match &token.token_kind {
+            TokenKind::Symbol(symbol) if *symbol == quote => return Ok((chars, token)),
+            TokenKind::Symbol(Symbol::BackSlash) => {
+                chars.push((token.token_pos, '\\'));
+                let Some(escaped) = next() else {
+                    return Err(unterminated);
+                };
+                chars.extend(token_chars(escaped));
+            }
+            _ => chars.extend(token_chars(token)),
+        }
+    }
+}
 
-    if !result {
-        return;
+/// 通常のコメントを消費し, その種類を返す
+/// ドキュメントコメントはトークンとして扱うので消費しない
+pub(crate) fn glue_comment<'a>(
+    iter: &mut ParseIter<'a>,
+) -> Result<Option<NagiCommentKind>, TokenStreamParseError> {
+    if peek_doc_comment(iter).is_some() {
+        return Ok(None);
     }
 
-    // コメントは何もしない
-    // 今は1行コメントのみの対応
-    eat_line_comment(iter);
+    if match_symbols(iter, &[Symbol::Slash, Symbol::Slash]) {
+        eat_line_comment(iter);
+        return Ok(Some(NagiCommentKind::Line));
+    }
+
+    let Some(&first) = iter.peek() else {
+        return Ok(None);
+    };
+
+    if match_symbols(iter, &[Symbol::Slash, Symbol::Star]) {
+        eat_block_comment(iter, first.token_pos)?;
+        return Ok(Some(NagiCommentKind::Block));
+    }
+
+    Ok(None)
 }
 
-fn glue_from_pattern<'a, T>(
+/// OUTER_DOC_COMMENT ::= "///" [^\n]*  (//// は通常のコメント)
+/// INNER_DOC_COMMENT ::= "//!" [^\n]*
+fn glue_doc_comment<'a>(
     iter: &mut ParseIter<'a>,
-    pattern_map: &PatternHashMap<Symbol, T>,
-    token_constructor: impl Fn(T) -> NagiProgramTokenKind,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError>
-where
-    T: Clone,
-{
-    let token = expect_token(iter, |t| matches!(t.token_kind, TokenKind::Symbol(_)))?;
-    let TokenKind::Symbol(symbol) = &token.token_kind else {
-        unreachable!()
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let Some(token) = iter.peek() else {
+        return Err(TokenStreamParseError::UnexpectedEOF);
     };
+    let position = token.token_pos;
 
-    let Some(patterns) = pattern_map.get(symbol) else {
-        return Err(TokenStreamParseError::UnmatchedToken {
-            position: token.token_pos,
-        });
+    let Some(inner) = peek_doc_comment(iter) else {
+        return Err(TokenStreamParseError::UnmatchedToken { position });
+    };
+    iter.nth(2); // /// もしくは //! の消費
+
+    let text: String =
+        from_fn(|| iter.next_if(|t| !matches!(t.token_kind, TokenKind::LineBreak(_))))
+            .flat_map(token_chars)
+            .map(|(_, c)| c)
+            .collect();
+
+    let doc_comment = if inner {
+        NagiDocComment::Inner(text)
+    } else {
+        NagiDocComment::Outer(text)
     };
 
-    for (pattern, value) in patterns {
-        let result = match_token(
-            iter,
-            pattern,
-            |token, symbol| matches!(&token.token_kind, TokenKind::Symbol(s) if s == symbol),
-        );
+    Ok(NagiProgramTokenKind::DocComment(doc_comment))
+}
 
-        if result {
-            return Ok(token_constructor(value.clone()));
-        }
+/// ドキュメントコメントの開始であれば内部ドキュメントかどうかを返す
+fn peek_doc_comment<'a>(iter: &ParseIter<'a>) -> Option<bool> {
+    let mut clone_iter = iter.clone();
+    if !match_symbols(&mut clone_iter, &[Symbol::Slash, Symbol::Slash]) {
+        return None;
     }
 
-    Err(TokenStreamParseError::UnmatchedToken {
-        position: token.token_pos,
-    })
+    if match_symbols(&mut clone_iter, &[Symbol::Not]) {
+        return Some(true);
+    }
+
+    if match_symbols(&mut clone_iter, &[Symbol::Slash])
+        && !match_symbols(&mut clone_iter, &[Symbol::Slash])
+    {
+        return Some(false);
+    }
+
+    None
+}
+
+fn match_symbols<'a>(iter: &mut ParseIter<'a>, symbols: &[Symbol]) -> bool {
+    match_token(
+        iter,
+        symbols,
+        |token, symbol| matches!(&token.token_kind, TokenKind::Symbol(s) if s == symbol),
+    )
+}
+
+/// 記号と演算子の表から最も長く一致するものを接着する
+/// . と .. と ..= や = と == と => のように表をまたいで先頭が重なるため, 両方の表を比べる
+fn glue_from_pattern<'a>(
+    iter: &mut ParseIter<'a>,
+) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
+    let token = expect_token(iter, |t| matches!(t.token_kind, TokenKind::Symbol(_)))?;
+
+    let symbol = match_pattern(iter, &SYMBOL_PATTERN_MAP);
+    let operator = match_pattern(iter, &OPERATOR_PATTERN_MAP);
+
+    let (length, token_kind) = match (symbol, operator) {
+        (Some((symbol_length, symbol)), Some((operator_length, _)))
+            if symbol_length >= operator_length =>
+        {
+            (symbol_length, NagiProgramTokenKind::Symbol(symbol))
+        }
+        (_, Some((length, operator))) => (length, NagiProgramTokenKind::Operator(operator)),
+        (Some((length, symbol)), None) => (length, NagiProgramTokenKind::Symbol(symbol)),
+        (None, None) => {
+            return Err(TokenStreamParseError::UnmatchedToken {
+                position: token.token_pos,
+            })
+        }
+    };
+
+    iter.nth(length - 1);
+    Ok(token_kind)
+}
+
+/// 一致したパターンのうち最長のものの長さと値を返す
+/// イテレータは消費しない
+fn match_pattern<'a, T>(
+    iter: &ParseIter<'a>,
+    pattern_map: &PatternHashMap<Symbol, T>,
+) -> Option<(usize, T)>
+where
+    T: Clone,
+{
+    let TokenKind::Symbol(symbol) = &iter.clone().peek()?.token_kind else {
+        return None;
+    };
+
+    // パターンは長い順に並んでいる
+    pattern_map
+        .get(symbol)?
+        .iter()
+        .find_map(|(pattern, value)| {
+            let mut clone_iter = iter.clone();
+            match_token(
+                &mut clone_iter,
+                pattern,
+                |token, symbol| matches!(&token.token_kind, TokenKind::Symbol(s) if s == symbol),
+            )
+            .then(|| (pattern.len(), value.clone()))
+        })
 }
 
 fn match_token<'a, T, F>(iter: &mut ParseIter<'a>, list: &[T], condition: F) -> bool
@@ -350,171 +1076,20 @@ fn glue_text_with_underscore<'a>(
     Ok(num_text)
 }
 
-/// 0始まりのトークンを解析
-fn eat_literal_with_prefix<'a>(
-    iter: &mut ParseIter<'a>,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    expect_token(iter, |t| t.token_kind == TokenKind::Number("0"))?;
-    iter.next(); // 0は確定しているので次のトークンへ
-
-    // 次が終端かつ0のみの場合
-    let Some(token) = iter.peek() else {
-        return Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-            value: 0,
-            suffix: None,
-        }));
-    };
-
-    match &token.token_kind {
-        // 0 のみの場合
-        TokenKind::LineBreak(_) | TokenKind::WhiteSpace(_) => {
-            Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-                value: 0,
-                suffix: None,
-            }))
-        }
-        // 0と記号始まりの場合
-        TokenKind::Symbol(symbol) => eat_literal_with_symbol_prefix(iter, symbol),
-        TokenKind::Identifier(ident) => eat_literal_with_identifier_prefix(iter, ident),
-        TokenKind::Number(_) => unreachable!(),
-    }
-}
-
-fn eat_literal_with_symbol_prefix<'a>(
-    iter: &mut ParseIter<'a>,
-    symbol: &Symbol,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    match symbol {
-        Symbol::Dot => {
-            iter.next(); // .の消費
-            eat_float_literal(iter, 0)
-        }
-        Symbol::Underscore => {
-            let value = eat_dec_literal(iter)?;
-            let suffix = eat_suffix(iter);
-            Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-                value,
-                suffix,
-            }))
-        }
-        _ => Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-            value: 0,
-            suffix: None,
-        })),
-    }
-}
-
-fn eat_literal_with_identifier_prefix<'a>(
-    iter: &mut ParseIter<'a>,
-    ident: &str,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    let value = match ident {
-        "b" => eat_bin_literal(iter)?,
-        "o" => eat_oct_literal(iter)?,
-        "x" => eat_hex_literal(iter)?,
-        _ => {
-            return Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-                value: 0,
-                suffix: Some(ident.to_string()),
-            }))
-        }
-    };
-
-    Ok(NagiProgramTokenKind::Literal(NagiLiteral::Integer {
-        value,
-        suffix: eat_suffix(iter),
-    }))
-}
-
-/// BIN_LITERAL ::= 0b ( BIN_DIGIT | "_" )*BIN_DIGIT ( BIN_DIGIT | "_" )*
-/// BIN_DIGIT   ::= [0-1]
-fn eat_bin_literal<'a>(iter: &mut ParseIter<'a>) -> Result<u64, TokenStreamParseError> {
-    expect_token(iter, |t| {
-        matches!(
-            t.token_kind,
-            TokenKind::Number(_) | TokenKind::Symbol(Symbol::Underscore)
-        )
-    })?;
-
-    convert_to_number(iter, 2, |c| matches!(c, '0' | '1'))
-}
-
-/// OCT_LITERAL ::= 0o ( OCT_DIGIT | "_" )*OCT_DIGIT (OCT_DIGIT | "_" )*
-/// OCT_DIGIT   ::= [0-7]
-fn eat_oct_literal<'a>(iter: &mut ParseIter<'a>) -> Result<u64, TokenStreamParseError> {
-    expect_token(iter, |t| {
-        matches!(
-            t.token_kind,
-            TokenKind::Number(_) | TokenKind::Symbol(Symbol::Underscore)
-        )
-    })?;
-
-    convert_to_number(iter, 8, |c| matches!(c, '0'..='7'))
-}
-
-/// DEC_LITERAL ::= DEC_DIGIT ( DEC_DIGIT | "_" )*
-/// DEC_DIGIT   ::= [0-9]
-fn eat_dec_literal<'a>(iter: &mut ParseIter<'a>) -> Result<u64, TokenStreamParseError> {
-    expect_token(iter, |t| {
-        matches!(
-            t.token_kind,
-            TokenKind::Number(_) | TokenKind::Symbol(Symbol::Underscore)
-        )
-    })?;
-
-    convert_to_number(iter, 10, |c| c.is_ascii_digit())
-}
-
-/// HEX_LITERAL ::= 0x ( HEX_DIGIT | "_" )* HEX_DIGIT ( HEX_DIGIT | "_" )*
-/// HEX_DIGIT   ::= [0-9a-fA-F]
-///
-/// 0x は解析済み前提
-fn eat_hex_literal<'a>(iter: &mut ParseIter<'a>) -> Result<u64, TokenStreamParseError> {
-    expect_token(iter, |t| {
-        matches!(
-            t.token_kind,
-            TokenKind::Identifier(_) | TokenKind::Number(_) | TokenKind::Symbol(Symbol::Underscore)
-        )
-    })?;
-
-    convert_to_number(iter, 16, |c| c.is_ascii_hexdigit())
-}
-
-/// FLOAT_LITERAL ::= DEC_LITERAL "."
-///                 | DEC_LITERAL "." DEC_LITERAL
-///
-/// 先頭の DEC_LITERAL "." は解析済み前提
-fn eat_float_literal<'a>(
-    iter: &mut ParseIter<'a>,
-    front_dec: u64,
-) -> Result<NagiProgramTokenKind, TokenStreamParseError> {
-    let Ok(token) = expect_token(iter, |token| {
-        matches!(token.token_kind, TokenKind::Number(_))
-    }) else {
-        return Ok(NagiProgramTokenKind::Literal(NagiLiteral::Float {
-            value: front_dec as f64,
-            suffix: None,
-        }));
-    };
-
-    let value = format!("{front_dec}.{}", eat_dec_literal(iter)?)
-        .parse()
-        .map_err(|_| TokenStreamParseError::CannotConvertTextToNumbers {
-            position: token.token_pos,
-        })?;
-
-    Ok(NagiProgramTokenKind::Literal(NagiLiteral::Float {
-        value,
-        suffix: eat_suffix(iter),
-    }))
-}
-
-fn eat_suffix<'a>(iter: &mut ParseIter<'a>) -> Option<String> {
-    iter.next_if(|t| matches!(t.token_kind, TokenKind::Identifier(_)))
-        .map(|t| match t.token_kind {
-            TokenKind::Identifier(ident) => ident.to_string(),
-            _ => unreachable!(),
+// 数字と識別子と_が続くトークンを位置付きの文字の列として集める
+fn eat_number_chars<'a>(iter: &mut ParseIter<'a>) -> Vec<(usize, char)> {
+    from_fn(|| {
+        iter.next_if(|t| {
+            matches!(
+                t.token_kind,
+                TokenKind::Number(_)
+                    | TokenKind::Identifier(_)
+                    | TokenKind::Symbol(Symbol::Underscore)
+            )
         })
+    })
+    .flat_map(token_chars)
+    .collect()
 }
 
 fn eat_line_comment<'a>(iter: &mut ParseIter<'a>) {
@@ -522,39 +1097,24 @@ fn eat_line_comment<'a>(iter: &mut ParseIter<'a>) {
     from_fn(|| iter.next_if(|t| !matches!(t.token_kind, TokenKind::LineBreak(_)))).count();
 }
 
-fn convert_to_number<'a>(
+/// 入れ子の /* */ に対応する
+/// 先頭の /* は解析済み前提
+fn eat_block_comment<'a>(
     iter: &mut ParseIter<'a>,
-    radix: u32,
-    condition: impl Fn(&char) -> bool,
-) -> Result<u64, TokenStreamParseError> {
-    let Some(token) = iter.peek() else {
-        return Err(TokenStreamParseError::UnexpectedEOF);
-    };
-    let token_pos = token.token_pos;
-
-    let num_text = glue_text_with_underscore(iter)?;
-    if num_text.is_empty() {
-        return Err(TokenStreamParseError::UnmatchedToken {
-            position: token_pos,
-        });
-    }
-
-    for (pos, c) in num_text.char_indices() {
-        if condition(&c) || matches!(c, '_') {
-            continue;
+    position: usize,
+) -> Result<(), TokenStreamParseError> {
+    let mut depth = 1;
+    while depth > 0 {
+        if match_symbols(iter, &[Symbol::Slash, Symbol::Star]) {
+            depth += 1;
+        } else if match_symbols(iter, &[Symbol::Star, Symbol::Slash]) {
+            depth -= 1;
+        } else if iter.next().is_none() {
+            return Err(TokenStreamParseError::UnterminatedBlockComment { position });
         }
-
-        return Err(TokenStreamParseError::UnusableCharacters {
-            position: token_pos + pos,
-        });
     }
 
-    let src: String = num_text.chars().filter(condition).collect();
-    u64::from_str_radix(&src, radix).map_err(|_| {
-        TokenStreamParseError::CannotConvertTextToNumbers {
-            position: token_pos,
-        }
-    })
+    Ok(())
 }
 
 fn expect_token<'a, F>(
@@ -588,3 +1148,698 @@ fn skip_white_space<'a>(iter: &mut ParseIter<'a>) {
         .is_some()
     {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{keyword_locale_directive, tokenize_program, NagiIdentifier, NagiProgramTokenKind};
+    use crate::lexer::code::escape::symbol_char;
+    use crate::lexer::code::keywords::{KeywordLocale, NagiCodeKeyword};
+    use crate::lexer::code::syntax_tree::build_syntax_tree;
+    use crate::lexer::code::{operators::OPERATOR_PATTERN_MAP, symbols::SYMBOL_PATTERN_MAP};
+
+    // 記号と演算子の表の全ての項目の (文字列, 期待する接着結果)
+    fn pattern_table() -> Vec<(String, String)> {
+        let symbols = SYMBOL_PATTERN_MAP
+            .values()
+            .flatten()
+            .map(|(pattern, value)| {
+                (
+                    pattern,
+                    format!("{:?}", NagiProgramTokenKind::Symbol(value.clone())),
+                )
+            });
+        let operators = OPERATOR_PATTERN_MAP
+            .values()
+            .flatten()
+            .map(|(pattern, value)| {
+                (
+                    pattern,
+                    format!("{:?}", NagiProgramTokenKind::Operator(value.clone())),
+                )
+            });
+
+        symbols
+            .chain(operators)
+            .map(|(pattern, kind)| (pattern.iter().map(symbol_char).collect(), kind))
+            .collect()
+    }
+
+    fn lex(source_code: &str) -> Vec<String> {
+        try_lex(source_code).unwrap()
+    }
+
+    // エラーは位置を含むメッセージで返す
+    fn try_lex(source_code: &str) -> Result<Vec<String>, String> {
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        let mut program = tokenize_program(&token_list).map_err(|e| e.to_string())?;
+        let mut kinds = vec![];
+        while let Some(token) = program.next() {
+            kinds.push(format!("{:?}", token.token_kind));
+        }
+        Ok(kinds)
+    }
+
+    // 先頭から最も長く一致する項目を取っていく
+    fn longest_match(table: &[(String, String)], mut text: &str) -> Vec<String> {
+        let mut kinds = vec![];
+        while !text.is_empty() {
+            let (pattern, kind) = table
+                .iter()
+                .filter(|(pattern, _)| text.starts_with(pattern.as_str()))
+                .max_by_key(|(pattern, _)| pattern.len())
+                .unwrap();
+            kinds.push(kind.clone());
+            text = &text[pattern.len()..];
+        }
+        kinds
+    }
+
+    #[test]
+    fn table_has_no_duplicates() {
+        let table = pattern_table();
+        for (i, (pattern, _)) in table.iter().enumerate() {
+            assert!(
+                table[i + 1..].iter().all(|(other, _)| other != pattern),
+                "{pattern}"
+            );
+        }
+    }
+
+    // 全ての2つの組を空白を挟まずに並べ, 最長一致で切り分けられることを確かめる
+    #[test]
+    fn every_pair_uses_longest_match() {
+        let table = pattern_table();
+        for (pattern, kind) in &table {
+            assert_eq!(lex(pattern), std::slice::from_ref(kind), "{pattern}");
+        }
+
+        for (first, first_kind) in &table {
+            for (second, second_kind) in &table {
+                let joined = format!("{first}{second}");
+                // コメントと属性の開始は記号として扱わない
+                if joined.contains("//") || joined.contains("/*") || joined.contains("#[") {
+                    continue;
+                }
+
+                assert_eq!(
+                    lex(&joined),
+                    longest_match(&table, &joined),
+                    "{first} {second}"
+                );
+                assert_eq!(
+                    lex(&format!("{first} {second}")),
+                    [first_kind.clone(), second_kind.clone()],
+                    "{first} {second}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_patterns() {
+        let table = pattern_table();
+        let kind = |text: &str| {
+            table
+                .iter()
+                .find(|(pattern, _)| pattern == text)
+                .map(|(_, kind)| kind.clone())
+                .unwrap()
+        };
+
+        let cases: &[(&str, &[&str])] = &[
+            (".", &["."]),
+            ("..", &[".."]),
+            ("..=", &["..="]),
+            ("...", &["..", "."]),
+            ("..==", &["..=", "="]),
+            ("=", &["="]),
+            ("==", &["=="]),
+            ("=>", &["=>"]),
+            ("===", &["==", "="]),
+            ("==>", &["==", ">"]),
+            ("->", &["->"]),
+            ("-=>", &["-=", ">"]),
+            ("::", &["::"]),
+            (":::", &["::", ":"]),
+            ("<<=", &["<<="]),
+            (">>=", &[">>="]),
+            ("<=>", &["<=", ">"]),
+        ];
+        for (text, expected) in cases {
+            let expected: Vec<_> = expected.iter().map(|text| kind(text)).collect();
+            assert_eq!(lex(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn keyword_locale_directive_exact_form() {
+        fn directive(source_code: &str) -> Option<(KeywordLocale, &str)> {
+            let token_list = nagi_lexer::tokenize(source_code).unwrap();
+            keyword_locale_directive(&token_list)
+                .map(|(locale, range)| (locale, &source_code[range]))
+        }
+
+        assert_eq!(
+            directive("// keywords: ja\n"),
+            Some((KeywordLocale::Japanese, "ja"))
+        );
+        assert_eq!(
+            directive("// license\n//keywords:en  \nfn"),
+            Some((KeywordLocale::English, "en"))
+        );
+
+        // 書式が一致しないコメントは無視する
+        for source_code in [
+            "// key words: ja",
+            "// keywords: j a",
+            "// keywords: ja, en",
+            "// keywords: fr",
+            "// keywords:",
+            "// see keywords: ja",
+            "/// keywords: ja",
+            "/* keywords: ja */",
+        ] {
+            assert_eq!(directive(source_code), None, "{source_code}");
+        }
+    }
+
+    #[test]
+    fn contextual_keyword_uses_file_locale() {
+        let union = NagiIdentifier::Identifier("union".to_string());
+        let japanese_union = NagiIdentifier::Identifier("共用体".to_string());
+
+        assert_eq!(union.contextual_keyword(None), Some(NagiCodeKeyword::Union));
+        assert_eq!(japanese_union.contextual_keyword(None), None);
+        assert_eq!(
+            japanese_union.contextual_keyword(Some(KeywordLocale::Japanese)),
+            Some(NagiCodeKeyword::Union)
+        );
+        assert_eq!(
+            union.contextual_keyword(Some(KeywordLocale::Japanese)),
+            None
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let cases = [
+            (r#""say \"hi\"""#, r#"say "hi""#),
+            (r#""a\n\t\r\0\\\'\x41""#, "a\n\t\r\0\\'A"),
+            (r#""\u{3042}\u{1F600}\u{1_F6_00}""#, "あ😀😀"),
+            ("\"line1 \\\n  \t line2\"", "line1 line2"),
+            ("\"a\\\r\n b\"", "ab"),
+            ("\"改行\nも\r\nそのまま\"", "改行\nも\r\nそのまま"),
+        ];
+        for (source_code, value) in cases {
+            assert_eq!(
+                lex(source_code),
+                [format!(
+                    "Literal(String {{ value: {value:?}, span: 0..{} }})",
+                    source_code.len()
+                )],
+                "{source_code}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_strings() {
+        let cases = [
+            (r#""bad \q""#, "Unknown escape sequence: \\q. position: 5"),
+            (
+                r#""bad \xFF""#,
+                "\\x escape must be in the range [\\x00-\\x7F]. position: 5",
+            ),
+            (
+                r#""\x4""#,
+                "\\x escape must be followed by 2 hex digits. position: 1",
+            ),
+            (
+                r#""\u3042""#,
+                "\\u escape must be of the form \\u{1-6 hex digits}. position: 1",
+            ),
+            (
+                r#""\u{}""#,
+                "\\u escape must be of the form \\u{1-6 hex digits}. position: 1",
+            ),
+            (
+                r#""\u{1234567}""#,
+                "\\u escape must be of the form \\u{1-6 hex digits}. position: 1",
+            ),
+            (
+                r#""bad \u{110000}""#,
+                "\\u escape is not a valid unicode scalar value. position: 5",
+            ),
+            (
+                r#""\u{D800}""#,
+                "\\u escape is not a valid unicode scalar value. position: 1",
+            ),
+            (
+                "let x = 1;\nlet s = \"abc",
+                "Unterminated string literal starting at position 19",
+            ),
+            (
+                "\"a\\",
+                "Unterminated string literal starting at position 0",
+            ),
+            (
+                "\"a\\\"",
+                "Unterminated string literal starting at position 0",
+            ),
+        ];
+        for (source_code, message) in cases {
+            assert_eq!(
+                try_lex(source_code),
+                Err(message.to_string()),
+                "{source_code}"
+            );
+        }
+    }
+
+    #[test]
+    fn char_literals_and_labels() {
+        let cases = [
+            ("'a'", "Literal(Char { value: 'a', span: 0..3 })"),
+            ("'日'", "Literal(Char { value: '日', span: 0..5 })"),
+            ("' '", "Literal(Char { value: ' ', span: 0..3 })"),
+            ("'_'", "Literal(Char { value: '_', span: 0..3 })"),
+            ("'\\n'", "Literal(Char { value: '\\n', span: 0..4 })"),
+            ("'\\''", "Literal(Char { value: '\\'', span: 0..4 })"),
+            ("'\\x4F'", "Literal(Char { value: 'O', span: 0..6 })"),
+            ("'\\u{3042}'", "Literal(Char { value: 'あ', span: 0..10 })"),
+            ("'a", "Label(\"a\")"),
+            ("'x_1 ", "Label(\"x_1\")"),
+        ];
+        for (source_code, kind) in cases {
+            assert_eq!(lex(source_code), [kind], "{source_code}");
+        }
+
+        assert_eq!(
+            lex("'outer: loop { break 'outer; }"),
+            [
+                "Label(\"outer\")",
+                "Symbol(Colon)",
+                "Identifier(Keyword(Loop))",
+                "Symbol(LeftBrace)",
+                "Identifier(Keyword(Break))",
+                "Label(\"outer\")",
+                "Symbol(Semicolon)",
+                "Symbol(RightBrace)",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_chars() {
+        let cases = [
+            (
+                "'ab'",
+                "Char literal must contain exactly one character. position: 0",
+            ),
+            (
+                "'12'",
+                "Char literal must contain exactly one character. position: 0",
+            ),
+            ("''", "Empty char literal at position 0"),
+            ("'+", "Unterminated char literal starting at position 0"),
+        ];
+        for (source_code, message) in cases {
+            assert_eq!(
+                try_lex(source_code),
+                Err(message.to_string()),
+                "{source_code}"
+            );
+        }
+    }
+
+    // コメントは捨て, ドキュメントコメントのみトークンにする
+    #[test]
+    fn block_and_doc_comments() {
+        let cases: &[(&str, &[&str])] = &[
+            (
+                "a /* x */ b",
+                &[
+                    "Identifier(Identifier(\"a\"))",
+                    "Identifier(Identifier(\"b\"))",
+                ],
+            ),
+            (
+                "a /* x /* y */ z */ b",
+                &[
+                    "Identifier(Identifier(\"a\"))",
+                    "Identifier(Identifier(\"b\"))",
+                ],
+            ),
+            ("/**/c", &["Identifier(Identifier(\"c\"))"]),
+            (
+                "/// doc\nfn f",
+                &[
+                    "DocComment(Outer(\" doc\"))",
+                    "Identifier(Keyword(Fn))",
+                    "Identifier(Identifier(\"f\"))",
+                ],
+            ),
+            (
+                "//! inner\n//// not doc\nx",
+                &[
+                    "DocComment(Inner(\" inner\"))",
+                    "Identifier(Identifier(\"x\"))",
+                ],
+            ),
+            (
+                "a */ b",
+                &[
+                    "Identifier(Identifier(\"a\"))",
+                    "Operator(Mul)",
+                    "Operator(Div)",
+                    "Identifier(Identifier(\"b\"))",
+                ],
+            ),
+        ];
+        for (source_code, kinds) in cases {
+            assert_eq!(lex(source_code), *kinds, "{source_code}");
+
+            let token_list = nagi_lexer::tokenize(source_code).unwrap();
+            let tree = build_syntax_tree(source_code, &token_list, None).unwrap();
+            assert_eq!(tree.to_string(), *source_code);
+        }
+
+        assert_eq!(
+            try_lex("a /* open /* x */"),
+            Err("Unterminated block comment opened at position 2".to_string())
+        );
+    }
+
+    #[test]
+    fn raw_strings_and_bytes() {
+        let cases = [
+            (
+                r#"r"C:\path""#,
+                r#"Literal(RawString { value: "C:\\path", span: 0..10 })"#,
+            ),
+            (
+                r##"r#"a "q" b"#"##,
+                r#"Literal(RawString { value: "a \"q\" b", span: 0..12 })"#,
+            ),
+            (
+                r###"r##"x"#y"##"###,
+                r##"Literal(RawString { value: "x\"#y", span: 0..11 })"##,
+            ),
+            (
+                r#"b"ab\xFF\n""#,
+                "Literal(ByteString { value: [97, 98, 255, 10], span: 0..11 })",
+            ),
+            ("b'a'", "Literal(Byte { value: 97, span: 0..4 })"),
+            (r"b'\x80'", "Literal(Byte { value: 128, span: 0..7 })"),
+            // 引用符が続かない r, b は識別子
+            ("r", "Identifier(Identifier(\"r\"))"),
+            ("b", "Identifier(Identifier(\"b\"))"),
+        ];
+        for (source_code, kind) in cases {
+            assert_eq!(lex(source_code), [kind], "{source_code}");
+        }
+        assert_eq!(
+            lex("r #"),
+            ["Identifier(Identifier(\"r\"))", "Symbol(Pound)"]
+        );
+    }
+
+    #[test]
+    fn invalid_raw_strings_and_bytes() {
+        let cases = [
+            (
+                r##"r#"a""##,
+                "Raw string must be terminated with 1 '#', found 0. position: 4..5",
+            ),
+            (
+                r###"r##"a"#"###,
+                "Raw string must be terminated with 2 '#', found 1. position: 5..7",
+            ),
+            (
+                r###"r#"a"##"###,
+                "Raw string must be terminated with 1 '#', found 2. position: 4..7",
+            ),
+            (
+                r##"r#"a"#"b"##,
+                "Unterminated string literal starting at position 6",
+            ),
+            (
+                "b\"\u{3042}\"",
+                "Non-ASCII character in byte literal at position 2",
+            ),
+            (
+                r#"b"\u{41}""#,
+                "\\u escape cannot be used in byte literals. position: 2",
+            ),
+            (
+                "b'ab'",
+                "Char literal must contain exactly one character. position: 0",
+            ),
+            ("b''", "Empty char literal at position 0"),
+        ];
+        for (source_code, message) in cases {
+            assert_eq!(
+                try_lex(source_code),
+                Err(message.to_string()),
+                "{source_code}"
+            );
+        }
+    }
+
+    #[test]
+    fn number_literals() {
+        let integer = |value: &str, suffix: &str| {
+            format!("Literal(Integer {{ value: {value}, suffix: {suffix} }})")
+        };
+        let float = |value: &str, suffix: &str| {
+            format!("Literal(Float {{ value: {value:?}, suffix: {suffix} }})")
+        };
+        let cases = [
+            ("0", integer("0", "None")),
+            ("42", integer("42", "None")),
+            ("1_000", integer("1000", "None")),
+            ("0_1", integer("1", "None")),
+            ("0xFF", integer("255", "None")),
+            ("0xffu8", integer("255", "Some(U8)")),
+            ("0x1F_u16", integer("31", "Some(U16)")),
+            // 16進数では f32 も数字として読む
+            ("0xff_f32", integer("1048370", "None")),
+            ("0b1010", integer("10", "None")),
+            ("0o777", integer("511", "None")),
+            ("255u8", integer("255", "Some(U8)")),
+            // 符号は別のトークンなので -128i8 のために 128 を許す
+            ("128i8", integer("128", "Some(I8)")),
+            ("0u128", integer("0", "Some(U128)")),
+            (
+                "340282366920938463463374607431768211455",
+                integer("340282366920938463463374607431768211455", "None"),
+            ),
+            ("1e10", float("1e10", "None")),
+            ("2.5E-3", float("2.5E-3", "None")),
+            ("1.5e+3f32", float("1.5e+3", "Some(F32)")),
+            ("1.", float("1.", "None")),
+            ("0.5", float("0.5", "None")),
+            ("3.14_15", float("3.1415", "None")),
+            ("1f32", float("1", "Some(F32)")),
+            ("1f64", float("1", "Some(F64)")),
+        ];
+        for (source_code, kind) in cases {
+            assert_eq!(lex(source_code), [kind], "{source_code}");
+        }
+
+        // . の後に数字が続かない場合は整数で切る
+        assert_eq!(
+            lex("1.foo"),
+            [
+                integer("1", "None"),
+                "Operator(Dot)".to_string(),
+                "Identifier(Identifier(\"foo\"))".to_string(),
+            ]
+        );
+        assert_eq!(
+            lex("1..2"),
+            [
+                integer("1", "None"),
+                "Operator(Range)".to_string(),
+                integer("2", "None"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        let cases = [
+            (
+                "0b102",
+                "Invalid digit '2' for a base 2 literal. position: 4",
+            ),
+            ("0x", "Number literal has no digits. position: 2"),
+            ("0x_", "Number literal has no digits. position: 2"),
+            ("1e", "Number literal has no digits. position: 2"),
+            ("1e+", "Number literal has no digits. position: 2"),
+            (
+                "0b1f32",
+                "Float suffix can only be used on decimal literals. position: 0",
+            ),
+            (
+                "1.5u8",
+                "Integer suffix `u8` cannot be used on a float literal. position: 3",
+            ),
+            (
+                "1abc",
+                "Invalid suffix `abc` for number literal. position: 1",
+            ),
+            (
+                "256u8",
+                "Literal `256` is out of range for u8 (0..=255). position: 0",
+            ),
+            (
+                "129i8",
+                "Literal `129` is out of range for i8 (-128..=127). position: 0",
+            ),
+            (
+                "340282366920938463463374607431768211456",
+                "Literal `340282366920938463463374607431768211456` is out of range for u128 \
+                 (0..=340282366920938463463374607431768211455). position: 0",
+            ),
+            (
+                "1e39f32",
+                "Literal `1e39` is out of range for f32. position: 0",
+            ),
+            (
+                "1e400",
+                "Literal `1e400` is out of range for f64. position: 0",
+            ),
+        ];
+        for (source_code, message) in cases {
+            assert_eq!(
+                try_lex(source_code),
+                Err(message.to_string()),
+                "{source_code}"
+            );
+        }
+    }
+
+    // 型の文脈で > を1つずつ取り出し, 取り出せないトークンはそのまま読む
+    fn split_greater(source_code: &str) -> Vec<String> {
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        let mut program = tokenize_program(&token_list).unwrap();
+        let mut tokens = vec![];
+        while program.peek().is_some() {
+            if let Some(span) = program.eat_greater_than() {
+                tokens.push(format!(">@{span:?}"));
+            } else {
+                let token = program.next().unwrap();
+                tokens.push(format!("{:?}@{}", token.token_kind, token.position));
+            }
+        }
+        tokens
+    }
+
+    #[test]
+    fn eat_greater_than_splits_glued_operators() {
+        assert_eq!(
+            split_greater("Vec<Vec<i32>>"),
+            [
+                "Identifier(Identifier(\"Vec\"))@0",
+                "Operator(Less)@3",
+                "Identifier(Identifier(\"Vec\"))@4",
+                "Operator(Less)@7",
+                "Identifier(Identifier(\"i32\"))@8",
+                ">@11..12",
+                ">@12..13",
+            ]
+        );
+        assert_eq!(
+            split_greater("a<b<c>>= d"),
+            [
+                "Identifier(Identifier(\"a\"))@0",
+                "Operator(Less)@1",
+                "Identifier(Identifier(\"b\"))@2",
+                "Operator(Less)@3",
+                "Identifier(Identifier(\"c\"))@4",
+                ">@5..6",
+                ">@6..7",
+                "Operator(Assign)@7",
+                "Identifier(Identifier(\"d\"))@9",
+            ]
+        );
+        assert_eq!(
+            split_greater("x<y>=z"),
+            [
+                "Identifier(Identifier(\"x\"))@0",
+                "Operator(Less)@1",
+                "Identifier(Identifier(\"y\"))@2",
+                ">@3..4",
+                "Operator(Assign)@4",
+                "Identifier(Identifier(\"z\"))@5",
+            ]
+        );
+
+        // > で始まらない演算子はそのまま
+        let token_list = nagi_lexer::tokenize("<= x").unwrap();
+        let mut program = tokenize_program(&token_list).unwrap();
+        assert_eq!(program.eat_greater_than(), None);
+        assert_eq!(program.get_token_position(), 0);
+    }
+
+    #[test]
+    fn keywords_and_raw_identifiers() {
+        assert_eq!(
+            lex("true false as use mod trait self Self where unsafe union"),
+            [
+                "Identifier(Keyword(True))",
+                "Identifier(Keyword(False))",
+                "Identifier(Keyword(As))",
+                "Identifier(Keyword(Use))",
+                "Identifier(Keyword(Mod))",
+                "Identifier(Keyword(Trait))",
+                "Identifier(Keyword(SelfValue))",
+                "Identifier(Keyword(SelfType))",
+                // 文脈キーワードは識別子として切り分ける
+                "Identifier(Identifier(\"where\"))",
+                "Identifier(Keyword(Unsafe))",
+                "Identifier(Identifier(\"union\"))",
+            ]
+        );
+        assert_eq!(
+            lex("let r#match = r#fn;"),
+            [
+                "Identifier(Keyword(Let))",
+                "Identifier(Identifier(\"match\"))",
+                "Operator(Assign)",
+                "Identifier(Identifier(\"fn\"))",
+                "Symbol(Semicolon)",
+            ]
+        );
+
+        let cases = [
+            ("r#union", "Identifier(Identifier(\"union\"))"),
+            ("r#_x", "Identifier(Identifier(\"_x\"))"),
+            ("matchx", "Identifier(Identifier(\"matchx\"))"),
+            (
+                "r#\"s\"#",
+                "Literal(RawString { value: \"s\", span: 0..6 })",
+            ),
+        ];
+        for (source_code, kind) in cases {
+            assert_eq!(lex(source_code), [kind], "{source_code}");
+        }
+        assert_eq!(
+            lex("r # x"),
+            [
+                "Identifier(Identifier(\"r\"))",
+                "Symbol(Pound)",
+                "Identifier(Identifier(\"x\"))",
+            ]
+        );
+
+        for source_code in ["r#self", "r#Self", "r#_"] {
+            assert_eq!(
+                try_lex(source_code),
+                Err("`_`, `self` and `Self` cannot be raw identifiers. position: 0".to_string()),
+                "{source_code}"
+            );
+        }
+    }
+}
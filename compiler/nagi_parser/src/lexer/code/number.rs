@@ -0,0 +1,284 @@
+use super::escape::CharIter;
+use super::nagi_code_lexer::NagiLiteral;
+use crate::errors::{NumberError, TokenStreamParseError};
+use std::fmt::Display;
+use std::iter::from_fn;
+use std::str::FromStr;
+
+// 数値リテラルの解釈
+// 切り分け済みのトークンは 0xFF が 0 と xFF になるなど数値の区切りと一致しないので,
+// 位置付きの文字の列に戻してから処理する
+//
+// INTEGER_LITERAL ::= ( DEC_LITERAL | BIN_LITERAL | OCT_LITERAL | HEX_LITERAL ) SUFFIX?
+// FLOAT_LITERAL   ::= DEC_LITERAL "." ( DEC_LITERAL EXPONENT? SUFFIX? )?
+//                   | DEC_LITERAL EXPONENT SUFFIX?
+//                   | DEC_LITERAL FLOAT_SUFFIX
+// DEC_LITERAL     ::= DEC_DIGIT ( DEC_DIGIT | "_" )*
+// BIN_LITERAL     ::= "0b" ( BIN_DIGIT | "_" )* BIN_DIGIT ( BIN_DIGIT | "_" )*
+// OCT_LITERAL     ::= "0o" ( OCT_DIGIT | "_" )* OCT_DIGIT ( OCT_DIGIT | "_" )*
+// HEX_LITERAL     ::= "0x" ( HEX_DIGIT | "_" )* HEX_DIGIT ( HEX_DIGIT | "_" )*
+// EXPONENT        ::= ( "e" | "E" ) ( "+" | "-" )? "_"* DEC_DIGIT ( DEC_DIGIT | "_" )*
+// SUFFIX          ::= INTEGER_SUFFIX | FLOAT_SUFFIX
+// INTEGER_SUFFIX  ::= "i8" | "i16" | "i32" | "i64" | "i128" | "u8" | "u16" | "u32" | "u64" | "u128"
+// FLOAT_SUFFIX    ::= "f32" | "f64"
+// BIN_DIGIT       ::= [0-1]
+// OCT_DIGIT       ::= [0-7]
+// DEC_DIGIT       ::= [0-9]
+// HEX_DIGIT       ::= [0-9a-fA-F]
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NagiNumberSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    F32,
+    F64,
+}
+
+impl NagiNumberSuffix {
+    pub fn is_float(&self) -> bool {
+        matches!(self, NagiNumberSuffix::F32 | NagiNumberSuffix::F64)
+    }
+
+    /// 整数型の取り得る値の範囲
+    /// 符号付きの下限は絶対値で返す
+    pub fn integer_range(&self) -> Option<(u128, u128)> {
+        let bits = match self {
+            NagiNumberSuffix::I8 | NagiNumberSuffix::U8 => 8,
+            NagiNumberSuffix::I16 | NagiNumberSuffix::U16 => 16,
+            NagiNumberSuffix::I32 | NagiNumberSuffix::U32 => 32,
+            NagiNumberSuffix::I64 | NagiNumberSuffix::U64 => 64,
+            NagiNumberSuffix::I128 | NagiNumberSuffix::U128 => 128,
+            NagiNumberSuffix::F32 | NagiNumberSuffix::F64 => return None,
+        };
+
+        let range = match self {
+            NagiNumberSuffix::U128 => (0, u128::MAX),
+            NagiNumberSuffix::U8
+            | NagiNumberSuffix::U16
+            | NagiNumberSuffix::U32
+            | NagiNumberSuffix::U64 => (0, (1 << bits) - 1),
+            _ => (1 << (bits - 1), (1 << (bits - 1)) - 1),
+        };
+
+        Some(range)
+    }
+}
+
+impl FromStr for NagiNumberSuffix {
+    type Err = NumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let suffix = match s {
+            "i8" => NagiNumberSuffix::I8,
+            "i16" => NagiNumberSuffix::I16,
+            "i32" => NagiNumberSuffix::I32,
+            "i64" => NagiNumberSuffix::I64,
+            "i128" => NagiNumberSuffix::I128,
+            "u8" => NagiNumberSuffix::U8,
+            "u16" => NagiNumberSuffix::U16,
+            "u32" => NagiNumberSuffix::U32,
+            "u64" => NagiNumberSuffix::U64,
+            "u128" => NagiNumberSuffix::U128,
+            "f32" => NagiNumberSuffix::F32,
+            "f64" => NagiNumberSuffix::F64,
+            _ => return Err(NumberError::UnknownSuffix(s.to_string())),
+        };
+
+        Ok(suffix)
+    }
+}
+
+impl Display for NagiNumberSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            NagiNumberSuffix::I8 => "i8",
+            NagiNumberSuffix::I16 => "i16",
+            NagiNumberSuffix::I32 => "i32",
+            NagiNumberSuffix::I64 => "i64",
+            NagiNumberSuffix::I128 => "i128",
+            NagiNumberSuffix::U8 => "u8",
+            NagiNumberSuffix::U16 => "u16",
+            NagiNumberSuffix::U32 => "u32",
+            NagiNumberSuffix::U64 => "u64",
+            NagiNumberSuffix::U128 => "u128",
+            NagiNumberSuffix::F32 => "f32",
+            NagiNumberSuffix::F64 => "f64",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// 数値リテラル全体の文字の列を解釈する
+pub(crate) fn parse_number(chars: &[(usize, char)]) -> Result<NagiLiteral, TokenStreamParseError> {
+    let Some(&(position, _)) = chars.first() else {
+        return Err(TokenStreamParseError::UnexpectedEOF);
+    };
+    let end = chars.last().map_or(position, |(p, c)| p + c.len_utf8());
+    let mut iter = chars.iter().peekable();
+
+    let radix = match chars.get(..2) {
+        Some([(_, '0'), (_, 'b')]) => 2,
+        Some([(_, '0'), (_, 'o')]) => 8,
+        Some([(_, '0'), (_, 'x')]) => 16,
+        _ => 10,
+    };
+    if radix != 10 {
+        iter.nth(1); // 0b 0o 0x の消費
+    }
+
+    let mut text = eat_digits(&mut iter, radix, end)?;
+    let mut is_float = false;
+
+    if radix == 10 {
+        if iter.next_if(|(_, c)| *c == '.').is_some() {
+            is_float = true;
+            text.push('.');
+            // 1. のように小数部は省略できる
+            if iter.peek().is_some_and(|(_, c)| c.is_ascii_digit()) {
+                text += &eat_digits(&mut iter, radix, end)?;
+            }
+        }
+
+        if let Some((_, e)) = iter.next_if(|(_, c)| matches!(c, 'e' | 'E')) {
+            is_float = true;
+            text.push(*e);
+            if let Some((_, sign)) = iter.next_if(|(_, c)| matches!(c, '+' | '-')) {
+                text.push(*sign);
+            }
+            text += &eat_digits(&mut iter, radix, end)?;
+        }
+    }
+
+    let suffix_position = iter.peek().map(|(p, _)| *p);
+    let suffix_text: String = iter.map(|(_, c)| c).collect();
+    // 数字の途中から解釈できなくなった場合
+    if let Some(digit) = suffix_text.chars().next().filter(char::is_ascii_digit) {
+        return Err(number_error(
+            suffix_position.unwrap_or(position),
+            NumberError::InvalidDigit { digit, radix },
+        ));
+    }
+
+    let suffix = match (suffix_position, suffix_text.as_str()) {
+        (Some(suffix_position), suffix_text) => Some(
+            suffix_text
+                .parse::<NagiNumberSuffix>()
+                .map_err(|e| number_error(suffix_position, e))?,
+        ),
+        (None, _) => None,
+    };
+
+    // 数値部分の元の文字列
+    let literal: String = chars
+        .iter()
+        .take_while(|(p, _)| suffix_position.is_none_or(|s| *p < s))
+        .map(|(_, c)| c)
+        .collect();
+
+    match suffix {
+        Some(suffix) if suffix.is_float() => {
+            if radix != 10 {
+                return Err(number_error(position, NumberError::FloatSuffixOnNonDecimal));
+            }
+            make_float(text, Some(suffix), literal, position)
+        }
+        Some(suffix) if is_float => Err(number_error(
+            suffix_position.unwrap_or(position),
+            NumberError::IntegerSuffixOnFloat(suffix),
+        )),
+        _ if is_float => make_float(text, suffix, literal, position),
+        _ => make_integer(&text, radix, suffix, literal, position),
+    }
+}
+
+// 数字と_を集めて_を除いた文字列を返す
+// 数字が1つもなければエラー
+fn eat_digits(
+    iter: &mut CharIter,
+    radix: u32,
+    end: usize,
+) -> Result<String, TokenStreamParseError> {
+    let position = iter.peek().map_or(end, |(p, _)| *p);
+    let digits: String = from_fn(|| iter.next_if(|(_, c)| c.is_digit(radix) || *c == '_'))
+        .map(|(_, c)| *c)
+        .filter(|c| *c != '_')
+        .collect();
+
+    if digits.is_empty() {
+        return Err(number_error(position, NumberError::MissingDigits));
+    }
+
+    Ok(digits)
+}
+
+fn make_integer(
+    text: &str,
+    radix: u32,
+    suffix: Option<NagiNumberSuffix>,
+    literal: String,
+    position: usize,
+) -> Result<NagiLiteral, TokenStreamParseError> {
+    let out_of_range = |suffix| {
+        number_error(
+            position,
+            NumberError::OutOfRange {
+                literal: literal.clone(),
+                suffix,
+            },
+        )
+    };
+
+    let value =
+        u128::from_str_radix(text, radix).map_err(|_| out_of_range(NagiNumberSuffix::U128))?;
+
+    // 符号付きは -128i8 のように単項マイナスと組み合わせられるので, 下限の絶対値まで許す
+    // 符号の付かない場合の検査は構文解析で行う
+    if let Some(suffix) = suffix
+        && let Some((min, max)) = suffix.integer_range()
+        && value > max.max(min)
+    {
+        return Err(out_of_range(suffix));
+    }
+
+    Ok(NagiLiteral::Integer { value, suffix })
+}
+
+fn make_float(
+    text: String,
+    suffix: Option<NagiNumberSuffix>,
+    literal: String,
+    position: usize,
+) -> Result<NagiLiteral, TokenStreamParseError> {
+    let is_finite = match suffix {
+        Some(NagiNumberSuffix::F32) => text.parse::<f32>().is_ok_and(f32::is_finite),
+        _ => text.parse::<f64>().is_ok_and(f64::is_finite),
+    };
+
+    if !is_finite {
+        return Err(number_error(
+            position,
+            NumberError::OutOfRange {
+                literal,
+                suffix: suffix.unwrap_or(NagiNumberSuffix::F64),
+            },
+        ));
+    }
+
+    Ok(NagiLiteral::Float {
+        value: text,
+        suffix,
+    })
+}
+
+fn number_error(position: usize, error: NumberError) -> TokenStreamParseError {
+    TokenStreamParseError::InvalidNumber { position, error }
+}
@@ -75,6 +75,12 @@ pub(crate) static OPERATOR_PATTERN_MAP: LazyLock<PatternHashMap<Symbol, NagiOper
             //
             (vec![Symbol::Question], NagiOperator::Question),
             (vec![Symbol::Dot], NagiOperator::Dot),
+            // 範囲
+            (vec![Symbol::Dot, Symbol::Dot], NagiOperator::Range),
+            (
+                vec![Symbol::Dot, Symbol::Dot, Symbol::Equal],
+                NagiOperator::RangeInclusive,
+            ),
         ];
 
         make_pattern_map(list)
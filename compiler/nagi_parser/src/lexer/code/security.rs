@@ -0,0 +1,213 @@
+use super::keywords::KeywordLocale;
+use super::nagi_code_lexer::{
+    tokenize_program_with_keywords, NagiIdentifier, NagiLiteral, NagiProgramTokenKind, NagiSymbol,
+};
+use crate::errors::TokenStreamParseError;
+use nagi_ast::{Attribute, AttributeArg, AttributeArgs, AttributeStyle};
+use nagi_lexer::security::{
+    AllowedCharacters, SourceRegion, SourceRegionKind, ALLOW_BIDI_CHARS, ALLOW_INVISIBLE_CHARS,
+};
+use nagi_lexer::token::Token;
+
+// 不審な文字の検査 (nagi_lexer::security) に渡す範囲を, コードの字句解析の結果から作る
+// 文字列やコメントの判定を字句解析と同じにするため, 検査側では文字列の状態を持たない
+//
+// #[allow(bidi_chars)] の許可の範囲は属性の直後の文, または波括弧のブロックの終わりまで
+// #![allow(...)] は囲んでいるブロックの終わりまで (ファイルの先頭ならファイル全体)
+
+#[derive(Debug)]
+struct Allowance {
+    allowed: AllowedCharacters,
+    depth: usize, // 属性が書かれた位置の波括弧の深さ
+    style: AttributeStyle,
+}
+
+/// 識別子とリテラルの範囲を位置の順に返す
+pub fn security_regions(
+    token_list: &[Token],
+    default_locale: Option<KeywordLocale>,
+) -> Result<Vec<SourceRegion>, TokenStreamParseError> {
+    let mut program = tokenize_program_with_keywords(token_list, default_locale)?;
+    let mut regions = vec![];
+    let mut allowances: Vec<Allowance> = vec![];
+    let mut depth = 0;
+
+    while let Some(token) = program.next() {
+        let span = token.position..token.end;
+        match &token.token_kind {
+            NagiProgramTokenKind::Identifier(NagiIdentifier::Identifier(_))
+            | NagiProgramTokenKind::Label(_) => regions.push(SourceRegion {
+                kind: SourceRegionKind::Identifier,
+                span,
+            }),
+            NagiProgramTokenKind::Literal(
+                NagiLiteral::String { .. }
+                | NagiLiteral::RawString { .. }
+                | NagiLiteral::Char { .. }
+                | NagiLiteral::ByteString { .. }
+                | NagiLiteral::Byte { .. },
+            ) => {
+                let allowed = allowances
+                    .iter()
+                    .fold(AllowedCharacters::default(), |a, b| AllowedCharacters {
+                        bidi_chars: a.bidi_chars || b.allowed.bidi_chars,
+                        invisible_chars: a.invisible_chars || b.allowed.invisible_chars,
+                    });
+                regions.push(SourceRegion {
+                    kind: SourceRegionKind::Literal(allowed),
+                    span,
+                });
+            }
+            NagiProgramTokenKind::Attribute(attribute) => {
+                if let Some(allowed) = allowed_characters(attribute) {
+                    allowances.push(Allowance {
+                        allowed,
+                        depth,
+                        style: attribute.style.clone(),
+                    });
+                }
+            }
+            NagiProgramTokenKind::Symbol(NagiSymbol::LeftBrace) => depth += 1,
+            NagiProgramTokenKind::Symbol(NagiSymbol::RightBrace) => {
+                depth = depth.saturating_sub(1);
+                allowances.retain(|a| match a.style {
+                    AttributeStyle::Outer => depth > a.depth,
+                    AttributeStyle::Inner => depth >= a.depth,
+                });
+            }
+            NagiProgramTokenKind::Symbol(NagiSymbol::Semicolon) => {
+                allowances.retain(|a| a.style == AttributeStyle::Inner || depth > a.depth);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(regions)
+}
+
+// #[allow(bidi_chars, invisible_chars)] であれば許可する文字を返す
+fn allowed_characters(attribute: &Attribute) -> Option<AllowedCharacters> {
+    let [segment] = attribute.path.segments.as_slice() else {
+        return None;
+    };
+    let AttributeArgs::List(args) = &attribute.args else {
+        return None;
+    };
+    if segment.ident != "allow" {
+        return None;
+    }
+
+    let mut allowed = AllowedCharacters::default();
+    for arg in args {
+        let AttributeArg::Path(path) = arg else {
+            continue;
+        };
+        let [lint] = path.segments.as_slice() else {
+            continue;
+        };
+        match lint.ident.as_str() {
+            ALLOW_BIDI_CHARS => allowed.bidi_chars = true,
+            ALLOW_INVISIBLE_CHARS => allowed.invisible_chars = true,
+            _ => {}
+        }
+    }
+
+    Some(allowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::security_regions;
+    use nagi_lexer::security::{
+        scan_suspicious_characters, SuspiciousCharacter, SuspiciousCharacterKind,
+    };
+
+    fn scan(source_code: &str) -> Vec<SuspiciousCharacter> {
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        let regions = security_regions(&token_list, None).unwrap();
+        scan_suspicious_characters(source_code, &regions)
+    }
+
+    fn kinds(list: &[SuspiciousCharacter]) -> Vec<SuspiciousCharacterKind> {
+        list.iter().map(|s| s.kind.clone()).collect()
+    }
+
+    // 引用符を含むリテラルやコメントの後も, コードの中の文字は許可しない
+    #[test]
+    fn literal_kinds_do_not_leak_string_state() {
+        let literals = [
+            "\"\\\"\"",
+            "'\"'",
+            "'\\''",
+            "r#\"a\"b\"#",
+            "b\"\\\"\"",
+            "b'\"'",
+            "/* \" */ 0",
+            "/* /* \" */ */ 0",
+            "// \"\n0",
+            "/// \"\n0",
+        ];
+
+        for literal in literals {
+            let source_code = format!(
+                "#[allow(bidi_chars)]\nfn f() {{ {literal}; let x\u{202E} = 1; let pаss = 2; }}"
+            );
+            let list = scan(&source_code);
+            assert_eq!(
+                kinds(&list),
+                [
+                    SuspiciousCharacterKind::BidiControl,
+                    SuspiciousCharacterKind::Confusable { similar: 'a' },
+                ],
+                "{literal}"
+            );
+            assert_eq!(&source_code[list[0].span.clone()], "\u{202E}");
+        }
+    }
+
+    #[test]
+    fn allowed_literals() {
+        let source_code = "#[allow(bidi_chars)]\nlet s = \"\u{202E}\";\nlet t = \"\u{202E}\";";
+        let list = scan(source_code);
+        assert_eq!(kinds(&list), [SuspiciousCharacterKind::BidiControl]);
+        assert_eq!(list[0].span.start, source_code.rfind('\u{202E}').unwrap());
+
+        for literal in ["\"\u{2066}\"", "r#\"\u{2066}\"#", "'\u{2066}'"] {
+            let source_code = format!("#[allow(bidi_chars)]\nfn f() {{ let s = {literal}; }}");
+            assert!(scan(&source_code).is_empty(), "{literal}");
+        }
+
+        // 空白を含む書き方
+        let source_code =
+            "#[ allow ( invisible_chars , bidi_chars ) ]\nlet s = \"\u{202E}\u{200B}\";";
+        assert!(scan(source_code).is_empty());
+
+        // 許可していない種類とコメントの中は報告する
+        let source_code = "#[allow(bidi_chars)]\nlet s = \"\u{200B}\"; // \u{202E}";
+        assert_eq!(
+            kinds(&scan(source_code)),
+            [
+                SuspiciousCharacterKind::Invisible,
+                SuspiciousCharacterKind::BidiControl,
+            ]
+        );
+
+        // 内部属性はファイル全体
+        let source_code = "#![allow(bidi_chars)]\nlet s = \"\u{202E}\";\nfn f() { \"\u{202E}\"; }";
+        assert!(scan(source_code).is_empty());
+    }
+
+    #[test]
+    fn confusable_identifiers() {
+        // ラテン文字を含まない識別子とコメントは調べない
+        assert!(scan("let раз = 1; // pаss").is_empty());
+        assert_eq!(
+            kinds(&scan("let 'lооp = r#pаss;")),
+            [
+                SuspiciousCharacterKind::Confusable { similar: 'o' },
+                SuspiciousCharacterKind::Confusable { similar: 'o' },
+                SuspiciousCharacterKind::Confusable { similar: 'a' },
+            ]
+        );
+    }
+}
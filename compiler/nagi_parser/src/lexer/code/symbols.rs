@@ -14,6 +14,15 @@ pub(crate) static SYMBOL_PATTERN_MAP: LazyLock<PatternHashMap<Symbol, NagiSymbol
             (vec![Symbol::RightParenthesis], NagiSymbol::RightParenthesis),
             (vec![Symbol::Semicolon], NagiSymbol::Semicolon),
             (vec![Symbol::Comma], NagiSymbol::Comma),
+            (vec![Symbol::Colon], NagiSymbol::Colon),
+            (vec![Symbol::Colon, Symbol::Colon], NagiSymbol::DoubleColon),
+            (vec![Symbol::Minus, Symbol::GreaterThan], NagiSymbol::Arrow),
+            (
+                vec![Symbol::Equal, Symbol::GreaterThan],
+                NagiSymbol::FatArrow,
+            ),
+            (vec![Symbol::Pound], NagiSymbol::Pound),
+            (vec![Symbol::At], NagiSymbol::At),
         ];
 
         make_pattern_map(list)
@@ -0,0 +1,274 @@
+use super::keywords::KeywordLocale;
+use super::nagi_code_lexer::{
+    glue_comment, glue_program_token, keyword_locale_directive, NagiCommentKind, NagiIdentifier,
+    NagiProgramTokenKind, ParseIter,
+};
+use crate::errors::TokenStreamParseError;
+use green::{GreenElement, GreenNode, GreenToken, GreenTrivia};
+use nagi_lexer::token::{Token, TokenKind};
+use red::SyntaxNode;
+use std::ops::Range;
+use std::rc::Rc;
+
+pub(crate) mod green;
+pub(crate) mod red;
+
+// ロスレスな具象構文木(CST)
+// rowanと同様にgreen tree(不変・位置を持たない)とred tree(親と位置を持つ)の2層構造
+// 空白やコメントはトリビアとしてトークンに付随させ,
+// 木を出力すると元のソースコードをバイト単位で再現できる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // ノード
+    Program,
+
+    // トークン
+    Identifier,
+    Keyword,
+    Literal,
+    Operator,
+    Symbol,
+    Label,
+    DocComment,
+    Attribute,
+    EndOfFile, // ファイル末尾のトリビアを保持するためのトークン
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriviaKind {
+    WhiteSpace,
+    LineBreak,
+    LineComment,
+    BlockComment,
+}
+
+impl From<&NagiProgramTokenKind> for SyntaxKind {
+    fn from(value: &NagiProgramTokenKind) -> Self {
+        match value {
+            NagiProgramTokenKind::Identifier(NagiIdentifier::Identifier(_)) => {
+                SyntaxKind::Identifier
+            }
+            NagiProgramTokenKind::Identifier(NagiIdentifier::Keyword(_)) => SyntaxKind::Keyword,
+            NagiProgramTokenKind::Literal(_) => SyntaxKind::Literal,
+            NagiProgramTokenKind::Operator(_) => SyntaxKind::Operator,
+            NagiProgramTokenKind::Symbol(_) => SyntaxKind::Symbol,
+            NagiProgramTokenKind::Label(_) => SyntaxKind::Label,
+            NagiProgramTokenKind::DocComment(_) => SyntaxKind::DocComment,
+            NagiProgramTokenKind::Attribute(_) => SyntaxKind::Attribute,
+        }
+    }
+}
+
+/// ソースコード全体からロスレスな構文木を作成する
+///
+/// トークンは同じ行にある後続のトリビアを末尾トリビアとして持ち,
+/// 改行以降のトリビアは次のトークンの先頭トリビアになる
+pub fn build_syntax_tree(
+    source_code: &str,
+    token_list: &[Token],
+    default_locale: Option<KeywordLocale>,
+) -> Result<SyntaxNode, TokenStreamParseError> {
+    let locale = keyword_locale_directive(token_list)
+        .map(|(locale, _)| locale)
+        .or(default_locale);
+
+    let mut iter = token_list.iter().peekable();
+    let mut children = vec![];
+
+    loop {
+        let leading_trivia = eat_trivia(source_code, &mut iter, false)?;
+
+        let Some(token) = iter.peek() else {
+            children.push(GreenElement::Token(Rc::new(GreenToken::new(
+                SyntaxKind::EndOfFile,
+                leading_trivia,
+                String::new(),
+                vec![],
+            ))));
+            break;
+        };
+
+        let start = token.token_pos;
+        let token_kind = glue_program_token(&mut iter, locale)?;
+        let end = current_position(source_code, &mut iter);
+        let trailing_trivia = eat_trivia(source_code, &mut iter, true)?;
+
+        children.push(GreenElement::Token(Rc::new(GreenToken::new(
+            SyntaxKind::from(&token_kind),
+            leading_trivia,
+            source_code[start..end].to_string(),
+            trailing_trivia,
+        ))));
+    }
+
+    let root = GreenNode::new(SyntaxKind::Program, children);
+
+    Ok(SyntaxNode::new_root(Rc::new(root)))
+}
+
+/// リテラルとコメントの範囲を位置の順に返す
+/// 空白の検査などで, 中身の値を変えないように除く範囲
+/// 行コメントの末尾の空白は範囲に含めない
+pub fn literal_and_comment_ranges(tree: &SyntaxNode) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut push_comments = |trivia_list: &[GreenTrivia], mut position: usize| {
+        for trivia in trivia_list {
+            match trivia.kind() {
+                TriviaKind::LineComment => {
+                    ranges.push(position..position + trivia.text().trim_end().len())
+                }
+                TriviaKind::BlockComment => ranges.push(position..position + trivia.text().len()),
+                _ => {}
+            }
+            position += trivia.text().len();
+        }
+    };
+
+    let mut token_ranges = vec![];
+    for token in tree.tokens() {
+        let text_range = token.text_range();
+        push_comments(token.leading_trivia(), token.full_range().start);
+        match token.kind() {
+            SyntaxKind::Literal => token_ranges.push(text_range.clone()),
+            SyntaxKind::DocComment if token.text().starts_with("//") => token_ranges
+                .push(text_range.start..text_range.start + token.text().trim_end().len()),
+            SyntaxKind::DocComment => token_ranges.push(text_range.clone()),
+            _ => {}
+        }
+        push_comments(token.trailing_trivia(), text_range.end);
+    }
+
+    ranges.extend(token_ranges);
+    ranges.sort_by_key(|range| range.start);
+    ranges
+}
+
+/// 空白, 改行, コメントをトリビアとして切り出す
+/// same_lineがtrueの場合は改行の手前で止める
+fn eat_trivia<'a>(
+    source_code: &str,
+    iter: &mut ParseIter<'a>,
+    same_line: bool,
+) -> Result<Vec<GreenTrivia>, TokenStreamParseError> {
+    let mut trivia_list = vec![];
+
+    while let Some(&token) = iter.peek() {
+        let start = token.token_pos;
+
+        let kind = match token.token_kind {
+            TokenKind::WhiteSpace(_) => {
+                iter.next();
+                TriviaKind::WhiteSpace
+            }
+            TokenKind::LineBreak(_) if !same_line => {
+                iter.next();
+                TriviaKind::LineBreak
+            }
+            TokenKind::Symbol(_) => match glue_comment(iter)? {
+                Some(NagiCommentKind::Line) => TriviaKind::LineComment,
+                Some(NagiCommentKind::Block) => TriviaKind::BlockComment,
+                None => break,
+            },
+            _ => break,
+        };
+
+        let end = current_position(source_code, iter);
+        trivia_list.push(GreenTrivia::new(kind, source_code[start..end].to_string()));
+    }
+
+    Ok(trivia_list)
+}
+
+fn current_position<'a>(source_code: &str, iter: &mut ParseIter<'a>) -> usize {
+    iter.peek()
+        .map(|t| t.token_pos)
+        .unwrap_or(source_code.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_syntax_tree, literal_and_comment_ranges};
+    use crate::test_util::Rng;
+    use nagi_lexer::whitespace::{apply_fixes, check_whitespace, WhitespaceLintConfig};
+
+    // 断片をランダムに連結したソースコードで, 木の出力が元のソースコードと一致することを確かめる
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::new(0x1234_5678_9abc_def1);
+        let mut checked = 0;
+        for _ in 0..5000 {
+            let source_code = rng.source_code(30);
+
+            let Ok(token_list) = nagi_lexer::tokenize(&source_code) else {
+                continue;
+            };
+            let Ok(tree) = build_syntax_tree(&source_code, &token_list, None) else {
+                continue;
+            };
+
+            assert_eq!(tree.to_string(), source_code);
+            for token in tree.tokens() {
+                assert_eq!(&source_code[token.text_range()], token.text());
+            }
+            checked += 1;
+        }
+        assert!(checked > 1000, "only {checked} sources were checked");
+    }
+
+    #[test]
+    fn literal_and_comment_ranges_cover_contents() {
+        let source_code =
+            "let s = \"a  \n  b\"; // c  \nlet r = r#\"\"x  \n\"#;\n/* d  \n */ 1.5\n/// e  \nf";
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        let tree = build_syntax_tree(source_code, &token_list, None).unwrap();
+        let texts: Vec<_> = literal_and_comment_ranges(&tree)
+            .into_iter()
+            .map(|range| &source_code[range])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "\"a  \n  b\"",
+                "// c",
+                "r#\"\"x  \n\"#",
+                "/* d  \n */",
+                "1.5",
+                "/// e",
+            ]
+        );
+    }
+
+    // 空白の修正でリテラルの値が変わらない
+    #[test]
+    fn whitespace_fix_keeps_literals() {
+        let source_code = "fn f() {\n\tlet s = \"a  \n    b\r\n\";  \n\tlet r = r\"\n  c \";\n}\n";
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        let tree = build_syntax_tree(source_code, &token_list, None).unwrap();
+        let skip_ranges = literal_and_comment_ranges(&tree);
+        let diagnostics =
+            check_whitespace(&token_list, &WhitespaceLintConfig::default(), &skip_ranges);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            apply_fixes(source_code, &diagnostics),
+            "fn f() {\n\tlet s = \"a  \n    b\r\n\";\n\tlet r = r\"\n  c \";\n}\n"
+        );
+    }
+
+    // 行コメントの後ろの空白は報告して修正する
+    #[test]
+    fn whitespace_after_line_comment() {
+        let source_code = "let x = 1; // note   \n/// doc \t\nfn f() {} /* a  \n */  \n";
+        let token_list = nagi_lexer::tokenize(source_code).unwrap();
+        let tree = build_syntax_tree(source_code, &token_list, None).unwrap();
+        let skip_ranges = literal_and_comment_ranges(&tree);
+        let diagnostics =
+            check_whitespace(&token_list, &WhitespaceLintConfig::default(), &skip_ranges);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            apply_fixes(source_code, &diagnostics),
+            "let x = 1; // note\n/// doc\nfn f() {} /* a  \n */\n"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use super::{SyntaxKind, TriviaKind};
+use std::{fmt::Display, rc::Rc};
+
+// green tree
+// 位置や親の情報を持たない不変のツリー
+// 同じ形の部分木はRcで共有できる
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    text_len: usize, // トリビアを含む子要素全体の長さ
+    children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    leading_trivia: Vec<GreenTrivia>,
+    text: String,
+    trailing_trivia: Vec<GreenTrivia>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenTrivia {
+    kind: TriviaKind,
+    text: String,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let text_len = children.iter().map(|child| child.text_len()).sum();
+        Self {
+            kind,
+            text_len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind(),
+            GreenElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len(),
+            GreenElement::Token(token) => token.full_len(),
+        }
+    }
+}
+
+impl GreenToken {
+    pub fn new(
+        kind: SyntaxKind,
+        leading_trivia: Vec<GreenTrivia>,
+        text: String,
+        trailing_trivia: Vec<GreenTrivia>,
+    ) -> Self {
+        Self {
+            kind,
+            leading_trivia,
+            text,
+            trailing_trivia,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn leading_trivia(&self) -> &[GreenTrivia] {
+        &self.leading_trivia
+    }
+
+    pub fn trailing_trivia(&self) -> &[GreenTrivia] {
+        &self.trailing_trivia
+    }
+
+    pub fn leading_len(&self) -> usize {
+        self.leading_trivia.iter().map(|t| t.text.len()).sum()
+    }
+
+    pub fn trailing_len(&self) -> usize {
+        self.trailing_trivia.iter().map(|t| t.text.len()).sum()
+    }
+
+    // トリビアを含む長さ
+    pub fn full_len(&self) -> usize {
+        self.leading_len() + self.text.len() + self.trailing_len()
+    }
+}
+
+impl GreenTrivia {
+    pub fn new(kind: TriviaKind, text: String) -> Self {
+        Self { kind, text }
+    }
+
+    pub fn kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Display for GreenNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.children.iter().try_for_each(|child| child.fmt(f))
+    }
+}
+
+impl Display for GreenElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GreenElement::Node(node) => node.fmt(f),
+            GreenElement::Token(token) => token.fmt(f),
+        }
+    }
+}
+
+impl Display for GreenToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.leading_trivia.iter().try_for_each(|t| t.fmt(f))?;
+        write!(f, "{}", self.text)?;
+        self.trailing_trivia.iter().try_for_each(|t| t.fmt(f))
+    }
+}
+
+impl Display for GreenTrivia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
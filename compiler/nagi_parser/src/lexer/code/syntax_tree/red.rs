@@ -0,0 +1,151 @@
+use super::{
+    green::{GreenElement, GreenNode, GreenToken, GreenTrivia},
+    SyntaxKind,
+};
+use std::{fmt::Display, ops::Range, rc::Rc};
+
+// red tree
+// green treeに親と絶対位置の情報を付与したビュー
+// 必要になったときにだけ作成されるので使い捨てにしてよい
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<SyntaxNodeData>);
+
+#[derive(Debug)]
+struct SyntaxNodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize, // 先頭トリビアを含めた開始位置
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize, // 先頭トリビアを含めた開始位置
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(SyntaxNodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &GreenNode {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    // トリビアを含む範囲
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.text_len()
+    }
+
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children()
+            .iter()
+            .map(|child| {
+                let child_offset = offset;
+                offset += child.text_len();
+                match child {
+                    GreenElement::Node(node) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(SyntaxNodeData {
+                            green: node.clone(),
+                            parent: Some(self.clone()),
+                            offset: child_offset,
+                        })))
+                    }
+                    GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+                        green: token.clone(),
+                        parent: self.clone(),
+                        offset: child_offset,
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// 部分木に含まれるトークンを出現順に返す
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        self.children()
+            .into_iter()
+            .flat_map(|child| match child {
+                SyntaxElement::Node(node) => node.tokens(),
+                SyntaxElement::Token(token) => vec![token],
+            })
+            .collect()
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    pub fn leading_trivia(&self) -> &[GreenTrivia] {
+        self.green.leading_trivia()
+    }
+
+    pub fn trailing_trivia(&self) -> &[GreenTrivia] {
+        self.green.trailing_trivia()
+    }
+
+    // トリビアを含まない範囲
+    pub fn text_range(&self) -> Range<usize> {
+        let start = self.offset + self.green.leading_len();
+        start..start + self.green.text().len()
+    }
+
+    // トリビアを含む範囲
+    pub fn full_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.full_len()
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.green.fmt(f)
+    }
+}
+
+impl Display for SyntaxElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyntaxElement::Node(node) => node.fmt(f),
+            SyntaxElement::Token(token) => token.fmt(f),
+        }
+    }
+}
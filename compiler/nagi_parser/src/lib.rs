@@ -0,0 +1,7 @@
+pub mod ebnf;
+pub mod ebnf_parser;
+pub mod errors;
+pub mod lexer;
+
+#[cfg(test)]
+mod test_util;
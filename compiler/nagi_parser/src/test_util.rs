@@ -0,0 +1,125 @@
+// 乱択テストで共有する疑似乱数とソースコードの断片
+
+/// 固定のシードから同じ列を再現できる疑似乱数 (xorshift64)
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// 0..len の範囲の添字
+    pub(crate) fn index(&mut self, len: usize) -> usize {
+        (self.next() % len as u64) as usize
+    }
+
+    /// FRAGMENTSから0個以上max_fragments個以下を選んで連結する
+    pub(crate) fn source_code(&mut self, max_fragments: u64) -> String {
+        let len = self.next() % (max_fragments + 1);
+        (0..len)
+            .map(|_| FRAGMENTS[self.index(FRAGMENTS.len())])
+            .collect()
+    }
+}
+
+/// 切り分けの境界になりやすいトークン, トリビア, 不正な文字の断片
+pub(crate) const FRAGMENTS: &[&str] = &[
+    "fn",
+    "let",
+    "abc",
+    "x_1",
+    "日本",
+    "関数",
+    "もし",
+    "0",
+    "12",
+    "0x1f",
+    "0b10",
+    "1e",
+    "e",
+    "3.5",
+    "1.",
+    ".5",
+    "..",
+    "..=",
+    "_",
+    "__",
+    "r",
+    "r#",
+    "b",
+    "'",
+    "\"",
+    "\\",
+    "#",
+    "!",
+    "[",
+    "]",
+    "#[test]",
+    "#![cfg(x = \"y\")]",
+    "#[a(b, c = 1, d(e))]",
+    "+",
+    "-",
+    "==",
+    "<<=",
+    ">>",
+    ">=",
+    "=>",
+    "->",
+    "::",
+    "&&",
+    "|=",
+    "?",
+    "@",
+    "`",
+    "(",
+    ")",
+    "{",
+    "}",
+    ";",
+    ",",
+    " ",
+    "\t",
+    "\n",
+    "\r\n",
+    "// comment\n",
+    "//x",
+    "// keywords: ja\n",
+    "/* b /* n */ */",
+    "/// d\n",
+    "//! i\n",
+    "////x\n",
+    "/*",
+    "*/",
+    "\"str ing\"",
+    "\"a\\n\"",
+    "'a'",
+    "'ab'",
+    "'\\n'",
+    "'x",
+    "b'a'",
+    "b\"x\"",
+    "r#\"x\"#",
+    "r##\"a\"#b\"##",
+    "1_000",
+    "2E+5",
+    "1e-3",
+    "5u8",
+    "self",
+    "union",
+    "'lbl",
+    "\u{200B}",
+    "\u{3000}",
+    "\u{000C}",
+    "💥",
+    ".",
+    "  ",
+    "'c'",
+    "r#\"raw\"#",
+];
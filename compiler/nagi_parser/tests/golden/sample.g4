@@ -0,0 +1,16 @@
+grammar Sample;
+
+tokens { IDENT }
+
+program : funcDecl+ ;
+funcDecl : 'fn' IDENT '(' params? ')' block ;
+params : IDENT ((',' IDENT) ((',' IDENT) (',' IDENT)?)?)? ;
+block : '{' stmt stmt+ '}' | '{' stmt (stmt stmt?)? '}' | '{' stmt stmt '}' ;
+// exception dropped: Expr - "x"
+stmt : ('let' IDENT '=' expr ';' | expr ';' | 'say "it\'s"' | '\t' | ';')? ;
+expr : sign NUMBER | IDENT ;
+sign : ('+' | '-')? ;
+empty : () ;
+NUMBER : DIGIT+ ('.' DIGIT+)? ;
+fragment DIGIT : [0-46-9] | '5' ;
+fragment LETTER : ~[\-/-9\]a-z] ;